@@ -2,6 +2,20 @@ use std::env;
 use std::path::PathBuf;
 use std::process::Command;
 
+const SHADERS: [(&str, &str); 8] = [
+    ("shaders/shader.vert", "vert.spv"),
+    ("shaders/shader.frag", "frag.spv"),
+    ("shaders/vertex_anim.comp", "vertex_anim.spv"),
+    ("shaders/particles.comp", "particles.spv"),
+    ("shaders/particle.vert", "particle_vert.spv"),
+    ("shaders/particle.frag", "particle_frag.spv"),
+    ("shaders/post.vert", "post_vert.spv"),
+    ("shaders/post.frag", "post_frag.spv"),
+];
+
+/// Files pulled in with `#include` by the shaders above.
+const SHADER_INCLUDES: [&str; 1] = ["shaders/encoding.glsl"];
+
 fn main() {
     println!("cargo:rustc-link-lib=vulkan");
     println!("cargo:rustc-link-lib=glfw");
@@ -19,17 +33,24 @@ fn main() {
         .write_to_file(out_path.join("glfw_vk_bindings.rs"))
         .expect("Couldn't write glfw bindings!");
 
-    let _vert_shader_out = Command::new("glslc")
-        .arg("shaders/shader.vert")
-        .arg("-o")
-        .arg(out_path.join("vert.spv"))
-        .output()
-        .expect("Should be able to compile shader.vert!");
-
-    let _frag_shader_out = Command::new("glslc")
-        .arg("shaders/shader.frag")
-        .arg("-o")
-        .arg(out_path.join("frag.spv"))
-        .output()
-        .expect("Should be able to compile shader.frag!");
+    for include in SHADER_INCLUDES {
+        println!("cargo:rerun-if-changed={}", include);
+    }
+
+    for (shader, output) in SHADERS {
+        println!("cargo:rerun-if-changed={}", shader);
+        let shader_out = Command::new("glslc")
+            .arg(shader)
+            .arg("-o")
+            .arg(out_path.join(output))
+            .output()
+            .unwrap_or_else(|_| panic!("Should be able to run glslc for {}!", shader));
+        if !shader_out.status.success() {
+            panic!(
+                "Failed to compile {}:\n{}",
+                shader,
+                String::from_utf8_lossy(&shader_out.stderr)
+            );
+        }
+    }
 }
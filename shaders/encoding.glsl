@@ -0,0 +1,47 @@
+// Final output encoding, selected from the swapchain's surface format. Must match
+// `OutputEncoding` in src/swapchain.rs.
+const uint ENCODING_LINEAR = 0;
+const uint ENCODING_SRGB = 1;
+const uint ENCODING_PQ = 2;
+const uint ENCODING_SCRGB = 3;
+
+layout(constant_id = 0) const uint OUTPUT_ENCODING = ENCODING_LINEAR;
+
+// Brightness of SDR white in HDR outputs, in nits.
+const float PAPER_WHITE_NITS = 203.0;
+
+vec3 linearToSrgb(vec3 color) {
+    vec3 lo = color * 12.92;
+    vec3 hi = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return mix(hi, lo, lessThanEqual(color, vec3(0.0031308)));
+}
+
+vec3 rec709ToRec2020(vec3 color) {
+    const mat3 m = mat3(
+        0.6274, 0.0691, 0.0164,
+        0.3293, 0.9195, 0.0880,
+        0.0433, 0.0114, 0.8956);
+    return m * color;
+}
+
+// SMPTE ST 2084, input normalized so 1.0 is 10000 nits.
+vec3 pqEncode(vec3 color) {
+    const float m1 = 0.1593017578125;
+    const float m2 = 78.84375;
+    const float c1 = 0.8359375;
+    const float c2 = 18.8515625;
+    const float c3 = 18.6875;
+    vec3 p = pow(max(color, vec3(0.0)), vec3(m1));
+    return pow((c1 + c2 * p) / (1.0 + c3 * p), vec3(m2));
+}
+
+vec4 encodeOutput(vec4 color) {
+    if (OUTPUT_ENCODING == ENCODING_SRGB) {
+        return vec4(linearToSrgb(clamp(color.rgb, 0.0, 1.0)), color.a);
+    } else if (OUTPUT_ENCODING == ENCODING_PQ) {
+        return vec4(pqEncode(rec709ToRec2020(color.rgb) * (PAPER_WHITE_NITS / 10000.0)), color.a);
+    } else if (OUTPUT_ENCODING == ENCODING_SCRGB) {
+        return vec4(color.rgb * (PAPER_WHITE_NITS / 80.0), color.a);
+    }
+    return color;
+}
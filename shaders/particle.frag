@@ -0,0 +1,15 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+#include "encoding.glsl"
+
+layout(location = 0) in vec4 fragColor;
+
+layout(location = 0) out vec4 outColor;
+
+void main() {
+    // Soft round sprite.
+    float dist = length(gl_PointCoord - vec2(0.5));
+    float falloff = 1.0 - smoothstep(0.25, 0.5, dist);
+    outColor = encodeOutput(vec4(fragColor.rgb, fragColor.a * falloff));
+}
@@ -0,0 +1,24 @@
+#version 450
+
+layout(location = 0) in vec2 inPosition;
+layout(location = 1) in vec4 inColor;
+layout(location = 2) in vec2 inAgeLifetime;
+
+layout(push_constant) uniform PushConstants {
+    mat4 viewProj;
+    float pointSize;
+} pc;
+
+layout(location = 0) out vec4 fragColor;
+
+void main() {
+    if (inAgeLifetime.x >= inAgeLifetime.y) {
+        // Dead particle, push it outside the clip volume.
+        gl_Position = vec4(2.0, 2.0, 2.0, 1.0);
+        gl_PointSize = 1.0;
+    } else {
+        gl_Position = pc.viewProj * vec4(inPosition, 0.0, 1.0);
+        gl_PointSize = pc.pointSize;
+    }
+    fragColor = inColor;
+}
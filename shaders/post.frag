@@ -0,0 +1,115 @@
+#version 450
+#extension GL_GOOGLE_include_directive : require
+
+#include "encoding.glsl"
+
+// Must match `PostEffect` in src/post_process.rs.
+const uint EFFECT_PASSTHROUGH = 0;
+const uint EFFECT_TONE_MAP = 1;
+const uint EFFECT_GAMMA = 2;
+const uint EFFECT_FXAA = 3;
+const uint EFFECT_VIGNETTE = 4;
+const uint EFFECT_COLOR_GRADE = 5;
+
+layout(constant_id = 1) const uint EFFECT = EFFECT_PASSTHROUGH;
+
+layout(set = 0, binding = 0) uniform sampler2D inputImage;
+// The blue slices of a 3D LUT side by side, see `ColorLut::strip_pixels`.
+layout(set = 0, binding = 1) uniform sampler2D colorLut;
+
+layout(push_constant) uniform PushConstants {
+    vec2 texelSize;
+    float exposure;
+    float gamma;
+    float vignette;
+    float lutSize;
+} pc;
+
+layout(location = 0) in vec2 fragUv;
+
+layout(location = 0) out vec4 outColor;
+
+const float FXAA_SPAN_MAX = 8.0;
+const float FXAA_REDUCE_MUL = 1.0 / 8.0;
+const float FXAA_REDUCE_MIN = 1.0 / 128.0;
+
+float luma(vec3 color) {
+    return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+// ACES filmic curve fit by Krzysztof Narkowicz.
+vec3 toneMap(vec3 color) {
+    color *= pc.exposure;
+    return clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), 0.0, 1.0);
+}
+
+vec3 fxaa(vec2 uv) {
+    vec3 rgbNW = texture(inputImage, uv + vec2(-1.0, -1.0) * pc.texelSize).rgb;
+    vec3 rgbNE = texture(inputImage, uv + vec2(1.0, -1.0) * pc.texelSize).rgb;
+    vec3 rgbSW = texture(inputImage, uv + vec2(-1.0, 1.0) * pc.texelSize).rgb;
+    vec3 rgbSE = texture(inputImage, uv + vec2(1.0, 1.0) * pc.texelSize).rgb;
+    vec3 rgbM = texture(inputImage, uv).rgb;
+
+    float lumaNW = luma(rgbNW);
+    float lumaNE = luma(rgbNE);
+    float lumaSW = luma(rgbSW);
+    float lumaSE = luma(rgbSE);
+    float lumaM = luma(rgbM);
+    float lumaMin = min(lumaM, min(min(lumaNW, lumaNE), min(lumaSW, lumaSE)));
+    float lumaMax = max(lumaM, max(max(lumaNW, lumaNE), max(lumaSW, lumaSE)));
+
+    // Blur along the edge, perpendicular to the luma gradient.
+    vec2 dir = vec2(-((lumaNW + lumaNE) - (lumaSW + lumaSE)), (lumaNW + lumaSW) - (lumaNE + lumaSE));
+    float dirReduce = max((lumaNW + lumaNE + lumaSW + lumaSE) * 0.25 * FXAA_REDUCE_MUL, FXAA_REDUCE_MIN);
+    float rcpDirMin = 1.0 / (min(abs(dir.x), abs(dir.y)) + dirReduce);
+    dir = clamp(dir * rcpDirMin, vec2(-FXAA_SPAN_MAX), vec2(FXAA_SPAN_MAX)) * pc.texelSize;
+
+    vec3 rgbA = 0.5 * (texture(inputImage, uv + dir * (1.0 / 3.0 - 0.5)).rgb
+        + texture(inputImage, uv + dir * (2.0 / 3.0 - 0.5)).rgb);
+    vec3 rgbB = rgbA * 0.5 + 0.25 * (texture(inputImage, uv - dir * 0.5).rgb
+        + texture(inputImage, uv + dir * 0.5).rgb);
+
+    float lumaB = luma(rgbB);
+    return (lumaB < lumaMin || lumaB > lumaMax) ? rgbA : rgbB;
+}
+
+vec3 vignette(vec3 color, vec2 uv) {
+    float dist = length(uv - 0.5) * 1.41421356;
+    return color * mix(1.0, 1.0 - smoothstep(0.3, 1.0, dist), pc.vignette);
+}
+
+vec3 colorGrade(vec3 color) {
+    color = clamp(color, 0.0, 1.0);
+    float size = pc.lutSize;
+
+    // Filter within the two nearest blue slices, then between them.
+    float slice = color.b * (size - 1.0);
+    float slice0 = floor(slice);
+    float slice1 = min(slice0 + 1.0, size - 1.0);
+    vec2 inSlice = (color.rg * (size - 1.0) + 0.5) / vec2(size * size, size);
+
+    vec3 graded0 = texture(colorLut, inSlice + vec2(slice0 / size, 0.0)).rgb;
+    vec3 graded1 = texture(colorLut, inSlice + vec2(slice1 / size, 0.0)).rgb;
+    return mix(graded0, graded1, slice - slice0);
+}
+
+void main() {
+    vec3 color;
+    if (EFFECT == EFFECT_FXAA) {
+        color = fxaa(fragUv);
+    } else {
+        color = texture(inputImage, fragUv).rgb;
+    }
+
+    if (EFFECT == EFFECT_TONE_MAP) {
+        color = toneMap(color);
+    } else if (EFFECT == EFFECT_GAMMA) {
+        color = pow(max(color, vec3(0.0)), vec3(1.0 / pc.gamma));
+    } else if (EFFECT == EFFECT_VIGNETTE) {
+        color = vignette(color, fragUv);
+    } else if (EFFECT == EFFECT_COLOR_GRADE) {
+        color = colorGrade(color);
+    }
+
+    outColor = encodeOutput(vec4(color, 1.0));
+}
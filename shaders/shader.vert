@@ -3,9 +3,17 @@
 layout(location = 0) in vec2 inPosition;
 layout(location = 1) in vec3 inColor;
 
+// Per-instance, from vertex binding 1.
+layout(location = 2) in mat4 inModel;
+layout(location = 6) in vec4 inInstanceColor;
+
+layout(push_constant) uniform PushConstants {
+    mat4 viewProj;
+} pc;
+
 layout(location = 0) out vec3 fragColor;
 
 void main() {
-    gl_Position = vec4(inPosition, 0.0, 1.0);
-    fragColor = inColor;
+    gl_Position = pc.viewProj * inModel * vec4(inPosition, 0.0, 1.0);
+    fragColor = inColor * inInstanceColor.rgb;
 }
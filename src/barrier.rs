@@ -0,0 +1,548 @@
+use crate::ffi;
+
+/// How a resource is used on one side of a barrier.
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Usage {
+    /// Nothing has used the resource yet, or its previous contents can be discarded.
+    Nothing,
+    /// A swapchain image right after acquiring it. Its stage matches the stage the acquire
+    /// semaphore is waited on, so the layout transition happens after the wait.
+    AcquiredImage,
+    HostWrite,
+    HostRead,
+    TransferRead,
+    TransferWrite,
+    IndirectBuffer,
+    IndexBuffer,
+    VertexBuffer,
+    VertexShaderRead,
+    FragmentShaderRead,
+    ComputeShaderRead,
+    ComputeShaderWrite,
+    ColorAttachment,
+    DepthAttachment,
+    DepthRead,
+    Present,
+    /// Any access from any command, for when nothing more specific is known.
+    General,
+}
+
+/// Stage, access and layout a usage maps to. Only bits that also exist in the legacy
+/// `VkPipelineStageFlags`/`VkAccessFlags` are used so the same masks work for both paths.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AccessInfo {
+    pub stages: ffi::VkPipelineStageFlags2,
+    pub access: ffi::VkAccessFlags2,
+    pub layout: ffi::VkImageLayout,
+}
+
+impl Usage {
+    pub fn info(self) -> AccessInfo {
+        let (stages, access, layout) = match self {
+            Usage::Nothing => (
+                ffi::VK_PIPELINE_STAGE_2_NONE,
+                ffi::VK_ACCESS_2_NONE,
+                ffi::VkImageLayout_VK_IMAGE_LAYOUT_UNDEFINED,
+            ),
+            Usage::AcquiredImage => (
+                ffi::VK_PIPELINE_STAGE_2_COLOR_ATTACHMENT_OUTPUT_BIT,
+                ffi::VK_ACCESS_2_NONE,
+                ffi::VkImageLayout_VK_IMAGE_LAYOUT_UNDEFINED,
+            ),
+            Usage::HostWrite => (
+                ffi::VK_PIPELINE_STAGE_2_HOST_BIT,
+                ffi::VK_ACCESS_2_HOST_WRITE_BIT,
+                ffi::VkImageLayout_VK_IMAGE_LAYOUT_GENERAL,
+            ),
+            Usage::HostRead => (
+                ffi::VK_PIPELINE_STAGE_2_HOST_BIT,
+                ffi::VK_ACCESS_2_HOST_READ_BIT,
+                ffi::VkImageLayout_VK_IMAGE_LAYOUT_GENERAL,
+            ),
+            Usage::TransferRead => (
+                ffi::VK_PIPELINE_STAGE_2_TRANSFER_BIT,
+                ffi::VK_ACCESS_2_TRANSFER_READ_BIT,
+                ffi::VkImageLayout_VK_IMAGE_LAYOUT_TRANSFER_SRC_OPTIMAL,
+            ),
+            Usage::TransferWrite => (
+                ffi::VK_PIPELINE_STAGE_2_TRANSFER_BIT,
+                ffi::VK_ACCESS_2_TRANSFER_WRITE_BIT,
+                ffi::VkImageLayout_VK_IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL,
+            ),
+            Usage::IndirectBuffer => (
+                ffi::VK_PIPELINE_STAGE_2_DRAW_INDIRECT_BIT,
+                ffi::VK_ACCESS_2_INDIRECT_COMMAND_READ_BIT,
+                ffi::VkImageLayout_VK_IMAGE_LAYOUT_UNDEFINED,
+            ),
+            Usage::IndexBuffer => (
+                ffi::VK_PIPELINE_STAGE_2_VERTEX_INPUT_BIT,
+                ffi::VK_ACCESS_2_INDEX_READ_BIT,
+                ffi::VkImageLayout_VK_IMAGE_LAYOUT_UNDEFINED,
+            ),
+            Usage::VertexBuffer => (
+                ffi::VK_PIPELINE_STAGE_2_VERTEX_INPUT_BIT,
+                ffi::VK_ACCESS_2_VERTEX_ATTRIBUTE_READ_BIT,
+                ffi::VkImageLayout_VK_IMAGE_LAYOUT_UNDEFINED,
+            ),
+            Usage::VertexShaderRead => (
+                ffi::VK_PIPELINE_STAGE_2_VERTEX_SHADER_BIT,
+                ffi::VK_ACCESS_2_SHADER_READ_BIT | ffi::VK_ACCESS_2_UNIFORM_READ_BIT,
+                ffi::VkImageLayout_VK_IMAGE_LAYOUT_SHADER_READ_ONLY_OPTIMAL,
+            ),
+            Usage::FragmentShaderRead => (
+                ffi::VK_PIPELINE_STAGE_2_FRAGMENT_SHADER_BIT,
+                ffi::VK_ACCESS_2_SHADER_READ_BIT | ffi::VK_ACCESS_2_UNIFORM_READ_BIT,
+                ffi::VkImageLayout_VK_IMAGE_LAYOUT_SHADER_READ_ONLY_OPTIMAL,
+            ),
+            Usage::ComputeShaderRead => (
+                ffi::VK_PIPELINE_STAGE_2_COMPUTE_SHADER_BIT,
+                ffi::VK_ACCESS_2_SHADER_READ_BIT | ffi::VK_ACCESS_2_UNIFORM_READ_BIT,
+                ffi::VkImageLayout_VK_IMAGE_LAYOUT_GENERAL,
+            ),
+            Usage::ComputeShaderWrite => (
+                ffi::VK_PIPELINE_STAGE_2_COMPUTE_SHADER_BIT,
+                ffi::VK_ACCESS_2_SHADER_WRITE_BIT,
+                ffi::VkImageLayout_VK_IMAGE_LAYOUT_GENERAL,
+            ),
+            Usage::ColorAttachment => (
+                ffi::VK_PIPELINE_STAGE_2_COLOR_ATTACHMENT_OUTPUT_BIT,
+                ffi::VK_ACCESS_2_COLOR_ATTACHMENT_READ_BIT
+                    | ffi::VK_ACCESS_2_COLOR_ATTACHMENT_WRITE_BIT,
+                ffi::VkImageLayout_VK_IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL,
+            ),
+            Usage::DepthAttachment => (
+                ffi::VK_PIPELINE_STAGE_2_EARLY_FRAGMENT_TESTS_BIT
+                    | ffi::VK_PIPELINE_STAGE_2_LATE_FRAGMENT_TESTS_BIT,
+                ffi::VK_ACCESS_2_DEPTH_STENCIL_ATTACHMENT_READ_BIT
+                    | ffi::VK_ACCESS_2_DEPTH_STENCIL_ATTACHMENT_WRITE_BIT,
+                ffi::VkImageLayout_VK_IMAGE_LAYOUT_DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+            ),
+            Usage::DepthRead => (
+                ffi::VK_PIPELINE_STAGE_2_EARLY_FRAGMENT_TESTS_BIT
+                    | ffi::VK_PIPELINE_STAGE_2_LATE_FRAGMENT_TESTS_BIT
+                    | ffi::VK_PIPELINE_STAGE_2_FRAGMENT_SHADER_BIT,
+                ffi::VK_ACCESS_2_DEPTH_STENCIL_ATTACHMENT_READ_BIT
+                    | ffi::VK_ACCESS_2_SHADER_READ_BIT,
+                ffi::VkImageLayout_VK_IMAGE_LAYOUT_DEPTH_STENCIL_READ_ONLY_OPTIMAL,
+            ),
+            // The presentation engine synchronizes through semaphores, the barrier only has
+            // to get the layout right.
+            Usage::Present => (
+                ffi::VK_PIPELINE_STAGE_2_NONE,
+                ffi::VK_ACCESS_2_NONE,
+                ffi::VkImageLayout_VK_IMAGE_LAYOUT_PRESENT_SRC_KHR,
+            ),
+            Usage::General => (
+                ffi::VK_PIPELINE_STAGE_2_ALL_COMMANDS_BIT,
+                ffi::VK_ACCESS_2_MEMORY_READ_BIT | ffi::VK_ACCESS_2_MEMORY_WRITE_BIT,
+                ffi::VkImageLayout_VK_IMAGE_LAYOUT_GENERAL,
+            ),
+        };
+
+        AccessInfo {
+            stages,
+            access,
+            layout,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn is_write(self) -> bool {
+        self.info().access & WRITE_ACCESS != 0
+    }
+}
+
+const WRITE_ACCESS: ffi::VkAccessFlags2 = ffi::VK_ACCESS_2_HOST_WRITE_BIT
+    | ffi::VK_ACCESS_2_TRANSFER_WRITE_BIT
+    | ffi::VK_ACCESS_2_SHADER_WRITE_BIT
+    | ffi::VK_ACCESS_2_COLOR_ATTACHMENT_WRITE_BIT
+    | ffi::VK_ACCESS_2_DEPTH_STENCIL_ATTACHMENT_WRITE_BIT
+    | ffi::VK_ACCESS_2_MEMORY_WRITE_BIT;
+
+/// Source and destination masks for going from `from` to `to`. Only writes have to be made
+/// available, after a read an execution dependency is enough.
+fn masks(
+    from: Usage,
+    to: Usage,
+) -> (
+    ffi::VkPipelineStageFlags2,
+    ffi::VkAccessFlags2,
+    ffi::VkPipelineStageFlags2,
+    ffi::VkAccessFlags2,
+) {
+    let src = from.info();
+    let dst = to.info();
+    (
+        src.stages,
+        src.access & WRITE_ACCESS,
+        dst.stages,
+        dst.access,
+    )
+}
+
+pub fn color_subresource_range() -> ffi::VkImageSubresourceRange {
+    ffi::VkImageSubresourceRange {
+        aspectMask: ffi::VkImageAspectFlagBits_VK_IMAGE_ASPECT_COLOR_BIT,
+        baseMipLevel: 0,
+        levelCount: 1,
+        baseArrayLayer: 0,
+        layerCount: 1,
+    }
+}
+
+/// A batch of barriers recorded with a single call.
+#[derive(Clone, Default)]
+pub struct Barriers {
+    memory: Vec<ffi::VkMemoryBarrier2>,
+    buffers: Vec<ffi::VkBufferMemoryBarrier2>,
+    images: Vec<ffi::VkImageMemoryBarrier2>,
+}
+
+impl Barriers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[allow(dead_code)]
+    pub fn memory(mut self, from: Usage, to: Usage) -> Self {
+        let (src_stages, src_access, dst_stages, dst_access) = masks(from, to);
+
+        let mut barrier: ffi::VkMemoryBarrier2 = unsafe { std::mem::zeroed() };
+        barrier.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_MEMORY_BARRIER_2;
+        barrier.srcStageMask = src_stages;
+        barrier.srcAccessMask = src_access;
+        barrier.dstStageMask = dst_stages;
+        barrier.dstAccessMask = dst_access;
+        self.memory.push(barrier);
+        self
+    }
+
+    pub fn buffer(
+        mut self,
+        buffer: ffi::VkBuffer,
+        offset: ffi::VkDeviceSize,
+        size: ffi::VkDeviceSize,
+        from: Usage,
+        to: Usage,
+    ) -> Self {
+        let (src_stages, src_access, dst_stages, dst_access) = masks(from, to);
+
+        let mut barrier: ffi::VkBufferMemoryBarrier2 = unsafe { std::mem::zeroed() };
+        barrier.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_BUFFER_MEMORY_BARRIER_2;
+        barrier.srcStageMask = src_stages;
+        barrier.srcAccessMask = src_access;
+        barrier.dstStageMask = dst_stages;
+        barrier.dstAccessMask = dst_access;
+        barrier.srcQueueFamilyIndex = ffi::VK_QUEUE_FAMILY_IGNORED as u32;
+        barrier.dstQueueFamilyIndex = ffi::VK_QUEUE_FAMILY_IGNORED as u32;
+        barrier.buffer = buffer;
+        barrier.offset = offset;
+        barrier.size = size;
+        self.buffers.push(barrier);
+        self
+    }
+
+    /// Transitions `image` into the layout of `to`. Going from `Usage::Nothing` discards the
+    /// current contents.
+    pub fn image(
+        mut self,
+        image: ffi::VkImage,
+        range: ffi::VkImageSubresourceRange,
+        from: Usage,
+        to: Usage,
+    ) -> Self {
+        let (src_stages, src_access, dst_stages, dst_access) = masks(from, to);
+
+        let mut barrier: ffi::VkImageMemoryBarrier2 = unsafe { std::mem::zeroed() };
+        barrier.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER_2;
+        barrier.srcStageMask = src_stages;
+        barrier.srcAccessMask = src_access;
+        barrier.dstStageMask = dst_stages;
+        barrier.dstAccessMask = dst_access;
+        barrier.oldLayout = from.info().layout;
+        barrier.newLayout = to.info().layout;
+        barrier.srcQueueFamilyIndex = ffi::VK_QUEUE_FAMILY_IGNORED as u32;
+        barrier.dstQueueFamilyIndex = ffi::VK_QUEUE_FAMILY_IGNORED as u32;
+        barrier.image = image;
+        barrier.subresourceRange = range;
+        self.images.push(barrier);
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn image_barriers(&self) -> &[ffi::VkImageMemoryBarrier2] {
+        &self.images
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.memory.is_empty() && self.buffers.is_empty() && self.images.is_empty()
+    }
+
+    /// Uses `vkCmdPipelineBarrier2` when `synchronization2` is enabled, otherwise merges the
+    /// stage masks of every barrier into one legacy `vkCmdPipelineBarrier`.
+    pub fn record(&self, command_buffer: ffi::VkCommandBuffer, synchronization2: bool) {
+        if self.is_empty() {
+            return;
+        }
+
+        if synchronization2 {
+            let mut dependency_info: ffi::VkDependencyInfo = unsafe { std::mem::zeroed() };
+            dependency_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_DEPENDENCY_INFO;
+            dependency_info.memoryBarrierCount = self.memory.len() as u32;
+            dependency_info.pMemoryBarriers = self.memory.as_ptr();
+            dependency_info.bufferMemoryBarrierCount = self.buffers.len() as u32;
+            dependency_info.pBufferMemoryBarriers = self.buffers.as_ptr();
+            dependency_info.imageMemoryBarrierCount = self.images.len() as u32;
+            dependency_info.pImageMemoryBarriers = self.images.as_ptr();
+
+            unsafe {
+                ffi::vkCmdPipelineBarrier2(command_buffer, std::ptr::addr_of!(dependency_info));
+            }
+            return;
+        }
+
+        let legacy = self.to_legacy();
+        unsafe {
+            ffi::vkCmdPipelineBarrier(
+                command_buffer,
+                legacy.src_stages,
+                legacy.dst_stages,
+                0,
+                legacy.memory.len() as u32,
+                legacy.memory.as_ptr(),
+                legacy.buffers.len() as u32,
+                legacy.buffers.as_ptr(),
+                legacy.images.len() as u32,
+                legacy.images.as_ptr(),
+            );
+        }
+    }
+
+    fn to_legacy(&self) -> LegacyBarriers {
+        let mut src_stages: ffi::VkPipelineStageFlags2 = 0;
+        let mut dst_stages: ffi::VkPipelineStageFlags2 = 0;
+
+        let memory = self
+            .memory
+            .iter()
+            .map(|barrier| {
+                src_stages |= barrier.srcStageMask;
+                dst_stages |= barrier.dstStageMask;
+
+                let mut legacy: ffi::VkMemoryBarrier = unsafe { std::mem::zeroed() };
+                legacy.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_MEMORY_BARRIER;
+                legacy.srcAccessMask = legacy_flags(barrier.srcAccessMask);
+                legacy.dstAccessMask = legacy_flags(barrier.dstAccessMask);
+                legacy
+            })
+            .collect();
+
+        let buffers = self
+            .buffers
+            .iter()
+            .map(|barrier| {
+                src_stages |= barrier.srcStageMask;
+                dst_stages |= barrier.dstStageMask;
+
+                let mut legacy: ffi::VkBufferMemoryBarrier = unsafe { std::mem::zeroed() };
+                legacy.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_BUFFER_MEMORY_BARRIER;
+                legacy.srcAccessMask = legacy_flags(barrier.srcAccessMask);
+                legacy.dstAccessMask = legacy_flags(barrier.dstAccessMask);
+                legacy.srcQueueFamilyIndex = barrier.srcQueueFamilyIndex;
+                legacy.dstQueueFamilyIndex = barrier.dstQueueFamilyIndex;
+                legacy.buffer = barrier.buffer;
+                legacy.offset = barrier.offset;
+                legacy.size = barrier.size;
+                legacy
+            })
+            .collect();
+
+        let images = self
+            .images
+            .iter()
+            .map(|barrier| {
+                src_stages |= barrier.srcStageMask;
+                dst_stages |= barrier.dstStageMask;
+
+                let mut legacy: ffi::VkImageMemoryBarrier = unsafe { std::mem::zeroed() };
+                legacy.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_IMAGE_MEMORY_BARRIER;
+                legacy.srcAccessMask = legacy_flags(barrier.srcAccessMask);
+                legacy.dstAccessMask = legacy_flags(barrier.dstAccessMask);
+                legacy.oldLayout = barrier.oldLayout;
+                legacy.newLayout = barrier.newLayout;
+                legacy.srcQueueFamilyIndex = barrier.srcQueueFamilyIndex;
+                legacy.dstQueueFamilyIndex = barrier.dstQueueFamilyIndex;
+                legacy.image = barrier.image;
+                legacy.subresourceRange = barrier.subresourceRange;
+                legacy
+            })
+            .collect();
+
+        // A stage mask of zero is only valid with synchronization2.
+        LegacyBarriers {
+            src_stages: match legacy_flags(src_stages) {
+                0 => ffi::VkPipelineStageFlagBits_VK_PIPELINE_STAGE_TOP_OF_PIPE_BIT,
+                stages => stages,
+            },
+            dst_stages: match legacy_flags(dst_stages) {
+                0 => ffi::VkPipelineStageFlagBits_VK_PIPELINE_STAGE_BOTTOM_OF_PIPE_BIT,
+                stages => stages,
+            },
+            memory,
+            buffers,
+            images,
+        }
+    }
+}
+
+struct LegacyBarriers {
+    src_stages: ffi::VkPipelineStageFlags,
+    dst_stages: ffi::VkPipelineStageFlags,
+    memory: Vec<ffi::VkMemoryBarrier>,
+    buffers: Vec<ffi::VkBufferMemoryBarrier>,
+    images: Vec<ffi::VkImageMemoryBarrier>,
+}
+
+fn legacy_flags(flags: u64) -> u32 {
+    debug_assert!(
+        flags >> 32 == 0,
+        "Flags {:#x} have no legacy equivalent",
+        flags
+    );
+    flags as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_only_need_execution_dependencies() {
+        let (src_stages, src_access, dst_stages, dst_access) =
+            masks(Usage::ComputeShaderWrite, Usage::VertexBuffer);
+        assert_eq!(src_stages, ffi::VK_PIPELINE_STAGE_2_COMPUTE_SHADER_BIT);
+        assert_eq!(src_access, ffi::VK_ACCESS_2_SHADER_WRITE_BIT);
+        assert_eq!(dst_stages, ffi::VK_PIPELINE_STAGE_2_VERTEX_INPUT_BIT);
+        assert_eq!(dst_access, ffi::VK_ACCESS_2_VERTEX_ATTRIBUTE_READ_BIT);
+
+        let (_, src_access, _, _) = masks(Usage::VertexBuffer, Usage::ComputeShaderWrite);
+        assert_eq!(src_access, ffi::VK_ACCESS_2_NONE);
+
+        let (_, src_access, _, _) = masks(Usage::ColorAttachment, Usage::Present);
+        assert_eq!(src_access, ffi::VK_ACCESS_2_COLOR_ATTACHMENT_WRITE_BIT);
+
+        assert!(Usage::ColorAttachment.is_write());
+        assert!(!Usage::FragmentShaderRead.is_write());
+        assert!(!Usage::Present.is_write());
+    }
+
+    #[test]
+    fn image_layouts_follow_usage() {
+        let image: ffi::VkImage = std::ptr::null_mut();
+        let barriers = Barriers::new()
+            .image(
+                image,
+                color_subresource_range(),
+                Usage::Nothing,
+                Usage::TransferWrite,
+            )
+            .image(
+                image,
+                color_subresource_range(),
+                Usage::ColorAttachment,
+                Usage::FragmentShaderRead,
+            );
+
+        assert_eq!(
+            barriers.images[0].oldLayout,
+            ffi::VkImageLayout_VK_IMAGE_LAYOUT_UNDEFINED
+        );
+        assert_eq!(
+            barriers.images[0].newLayout,
+            ffi::VkImageLayout_VK_IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL
+        );
+        assert_eq!(
+            barriers.images[1].oldLayout,
+            ffi::VkImageLayout_VK_IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL
+        );
+        assert_eq!(
+            barriers.images[1].newLayout,
+            ffi::VkImageLayout_VK_IMAGE_LAYOUT_SHADER_READ_ONLY_OPTIMAL
+        );
+    }
+
+    #[test]
+    fn legacy_fallback_merges_stages() {
+        let image: ffi::VkImage = std::ptr::null_mut();
+        let buffer: ffi::VkBuffer = std::ptr::null_mut();
+        let barriers = Barriers::new()
+            .buffer(
+                buffer,
+                0,
+                64,
+                Usage::ComputeShaderWrite,
+                Usage::IndirectBuffer,
+            )
+            .image(
+                image,
+                color_subresource_range(),
+                Usage::ColorAttachment,
+                Usage::Present,
+            );
+        assert!(!barriers.is_empty());
+
+        let legacy = barriers.to_legacy();
+        assert_eq!(
+            legacy.src_stages,
+            ffi::VkPipelineStageFlagBits_VK_PIPELINE_STAGE_COMPUTE_SHADER_BIT
+                | ffi::VkPipelineStageFlagBits_VK_PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT
+        );
+        assert_eq!(
+            legacy.dst_stages,
+            ffi::VkPipelineStageFlagBits_VK_PIPELINE_STAGE_DRAW_INDIRECT_BIT
+        );
+        assert_eq!(
+            legacy.buffers[0].dstAccessMask,
+            ffi::VkAccessFlagBits_VK_ACCESS_INDIRECT_COMMAND_READ_BIT
+        );
+        assert_eq!(
+            legacy.images[0].newLayout,
+            ffi::VkImageLayout_VK_IMAGE_LAYOUT_PRESENT_SRC_KHR
+        );
+
+        // Presenting alone has no stages, which the legacy call doesn't allow.
+        let legacy = Barriers::new()
+            .image(
+                image,
+                color_subresource_range(),
+                Usage::Present,
+                Usage::Present,
+            )
+            .to_legacy();
+        assert_eq!(
+            legacy.src_stages,
+            ffi::VkPipelineStageFlagBits_VK_PIPELINE_STAGE_TOP_OF_PIPE_BIT
+        );
+        assert_eq!(
+            legacy.dst_stages,
+            ffi::VkPipelineStageFlagBits_VK_PIPELINE_STAGE_BOTTOM_OF_PIPE_BIT
+        );
+    }
+
+    #[test]
+    fn legacy_bits_match() {
+        assert_eq!(
+            ffi::VK_PIPELINE_STAGE_2_COLOR_ATTACHMENT_OUTPUT_BIT as u32,
+            ffi::VkPipelineStageFlagBits_VK_PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT
+        );
+        assert_eq!(
+            ffi::VK_ACCESS_2_SHADER_WRITE_BIT as u32,
+            ffi::VkAccessFlagBits_VK_ACCESS_SHADER_WRITE_BIT
+        );
+        assert_eq!(
+            ffi::VK_ACCESS_2_DEPTH_STENCIL_ATTACHMENT_WRITE_BIT as u32,
+            ffi::VkAccessFlagBits_VK_ACCESS_DEPTH_STENCIL_ATTACHMENT_WRITE_BIT
+        );
+    }
+}
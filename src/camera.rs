@@ -0,0 +1,390 @@
+use crate::ffi;
+use crate::math3d::{
+    mat4_look_at, mat4_mul, mat4_perspective, vec3_add, vec3_cross, vec3_dot, vec3_normalize,
+    vec3_scale, vec3_sub, Mat4,
+};
+
+pub const WORLD_UP: [f32; 3] = [0.0, 1.0, 0.0];
+
+/// Keeps the view direction away from `WORLD_UP`, where look-at degenerates.
+const MAX_PITCH: f32 = 89.0 * std::f32::consts::PI / 180.0;
+
+/// Matches the push constant block in `shaders/shader.vert`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct CameraPushConstants {
+    pub view_proj: Mat4,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    Fps,
+    #[default]
+    Orbit,
+}
+
+impl CameraMode {
+    pub fn toggled(self) -> Self {
+        match self {
+            CameraMode::Fps => CameraMode::Orbit,
+            CameraMode::Orbit => CameraMode::Fps,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CameraMode::Fps => "fps",
+            CameraMode::Orbit => "orbit",
+        }
+    }
+}
+
+impl std::str::FromStr for CameraMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "fps" => Ok(CameraMode::Fps),
+            "orbit" => Ok(CameraMode::Orbit),
+            _ => Err(format!(
+                "Unknown camera mode \"{}\", expected fps or orbit",
+                s
+            )),
+        }
+    }
+}
+
+/// Input gathered over one frame.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct CameraInput {
+    /// W/S, in [-1, 1].
+    pub forward: f32,
+    /// D/A, in [-1, 1].
+    pub right: f32,
+    /// Cursor movement in pixels while the mouse button is held.
+    pub drag: [f32; 2],
+    /// Scroll wheel offset, positive when scrolling away from the user.
+    pub scroll: f32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera {
+    pub eye: [f32; 3],
+    pub target: [f32; 3],
+    /// Vertical field of view in radians.
+    pub fov_y: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for Camera {
+    /// Frames the instance grid, which spans [-1, 1] on the XY plane.
+    fn default() -> Self {
+        Self {
+            eye: [0.0, 0.0, 2.0],
+            target: [0.0, 0.0, 0.0],
+            fov_y: 60.0_f32.to_radians(),
+            near: 0.1,
+            far: 100.0,
+        }
+    }
+}
+
+impl Camera {
+    pub fn view(&self) -> Mat4 {
+        mat4_look_at(self.eye, self.target, WORLD_UP)
+    }
+
+    pub fn projection(&self, aspect: f32) -> Mat4 {
+        mat4_perspective(self.fov_y, aspect, self.near, self.far)
+    }
+
+    pub fn view_projection(&self, extent: ffi::VkExtent2D) -> Mat4 {
+        mat4_mul(&self.projection(aspect_ratio(extent)), &self.view())
+    }
+}
+
+pub fn aspect_ratio(extent: ffi::VkExtent2D) -> f32 {
+    if extent.height == 0 {
+        1.0
+    } else {
+        extent.width as f32 / extent.height as f32
+    }
+}
+
+fn clamp_pitch(pitch: f32) -> f32 {
+    pitch.clamp(-MAX_PITCH, MAX_PITCH)
+}
+
+/// Flies through the scene: WASD moves along the view direction, dragging looks around.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FpsController {
+    pub position: [f32; 3],
+    /// Radians, 0 looks down -Z.
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Units per second.
+    pub move_speed: f32,
+    /// Radians per pixel.
+    pub look_sensitivity: f32,
+}
+
+impl FpsController {
+    /// Continues from wherever `camera` is looking.
+    pub fn from_camera(camera: &Camera) -> Self {
+        let dir = vec3_normalize(vec3_sub(camera.target, camera.eye));
+        Self {
+            position: camera.eye,
+            yaw: dir[0].atan2(-dir[2]),
+            pitch: clamp_pitch(dir[1].clamp(-1.0, 1.0).asin()),
+            move_speed: 2.0,
+            look_sensitivity: 0.003,
+        }
+    }
+
+    pub fn forward(&self) -> [f32; 3] {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        [sin_yaw * cos_pitch, sin_pitch, -cos_yaw * cos_pitch]
+    }
+
+    pub fn update(&mut self, input: &CameraInput, delta_time: f32) {
+        self.yaw += input.drag[0] * self.look_sensitivity;
+        self.pitch = clamp_pitch(self.pitch - input.drag[1] * self.look_sensitivity);
+
+        let forward = self.forward();
+        let right = vec3_normalize(vec3_cross(forward, WORLD_UP));
+        let mut direction = vec3_add(
+            vec3_scale(forward, input.forward),
+            vec3_scale(right, input.right),
+        );
+        // Diagonal movement isn't faster.
+        if vec3_dot(direction, direction) > 1.0 {
+            direction = vec3_normalize(direction);
+        }
+
+        self.position = vec3_add(
+            self.position,
+            vec3_scale(direction, self.move_speed * delta_time),
+        );
+    }
+
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.eye = self.position;
+        camera.target = vec3_add(self.position, self.forward());
+    }
+}
+
+/// Circles a target point: dragging rotates around it, scrolling zooms in and out.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OrbitController {
+    pub target: [f32; 3],
+    pub distance: f32,
+    /// Radians, 0 places the eye on +Z of the target.
+    pub yaw: f32,
+    pub pitch: f32,
+    /// Radians per pixel.
+    pub rotate_sensitivity: f32,
+    /// Fraction of the distance zoomed per scroll step.
+    pub zoom_speed: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+}
+
+impl OrbitController {
+    /// Continues from wherever `camera` is looking.
+    pub fn from_camera(camera: &Camera) -> Self {
+        let min_distance = 0.5;
+        let max_distance = 50.0;
+        let offset = vec3_sub(camera.eye, camera.target);
+        let distance = vec3_dot(offset, offset).sqrt();
+        let pitch = if distance > 0.0 {
+            (offset[1] / distance).clamp(-1.0, 1.0).asin()
+        } else {
+            0.0
+        };
+
+        Self {
+            target: camera.target,
+            distance: distance.clamp(min_distance, max_distance),
+            yaw: offset[0].atan2(offset[2]),
+            pitch: clamp_pitch(pitch),
+            rotate_sensitivity: 0.005,
+            zoom_speed: 0.1,
+            min_distance,
+            max_distance,
+        }
+    }
+
+    pub fn eye(&self) -> [f32; 3] {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        vec3_add(
+            self.target,
+            vec3_scale(
+                [sin_yaw * cos_pitch, sin_pitch, cos_yaw * cos_pitch],
+                self.distance,
+            ),
+        )
+    }
+
+    pub fn update(&mut self, input: &CameraInput) {
+        // Dragging right spins the scene right, so the eye moves left.
+        self.yaw -= input.drag[0] * self.rotate_sensitivity;
+        self.pitch = clamp_pitch(self.pitch + input.drag[1] * self.rotate_sensitivity);
+
+        self.distance = (self.distance * (-input.scroll * self.zoom_speed).exp())
+            .clamp(self.min_distance, self.max_distance);
+    }
+
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.eye = self.eye();
+        camera.target = self.target;
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CameraController {
+    Fps(FpsController),
+    Orbit(OrbitController),
+}
+
+impl CameraController {
+    pub fn new(mode: CameraMode, camera: &Camera) -> Self {
+        match mode {
+            CameraMode::Fps => CameraController::Fps(FpsController::from_camera(camera)),
+            CameraMode::Orbit => CameraController::Orbit(OrbitController::from_camera(camera)),
+        }
+    }
+
+    pub fn mode(&self) -> CameraMode {
+        match self {
+            CameraController::Fps(_) => CameraMode::Fps,
+            CameraController::Orbit(_) => CameraMode::Orbit,
+        }
+    }
+
+    pub fn update(&mut self, input: &CameraInput, delta_time: f32, camera: &mut Camera) {
+        match self {
+            CameraController::Fps(fps) => {
+                fps.update(input, delta_time);
+                fps.apply(camera);
+            }
+            CameraController::Orbit(orbit) => {
+                orbit.update(input);
+                orbit.apply(camera);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math3d::mat4_transform_vec4;
+
+    fn assert_vec3_near(a: [f32; 3], b: [f32; 3]) {
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert!((x - y).abs() < 1e-4, "{:?} != {:?}", a, b);
+        }
+    }
+
+    fn extent(width: u32, height: u32) -> ffi::VkExtent2D {
+        ffi::VkExtent2D { width, height }
+    }
+
+    #[test]
+    fn parse_mode() {
+        assert_eq!("FPS".parse::<CameraMode>(), Ok(CameraMode::Fps));
+        assert_eq!(" orbit".parse::<CameraMode>(), Ok(CameraMode::Orbit));
+        assert!("free".parse::<CameraMode>().is_err());
+        assert_eq!(CameraMode::Orbit.toggled(), CameraMode::Fps);
+    }
+
+    #[test]
+    fn projection_follows_extent() {
+        assert_eq!(aspect_ratio(extent(1600, 800)), 2.0);
+        assert_eq!(aspect_ratio(extent(800, 0)), 1.0);
+
+        let camera = Camera::default();
+        let project = |extent: ffi::VkExtent2D, point: [f32; 3]| {
+            let clip = mat4_transform_vec4(
+                &camera.view_projection(extent),
+                [point[0], point[1], point[2], 1.0],
+            );
+            [clip[0] / clip[3], clip[1] / clip[3], clip[2] / clip[3]]
+        };
+
+        let center = project(extent(800, 600), camera.target);
+        assert!(center[0].abs() < 1e-5 && center[1].abs() < 1e-5);
+        assert!(center[2] > 0.0 && center[2] < 1.0);
+
+        // Widening the window squeezes X but leaves Y alone.
+        let narrow = project(extent(800, 800), [1.0, 1.0, 0.0]);
+        let wide = project(extent(1600, 800), [1.0, 1.0, 0.0]);
+        assert!((wide[0] * 2.0 - narrow[0]).abs() < 1e-5);
+        assert!((wide[1] - narrow[1]).abs() < 1e-5);
+        // World up is screen up.
+        assert!(narrow[1] < 0.0);
+    }
+
+    #[test]
+    fn fps_controller() {
+        let camera = Camera::default();
+        let mut fps = FpsController::from_camera(&camera);
+        assert_vec3_near(fps.forward(), [0.0, 0.0, -1.0]);
+
+        let input = CameraInput {
+            forward: 1.0,
+            right: 1.0,
+            ..Default::default()
+        };
+        fps.update(&input, 1.0);
+        let moved = vec3_sub(fps.position, camera.eye);
+        assert!((vec3_dot(moved, moved).sqrt() - fps.move_speed).abs() < 1e-4);
+        assert!(moved[0] > 0.0 && moved[2] < 0.0);
+
+        let input = CameraInput {
+            drag: [0.0, -1.0e6],
+            ..Default::default()
+        };
+        fps.update(&input, 0.0);
+        assert_eq!(fps.pitch, MAX_PITCH);
+    }
+
+    #[test]
+    fn orbit_controller() {
+        let mut camera = Camera::default();
+        let mut orbit = OrbitController::from_camera(&camera);
+        assert_vec3_near(orbit.eye(), camera.eye);
+
+        let input = CameraInput {
+            drag: [100.0, 50.0],
+            ..Default::default()
+        };
+        orbit.update(&input);
+        orbit.apply(&mut camera);
+        let offset = vec3_sub(camera.eye, camera.target);
+        assert!((vec3_dot(offset, offset).sqrt() - 2.0).abs() < 1e-4);
+        assert!(camera.eye[0] < 0.0 && camera.eye[1] > 0.0);
+
+        orbit.update(&CameraInput {
+            scroll: 1.0,
+            ..Default::default()
+        });
+        assert!(orbit.distance < 2.0);
+        orbit.update(&CameraInput {
+            scroll: -1000.0,
+            ..Default::default()
+        });
+        assert_eq!(orbit.distance, orbit.max_distance);
+
+        // Switching controllers keeps the view.
+        let before = camera;
+        let mut controller = CameraController::new(CameraMode::Fps, &camera);
+        controller.update(&CameraInput::default(), 0.0, &mut camera);
+        assert_vec3_near(camera.eye, before.eye);
+        let dir = vec3_normalize(vec3_sub(camera.target, camera.eye));
+        assert_vec3_near(dir, vec3_normalize(vec3_sub(before.target, before.eye)));
+    }
+}
@@ -0,0 +1,304 @@
+use crate::ffi;
+
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DescriptorKind {
+    StorageBuffer,
+    StorageImage,
+    CombinedImageSampler,
+}
+
+impl DescriptorKind {
+    pub fn descriptor_type(self) -> ffi::VkDescriptorType {
+        match self {
+            DescriptorKind::StorageBuffer => {
+                ffi::VkDescriptorType_VK_DESCRIPTOR_TYPE_STORAGE_BUFFER
+            }
+            DescriptorKind::StorageImage => ffi::VkDescriptorType_VK_DESCRIPTOR_TYPE_STORAGE_IMAGE,
+            DescriptorKind::CombinedImageSampler => {
+                ffi::VkDescriptorType_VK_DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER
+            }
+        }
+    }
+}
+
+pub fn create_descriptor_set_layout(
+    device: ffi::VkDevice,
+    bindings: &[(u32, DescriptorKind)],
+    stages: ffi::VkShaderStageFlags,
+) -> Result<ffi::VkDescriptorSetLayout, String> {
+    let layout_bindings: Vec<ffi::VkDescriptorSetLayoutBinding> = bindings
+        .iter()
+        .map(|(binding, kind)| {
+            let mut layout_binding: ffi::VkDescriptorSetLayoutBinding =
+                unsafe { std::mem::zeroed() };
+            layout_binding.binding = *binding;
+            layout_binding.descriptorType = kind.descriptor_type();
+            layout_binding.descriptorCount = 1;
+            layout_binding.stageFlags = stages;
+            layout_binding.pImmutableSamplers = std::ptr::null();
+            layout_binding
+        })
+        .collect();
+
+    let mut layout_info: ffi::VkDescriptorSetLayoutCreateInfo = unsafe { std::mem::zeroed() };
+    layout_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_DESCRIPTOR_SET_LAYOUT_CREATE_INFO;
+    layout_info.bindingCount = layout_bindings.len() as u32;
+    layout_info.pBindings = layout_bindings.as_ptr();
+
+    let mut layout: ffi::VkDescriptorSetLayout = std::ptr::null_mut();
+    let result = unsafe {
+        ffi::vkCreateDescriptorSetLayout(
+            device,
+            std::ptr::addr_of!(layout_info),
+            std::ptr::null(),
+            std::ptr::addr_of_mut!(layout),
+        )
+    };
+    if result != ffi::VkResult_VK_SUCCESS {
+        return Err(String::from("Failed to create descriptor set layout!"));
+    }
+
+    Ok(layout)
+}
+
+pub fn create_pipeline_layout(
+    device: ffi::VkDevice,
+    set_layouts: &[ffi::VkDescriptorSetLayout],
+    push_constant_size: u32,
+    push_constant_stages: ffi::VkShaderStageFlags,
+) -> Result<ffi::VkPipelineLayout, String> {
+    let push_constant_range = ffi::VkPushConstantRange {
+        stageFlags: push_constant_stages,
+        offset: 0,
+        size: push_constant_size,
+    };
+
+    let mut pipeline_layout_info: ffi::VkPipelineLayoutCreateInfo = unsafe { std::mem::zeroed() };
+    pipeline_layout_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_LAYOUT_CREATE_INFO;
+    pipeline_layout_info.setLayoutCount = set_layouts.len() as u32;
+    pipeline_layout_info.pSetLayouts = set_layouts.as_ptr();
+    if push_constant_size > 0 {
+        pipeline_layout_info.pushConstantRangeCount = 1;
+        pipeline_layout_info.pPushConstantRanges = std::ptr::addr_of!(push_constant_range);
+    }
+
+    let mut pipeline_layout: ffi::VkPipelineLayout = std::ptr::null_mut();
+    let result = unsafe {
+        ffi::vkCreatePipelineLayout(
+            device,
+            std::ptr::addr_of!(pipeline_layout_info),
+            std::ptr::null(),
+            std::ptr::addr_of_mut!(pipeline_layout),
+        )
+    };
+    if result != ffi::VkResult_VK_SUCCESS {
+        return Err(String::from("Failed to create pipeline layout!"));
+    }
+
+    Ok(pipeline_layout)
+}
+
+pub fn create_compute_pipeline(
+    device: ffi::VkDevice,
+    module: ffi::VkShaderModule,
+    layout: ffi::VkPipelineLayout,
+) -> Result<ffi::VkPipeline, String> {
+    let mut stage_info: ffi::VkPipelineShaderStageCreateInfo = unsafe { std::mem::zeroed() };
+    stage_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_SHADER_STAGE_CREATE_INFO;
+    stage_info.stage = ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_COMPUTE_BIT;
+    stage_info.module = module;
+    stage_info.pName = c"main".as_ptr();
+
+    let mut pipeline_info: ffi::VkComputePipelineCreateInfo = unsafe { std::mem::zeroed() };
+    pipeline_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_COMPUTE_PIPELINE_CREATE_INFO;
+    pipeline_info.stage = stage_info;
+    pipeline_info.layout = layout;
+    pipeline_info.basePipelineHandle = std::ptr::null_mut();
+    pipeline_info.basePipelineIndex = -1;
+
+    let mut pipeline: ffi::VkPipeline = std::ptr::null_mut();
+    let result = unsafe {
+        ffi::vkCreateComputePipelines(
+            device,
+            std::ptr::null_mut(),
+            1,
+            std::ptr::addr_of!(pipeline_info),
+            std::ptr::null(),
+            std::ptr::addr_of_mut!(pipeline),
+        )
+    };
+    if result != ffi::VkResult_VK_SUCCESS {
+        return Err(String::from("Failed to create a compute pipeline!"));
+    }
+
+    Ok(pipeline)
+}
+
+pub fn allocate_descriptor_set(
+    device: ffi::VkDevice,
+    pool: ffi::VkDescriptorPool,
+    layout: ffi::VkDescriptorSetLayout,
+) -> Result<ffi::VkDescriptorSet, String> {
+    let mut alloc_info: ffi::VkDescriptorSetAllocateInfo = unsafe { std::mem::zeroed() };
+    alloc_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_DESCRIPTOR_SET_ALLOCATE_INFO;
+    alloc_info.descriptorPool = pool;
+    alloc_info.descriptorSetCount = 1;
+    alloc_info.pSetLayouts = std::ptr::addr_of!(layout);
+
+    let mut descriptor_set: ffi::VkDescriptorSet = std::ptr::null_mut();
+    let result = unsafe {
+        ffi::vkAllocateDescriptorSets(
+            device,
+            std::ptr::addr_of!(alloc_info),
+            std::ptr::addr_of_mut!(descriptor_set),
+        )
+    };
+    if result != ffi::VkResult_VK_SUCCESS {
+        return Err(String::from("Failed to allocate descriptor set!"));
+    }
+
+    Ok(descriptor_set)
+}
+
+pub fn write_storage_buffer(
+    device: ffi::VkDevice,
+    set: ffi::VkDescriptorSet,
+    binding: u32,
+    buffer: ffi::VkBuffer,
+    range: ffi::VkDeviceSize,
+) {
+    let buffer_info = ffi::VkDescriptorBufferInfo {
+        buffer,
+        offset: 0,
+        range,
+    };
+
+    let mut write: ffi::VkWriteDescriptorSet = unsafe { std::mem::zeroed() };
+    write.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_WRITE_DESCRIPTOR_SET;
+    write.dstSet = set;
+    write.dstBinding = binding;
+    write.dstArrayElement = 0;
+    write.descriptorType = DescriptorKind::StorageBuffer.descriptor_type();
+    write.descriptorCount = 1;
+    write.pBufferInfo = std::ptr::addr_of!(buffer_info);
+
+    unsafe {
+        ffi::vkUpdateDescriptorSets(device, 1, std::ptr::addr_of!(write), 0, std::ptr::null());
+    }
+}
+
+#[allow(dead_code)]
+pub fn write_storage_image(
+    device: ffi::VkDevice,
+    set: ffi::VkDescriptorSet,
+    binding: u32,
+    image_view: ffi::VkImageView,
+) {
+    let image_info = ffi::VkDescriptorImageInfo {
+        sampler: std::ptr::null_mut(),
+        imageView: image_view,
+        imageLayout: ffi::VkImageLayout_VK_IMAGE_LAYOUT_GENERAL,
+    };
+
+    let mut write: ffi::VkWriteDescriptorSet = unsafe { std::mem::zeroed() };
+    write.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_WRITE_DESCRIPTOR_SET;
+    write.dstSet = set;
+    write.dstBinding = binding;
+    write.dstArrayElement = 0;
+    write.descriptorType = DescriptorKind::StorageImage.descriptor_type();
+    write.descriptorCount = 1;
+    write.pImageInfo = std::ptr::addr_of!(image_info);
+
+    unsafe {
+        ffi::vkUpdateDescriptorSets(device, 1, std::ptr::addr_of!(write), 0, std::ptr::null());
+    }
+}
+
+/// The image has to be in `SHADER_READ_ONLY_OPTIMAL` when it is sampled.
+pub fn write_combined_image_sampler(
+    device: ffi::VkDevice,
+    set: ffi::VkDescriptorSet,
+    binding: u32,
+    image_view: ffi::VkImageView,
+    sampler: ffi::VkSampler,
+) {
+    let image_info = ffi::VkDescriptorImageInfo {
+        sampler,
+        imageView: image_view,
+        imageLayout: ffi::VkImageLayout_VK_IMAGE_LAYOUT_SHADER_READ_ONLY_OPTIMAL,
+    };
+
+    let mut write: ffi::VkWriteDescriptorSet = unsafe { std::mem::zeroed() };
+    write.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_WRITE_DESCRIPTOR_SET;
+    write.dstSet = set;
+    write.dstBinding = binding;
+    write.dstArrayElement = 0;
+    write.descriptorType = DescriptorKind::CombinedImageSampler.descriptor_type();
+    write.descriptorCount = 1;
+    write.pImageInfo = std::ptr::addr_of!(image_info);
+
+    unsafe {
+        ffi::vkUpdateDescriptorSets(device, 1, std::ptr::addr_of!(write), 0, std::ptr::null());
+    }
+}
+
+pub fn record_dispatch<T>(
+    command_buffer: ffi::VkCommandBuffer,
+    pipeline: ffi::VkPipeline,
+    layout: ffi::VkPipelineLayout,
+    descriptor_set: ffi::VkDescriptorSet,
+    push_constants: &T,
+    group_counts: [u32; 3],
+) {
+    unsafe {
+        ffi::vkCmdBindPipeline(
+            command_buffer,
+            ffi::VkPipelineBindPoint_VK_PIPELINE_BIND_POINT_COMPUTE,
+            pipeline,
+        );
+        ffi::vkCmdBindDescriptorSets(
+            command_buffer,
+            ffi::VkPipelineBindPoint_VK_PIPELINE_BIND_POINT_COMPUTE,
+            layout,
+            0,
+            1,
+            std::ptr::addr_of!(descriptor_set),
+            0,
+            std::ptr::null(),
+        );
+        if std::mem::size_of::<T>() > 0 {
+            ffi::vkCmdPushConstants(
+                command_buffer,
+                layout,
+                ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_COMPUTE_BIT,
+                0,
+                std::mem::size_of::<T>() as u32,
+                push_constants as *const T as *const std::ffi::c_void,
+            );
+        }
+        ffi::vkCmdDispatch(
+            command_buffer,
+            group_counts[0],
+            group_counts[1],
+            group_counts[2],
+        );
+    }
+}
+
+pub const fn group_count(invocations: u32, local_size: u32) -> u32 {
+    invocations.div_ceil(local_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn group_counts() {
+        assert_eq!(group_count(0, 64), 0);
+        assert_eq!(group_count(1, 64), 1);
+        assert_eq!(group_count(64, 64), 1);
+        assert_eq!(group_count(65, 64), 2);
+    }
+}
@@ -0,0 +1,586 @@
+use crate::camera::CameraMode;
+use crate::device_select::DeviceSelector;
+use crate::input::Bindings;
+use crate::logger::Level;
+use crate::multisample::MsaaSetting;
+use crate::post_process::{PostChain, PostSettings};
+use crate::swapchain::{PresentModePolicy, SurfaceFormatPreference};
+use crate::validation::{self, ErrorAction, ValidationFeature};
+
+pub const PRESENT_MODE_ENV: &str = "VULKAN_PRESENT_MODE";
+pub const SURFACE_FORMAT_ENV: &str = "VULKAN_SURFACE_FORMAT";
+pub const GPU_ENV: &str = "VULKAN_GPU";
+pub const MSAA_ENV: &str = "VULKAN_MSAA";
+pub const POST_ENV: &str = "VULKAN_POST";
+pub const BINDINGS_ENV: &str = "VULKAN_BINDINGS";
+pub const LOG_LEVEL_ENV: &str = "VULKAN_LOG_LEVEL";
+pub const SUPPRESS_VALIDATION_ENV: &str = "VULKAN_SUPPRESS_VALIDATION";
+pub const VALIDATION_ENV: &str = "VULKAN_VALIDATION";
+pub const VALIDATION_FEATURES_ENV: &str = "VULKAN_VALIDATION_FEATURES";
+
+pub const USAGE: &str = "Usage: vulkan_rust [OPTIONS]
+
+Options:
+  --present-mode <MODE>      vsync, mailbox, immediate or relaxed (env: VULKAN_PRESENT_MODE)
+  --surface-format <FORMAT>  sdr, 10bit, hdr10 or scrgb (env: VULKAN_SURFACE_FORMAT)
+  --gpu <SELECTOR>           Use the GPU with this index, vendor:device ID (hex) or name
+                             substring instead of the best scoring one (env: VULKAN_GPU)
+  --msaa <SAMPLES>           off, 2, 4, 8, 16, 32, 64 or max, lowered to what the GPU supports
+                             (default: 4, env: VULKAN_MSAA)
+  --sample-shading <MIN>     Shade at least this fraction (0-1] of the samples of each pixel
+  --post <EFFECTS>           Comma separated post-process chain of tonemap, gamma, fxaa,
+                             vignette and lut, or none (default: tonemap,fxaa, env: VULKAN_POST)
+  --exposure <VALUE>         Scene brightness multiplier applied before tone mapping (default: 1)
+  --gamma <VALUE>            Exponent of the gamma effect, above 1 brightens (default: 1)
+  --lut <FILE>               .cube 3D LUT used by the lut effect (default: identity)
+  --camera <MODE>            orbit or fps controls at startup (default: orbit)
+  --gpu-trace <FILE>         Write the GPU pass timings of the last frames as a Chrome trace on
+                             exit
+  --gpu-stats                Collect pipeline statistics per pass and an occlusion query per
+                             draw, printed with I
+  --bind <ACTION>=<KEYS>     Rebind vsync, camera, forward, back, left, right, look,
+                             gpu-timings or gpu-stats to comma separated keys, mouse_* or
+                             gamepad_* buttons, repeatable (env: VULKAN_BINDINGS, ; separated)
+  --fps-cap <FPS>            Sleep to stay under this frame rate
+  --fixed-step <HZ>          Advance the simulation in fixed steps of 1/HZ seconds
+  --validation, --no-validation
+                             Enable or disable the validation layers (default: on in debug
+                             builds, env: VULKAN_VALIDATION=1 or 0)
+  --validation-features <FEATURES>
+                             Comma separated gpu-assisted, sync, best-practices and
+                             debug-printf checks, enables validation
+                             (env: VULKAN_VALIDATION_FEATURES)
+  --log-level <LEVEL>        debug, info, warn or error, also the lowest validation message
+                             severity subscribed to (default: warn, env: VULKAN_LOG_LEVEL)
+  --suppress-validation <IDS>
+                             Comma separated validation message ID names or numbers to drop,
+                             repeatable (env: VULKAN_SUPPRESS_VALIDATION)
+  --validation-errors <MODE> log, record (count them on exit) or panic after the frame that
+                             caused the first one (default: log)
+  --list-devices             Print every GPU and why it can or can't be used, then exit
+  -h, --help                 Print this message
+
+Keys (defaults):
+  V                          Toggle vsync
+  C                          Switch between the orbit and fps camera
+  W, A, S, D or arrows       Move the fps camera, or the left stick
+  Left mouse drag            Look around (fps) or rotate around the scene (orbit), or the
+                             right stick
+  Scroll                     Zoom the orbit camera
+  P                          Print the GPU time of each pass
+  I                          Print the pipeline statistics and occlusion results (--gpu-stats)";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AppConfig {
+    pub present_mode: PresentModePolicy,
+    pub surface_format: SurfaceFormatPreference,
+    pub gpu: Option<DeviceSelector>,
+    pub msaa: MsaaSetting,
+    pub sample_shading: Option<f32>,
+    pub post_chain: PostChain,
+    pub post_settings: PostSettings,
+    pub lut: Option<String>,
+    pub camera: CameraMode,
+    pub bindings: Bindings,
+    pub fps_cap: Option<f32>,
+    pub fixed_step: Option<f32>,
+    pub gpu_trace: Option<String>,
+    pub gpu_stats: bool,
+    /// `None` leaves it to `validation_enabled`.
+    pub validation: Option<bool>,
+    pub validation_features: Vec<ValidationFeature>,
+    pub log_level: Level,
+    pub suppress_validation: Vec<String>,
+    pub validation_errors: ErrorAction,
+    pub list_devices: bool,
+    pub show_help: bool,
+}
+
+impl AppConfig {
+    /// On in debug builds or when validation features were asked for, unless set explicitly.
+    pub fn validation_enabled(&self) -> bool {
+        self.validation
+            .unwrap_or(cfg!(debug_assertions) || !self.validation_features.is_empty())
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(std::env::args().skip(1), |key| std::env::var(key).ok())
+    }
+
+    /// Environment variables are applied first so command line flags override them.
+    pub fn parse<I, E>(args: I, env: E) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = String>,
+        E: Fn(&str) -> Option<String>,
+    {
+        let mut config = AppConfig::default();
+
+        if let Some(value) = env(PRESENT_MODE_ENV) {
+            config.present_mode = value.parse()?;
+        }
+        if let Some(value) = env(SURFACE_FORMAT_ENV) {
+            config.surface_format = value.parse()?;
+        }
+        if let Some(value) = env(GPU_ENV) {
+            config.gpu = Some(value.parse()?);
+        }
+        if let Some(value) = env(MSAA_ENV) {
+            config.msaa = value.parse()?;
+        }
+        if let Some(value) = env(POST_ENV) {
+            config.post_chain = value.parse()?;
+        }
+        if let Some(value) = env(BINDINGS_ENV) {
+            config.bindings.bind_all(&value)?;
+        }
+        let mut log_level_set = false;
+        if let Some(value) = env(LOG_LEVEL_ENV) {
+            config.log_level = value.parse()?;
+            log_level_set = true;
+        }
+        if let Some(value) = env(VALIDATION_ENV) {
+            config.validation = Some(parse_switch(VALIDATION_ENV, &value)?);
+        }
+        if let Some(value) = env(VALIDATION_FEATURES_ENV) {
+            config.validation_features = validation::parse_features(&value)?;
+        }
+        if let Some(value) = env(SUPPRESS_VALIDATION_ENV) {
+            config.suppress_validation.extend(parse_ids(&value));
+        }
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_owned(), Some(value.to_owned())),
+                None => (arg, None),
+            };
+
+            match flag.as_str() {
+                "-h" | "--help" => config.show_help = true,
+                "--list-devices" => config.list_devices = true,
+                "--gpu-stats" => config.gpu_stats = true,
+                "--gpu" => config.gpu = Some(take_value(&flag, inline_value, &mut args)?.parse()?),
+                "--present-mode" => {
+                    config.present_mode = take_value(&flag, inline_value, &mut args)?.parse()?
+                }
+                "--surface-format" => {
+                    config.surface_format = take_value(&flag, inline_value, &mut args)?.parse()?
+                }
+                "--msaa" => config.msaa = take_value(&flag, inline_value, &mut args)?.parse()?,
+                "--sample-shading" => {
+                    config.sample_shading = Some(parse_sample_shading(&take_value(
+                        &flag,
+                        inline_value,
+                        &mut args,
+                    )?)?)
+                }
+                "--post" => {
+                    config.post_chain = take_value(&flag, inline_value, &mut args)?.parse()?
+                }
+                "--exposure" => {
+                    config.post_settings.exposure =
+                        parse_positive(&flag, &take_value(&flag, inline_value, &mut args)?)?
+                }
+                "--gamma" => {
+                    config.post_settings.gamma =
+                        parse_positive(&flag, &take_value(&flag, inline_value, &mut args)?)?
+                }
+                "--lut" => config.lut = Some(take_value(&flag, inline_value, &mut args)?),
+                "--camera" => {
+                    config.camera = take_value(&flag, inline_value, &mut args)?.parse()?
+                }
+                "--fps-cap" => {
+                    config.fps_cap = Some(parse_positive(
+                        &flag,
+                        &take_value(&flag, inline_value, &mut args)?,
+                    )?)
+                }
+                "--fixed-step" => {
+                    config.fixed_step = Some(parse_positive(
+                        &flag,
+                        &take_value(&flag, inline_value, &mut args)?,
+                    )?)
+                }
+                "--gpu-trace" => {
+                    config.gpu_trace = Some(take_value(&flag, inline_value, &mut args)?)
+                }
+                "--validation" => config.validation = Some(true),
+                "--no-validation" => config.validation = Some(false),
+                "--validation-features" => {
+                    config.validation_features =
+                        validation::parse_features(&take_value(&flag, inline_value, &mut args)?)?
+                }
+                "--log-level" => {
+                    config.log_level = take_value(&flag, inline_value, &mut args)?.parse()?;
+                    log_level_set = true;
+                }
+                "--suppress-validation" => config
+                    .suppress_validation
+                    .extend(parse_ids(&take_value(&flag, inline_value, &mut args)?)),
+                "--validation-errors" => {
+                    config.validation_errors =
+                        take_value(&flag, inline_value, &mut args)?.parse()?
+                }
+                "--bind" => config
+                    .bindings
+                    .bind(&take_value(&flag, inline_value, &mut args)?)?,
+                _ => return Err(format!("Unknown argument \"{}\"", flag)),
+            }
+        }
+
+        // Printf output is logged as info, so it would be dropped by the default level.
+        if !log_level_set
+            && config
+                .validation_features
+                .contains(&ValidationFeature::DebugPrintf)
+        {
+            config.log_level = config.log_level.min(Level::Info);
+        }
+
+        Ok(config)
+    }
+}
+
+fn take_value<I: Iterator<Item = String>>(
+    flag: &str,
+    inline_value: Option<String>,
+    args: &mut I,
+) -> Result<String, String> {
+    inline_value
+        .or_else(|| args.next())
+        .ok_or_else(|| format!("Missing value for \"{}\"", flag))
+}
+
+fn parse_ids(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(String::from)
+}
+
+fn parse_switch(name: &str, value: &str) -> Result<bool, String> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "on" | "true" | "yes" => Ok(true),
+        "0" | "off" | "false" | "no" => Ok(false),
+        _ => Err(format!("Invalid {} \"{}\", expected 1 or 0", name, value)),
+    }
+}
+
+fn parse_sample_shading(value: &str) -> Result<f32, String> {
+    match value.trim().parse::<f32>() {
+        Ok(min) if min > 0.0 && min <= 1.0 => Ok(min),
+        _ => Err(format!(
+            "Invalid sample shading \"{}\", expected a fraction in (0, 1]",
+            value
+        )),
+    }
+}
+
+fn parse_positive(flag: &str, value: &str) -> Result<f32, String> {
+    match value.trim().parse::<f32>() {
+        Ok(value) if value > 0.0 && value.is_finite() => Ok(value),
+        _ => Err(format!(
+            "Invalid value \"{}\" for \"{}\", expected a positive number",
+            value, flag
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{Action, Button};
+    use crate::post_process::PostEffect;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_present_mode() {
+        let no_env = |_: &str| None;
+
+        assert_eq!(
+            AppConfig::parse(args(&[]), no_env).unwrap(),
+            AppConfig::default()
+        );
+        assert_eq!(
+            AppConfig::parse(args(&["--present-mode", "mailbox"]), no_env)
+                .unwrap()
+                .present_mode,
+            PresentModePolicy::LowLatency
+        );
+        assert_eq!(
+            AppConfig::parse(args(&["--present-mode=immediate"]), no_env)
+                .unwrap()
+                .present_mode,
+            PresentModePolicy::Immediate
+        );
+
+        let env = |key: &str| (key == PRESENT_MODE_ENV).then(|| String::from("relaxed"));
+        assert_eq!(
+            AppConfig::parse(args(&[]), env).unwrap().present_mode,
+            PresentModePolicy::Relaxed
+        );
+        assert_eq!(
+            AppConfig::parse(args(&["--present-mode", "vsync"]), env)
+                .unwrap()
+                .present_mode,
+            PresentModePolicy::Vsync
+        );
+
+        assert!(AppConfig::parse(args(&["--present-mode"]), no_env).is_err());
+        assert!(AppConfig::parse(args(&["--bogus"]), no_env).is_err());
+    }
+
+    #[test]
+    fn parse_surface_format() {
+        let no_env = |_: &str| None;
+
+        assert_eq!(
+            AppConfig::parse(args(&["--surface-format", "hdr10"]), no_env)
+                .unwrap()
+                .surface_format,
+            SurfaceFormatPreference::Hdr10
+        );
+
+        let env = |key: &str| (key == SURFACE_FORMAT_ENV).then(|| String::from("scrgb"));
+        assert_eq!(
+            AppConfig::parse(args(&[]), env).unwrap().surface_format,
+            SurfaceFormatPreference::ScRgb
+        );
+        assert!(AppConfig::parse(args(&["--surface-format=hdr9000"]), no_env).is_err());
+    }
+
+    #[test]
+    fn parse_gpu_selection() {
+        let no_env = |_: &str| None;
+
+        let config =
+            AppConfig::parse(args(&["--list-devices", "--gpu", "10de:2684"]), no_env).unwrap();
+        assert!(config.list_devices);
+        assert_eq!(
+            config.gpu,
+            Some(DeviceSelector::VendorDevice(0x10de, 0x2684))
+        );
+
+        let env = |key: &str| (key == GPU_ENV).then(|| String::from("1"));
+        assert_eq!(
+            AppConfig::parse(args(&[]), env).unwrap().gpu,
+            Some(DeviceSelector::Index(1))
+        );
+        assert_eq!(
+            AppConfig::parse(args(&["--gpu=radeon"]), env).unwrap().gpu,
+            Some(DeviceSelector::Name(String::from("radeon")))
+        );
+    }
+
+    #[test]
+    fn parse_multisampling() {
+        let no_env = |_: &str| None;
+
+        let config = AppConfig::parse(args(&[]), no_env).unwrap();
+        assert_eq!(config.msaa, MsaaSetting::Samples(4));
+        assert_eq!(config.sample_shading, None);
+
+        let config =
+            AppConfig::parse(args(&["--msaa=max", "--sample-shading", "0.5"]), no_env).unwrap();
+        assert_eq!(config.msaa, MsaaSetting::Max);
+        assert_eq!(config.sample_shading, Some(0.5));
+
+        let env = |key: &str| (key == MSAA_ENV).then(|| String::from("off"));
+        assert_eq!(
+            AppConfig::parse(args(&[]), env).unwrap().msaa,
+            MsaaSetting::Samples(1)
+        );
+
+        assert!(AppConfig::parse(args(&["--sample-shading", "0"]), no_env).is_err());
+        assert!(AppConfig::parse(args(&["--sample-shading", "1.5"]), no_env).is_err());
+        assert!(AppConfig::parse(args(&["--msaa", "6"]), no_env).is_err());
+    }
+
+    #[test]
+    fn parse_post_processing() {
+        let no_env = |_: &str| None;
+
+        let config = AppConfig::parse(args(&[]), no_env).unwrap();
+        assert_eq!(config.post_chain, PostChain::default());
+        assert_eq!(config.post_settings, PostSettings::default());
+
+        let env = |key: &str| (key == POST_ENV).then(|| String::from("vignette"));
+        let config = AppConfig::parse(
+            args(&["--exposure", "2", "--gamma=1.5", "--lut", "film.cube"]),
+            env,
+        )
+        .unwrap();
+        assert_eq!(config.post_chain, PostChain(vec![PostEffect::Vignette]));
+        assert_eq!(config.post_settings.exposure, 2.0);
+        assert_eq!(config.post_settings.gamma, 1.5);
+        assert_eq!(config.lut.as_deref(), Some("film.cube"));
+
+        assert_eq!(
+            AppConfig::parse(args(&["--post", "none"]), env)
+                .unwrap()
+                .post_chain,
+            PostChain(Vec::new())
+        );
+        assert!(AppConfig::parse(args(&["--exposure", "-1"]), no_env).is_err());
+        assert!(AppConfig::parse(args(&["--post", "bloom"]), no_env).is_err());
+    }
+
+    #[test]
+    fn parse_camera() {
+        let no_env = |_: &str| None;
+
+        assert_eq!(
+            AppConfig::parse(args(&[]), no_env).unwrap().camera,
+            CameraMode::Orbit
+        );
+        assert_eq!(
+            AppConfig::parse(args(&["--camera", "fps"]), no_env)
+                .unwrap()
+                .camera,
+            CameraMode::Fps
+        );
+        assert!(AppConfig::parse(args(&["--camera=free"]), no_env).is_err());
+    }
+
+    #[test]
+    fn parse_timing() {
+        let no_env = |_: &str| None;
+
+        let config = AppConfig::parse(args(&[]), no_env).unwrap();
+        assert_eq!(config.fps_cap, None);
+        assert_eq!(config.fixed_step, None);
+
+        let config =
+            AppConfig::parse(args(&["--fps-cap", "60", "--fixed-step=120"]), no_env).unwrap();
+        assert_eq!(config.fps_cap, Some(60.0));
+        assert_eq!(config.fixed_step, Some(120.0));
+
+        assert!(AppConfig::parse(args(&["--fps-cap", "0"]), no_env).is_err());
+        assert!(AppConfig::parse(args(&["--fixed-step", "fast"]), no_env).is_err());
+
+        assert_eq!(config.gpu_trace, None);
+        assert_eq!(
+            AppConfig::parse(args(&["--gpu-trace", "trace.json"]), no_env)
+                .unwrap()
+                .gpu_trace
+                .as_deref(),
+            Some("trace.json")
+        );
+
+        assert!(!config.gpu_stats);
+        assert!(
+            AppConfig::parse(args(&["--gpu-stats"]), no_env)
+                .unwrap()
+                .gpu_stats
+        );
+    }
+
+    #[test]
+    fn parse_validation() {
+        let no_env = |_: &str| None;
+
+        let config = AppConfig::parse(args(&[]), no_env).unwrap();
+        assert_eq!(config.log_level, Level::Warn);
+        assert!(config.suppress_validation.is_empty());
+        assert_eq!(config.validation_errors, ErrorAction::Log);
+
+        let env = |key: &str| match key {
+            LOG_LEVEL_ENV => Some(String::from("info")),
+            SUPPRESS_VALIDATION_ENV => Some(String::from("VUID-a, 0x10")),
+            _ => None,
+        };
+        let config = AppConfig::parse(
+            args(&[
+                "--log-level=error",
+                "--suppress-validation",
+                "VUID-b",
+                "--validation-errors",
+                "panic",
+            ]),
+            env,
+        )
+        .unwrap();
+        assert_eq!(config.log_level, Level::Error);
+        assert_eq!(config.suppress_validation, ["VUID-a", "0x10", "VUID-b"]);
+        assert_eq!(config.validation_errors, ErrorAction::Panic);
+
+        assert!(AppConfig::parse(args(&["--log-level", "loud"]), no_env).is_err());
+        assert!(AppConfig::parse(args(&["--validation-errors", "ignore"]), no_env).is_err());
+    }
+
+    #[test]
+    fn parse_validation_toggle() {
+        let no_env = |_: &str| None;
+
+        let config = AppConfig::parse(args(&[]), no_env).unwrap();
+        assert_eq!(config.validation, None);
+        assert_eq!(config.validation_enabled(), cfg!(debug_assertions));
+
+        let config = AppConfig::parse(
+            args(&["--validation-features", "sync,debug-printf"]),
+            no_env,
+        )
+        .unwrap();
+        assert!(config.validation_enabled());
+        assert_eq!(
+            config.validation_features,
+            [
+                ValidationFeature::Synchronization,
+                ValidationFeature::DebugPrintf
+            ]
+        );
+        assert_eq!(config.log_level, Level::Info);
+
+        let config = AppConfig::parse(
+            args(&["--validation-features=debug-printf", "--log-level", "error"]),
+            no_env,
+        )
+        .unwrap();
+        assert_eq!(config.log_level, Level::Error);
+
+        let env = |key: &str| (key == VALIDATION_ENV).then(|| String::from("off"));
+        assert!(!AppConfig::parse(args(&[]), env)
+            .unwrap()
+            .validation_enabled());
+        assert!(AppConfig::parse(args(&["--validation"]), env)
+            .unwrap()
+            .validation_enabled());
+        assert!(!AppConfig::parse(args(&["--no-validation"]), no_env)
+            .unwrap()
+            .validation_enabled());
+
+        let env = |key: &str| (key == VALIDATION_ENV).then(|| String::from("maybe"));
+        assert!(AppConfig::parse(args(&[]), env).is_err());
+        assert!(AppConfig::parse(
+            args(&["--validation-features", "gpu-assisted,printf"]),
+            no_env
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn parse_bindings() {
+        let no_env = |_: &str| None;
+        let button = |name: &str| name.parse::<Button>().unwrap();
+
+        assert_eq!(
+            AppConfig::parse(args(&[]), no_env).unwrap().bindings,
+            Bindings::default()
+        );
+
+        let env = |key: &str| (key == BINDINGS_ENV).then(|| String::from("vsync=f2;camera=tab"));
+        let config = AppConfig::parse(args(&["--bind", "camera=c,gamepad_y"]), env).unwrap();
+        assert_eq!(config.bindings.buttons(Action::ToggleVsync), [button("f2")]);
+        assert_eq!(
+            config.bindings.buttons(Action::ToggleCamera),
+            [button("c"), button("gamepad_y")]
+        );
+
+        assert!(AppConfig::parse(args(&["--bind", "jump=space"]), no_env).is_err());
+        assert!(AppConfig::parse(args(&["--bind=look"]), no_env).is_err());
+    }
+}
@@ -0,0 +1,233 @@
+use std::ffi::CString;
+
+use crate::ffi;
+use crate::image::AllocatedImage;
+
+/// Vulkan handles that can be given a debug name.
+pub trait DebugHandle: Copy {
+    const OBJECT_TYPE: ffi::VkObjectType;
+
+    fn raw(self) -> u64;
+}
+
+macro_rules! debug_handles {
+    ($($handle:ident => $object_type:ident),* $(,)?) => {
+        $(
+            impl DebugHandle for ffi::$handle {
+                const OBJECT_TYPE: ffi::VkObjectType = ffi::$object_type;
+
+                fn raw(self) -> u64 {
+                    self as usize as u64
+                }
+            }
+        )*
+
+        /// Handle type name for an object type reported in a validation message.
+        pub fn object_type_name(object_type: ffi::VkObjectType) -> &'static str {
+            match object_type {
+                $(ffi::$object_type => stringify!($handle),)*
+                _ => "object",
+            }
+        }
+    };
+}
+
+debug_handles! {
+    VkBuffer => VkObjectType_VK_OBJECT_TYPE_BUFFER,
+    VkImage => VkObjectType_VK_OBJECT_TYPE_IMAGE,
+    VkImageView => VkObjectType_VK_OBJECT_TYPE_IMAGE_VIEW,
+    VkDeviceMemory => VkObjectType_VK_OBJECT_TYPE_DEVICE_MEMORY,
+    VkSampler => VkObjectType_VK_OBJECT_TYPE_SAMPLER,
+    VkPipeline => VkObjectType_VK_OBJECT_TYPE_PIPELINE,
+    VkPipelineLayout => VkObjectType_VK_OBJECT_TYPE_PIPELINE_LAYOUT,
+    VkRenderPass => VkObjectType_VK_OBJECT_TYPE_RENDER_PASS,
+    VkFramebuffer => VkObjectType_VK_OBJECT_TYPE_FRAMEBUFFER,
+    VkDescriptorPool => VkObjectType_VK_OBJECT_TYPE_DESCRIPTOR_POOL,
+    VkDescriptorSetLayout => VkObjectType_VK_OBJECT_TYPE_DESCRIPTOR_SET_LAYOUT,
+    VkDescriptorSet => VkObjectType_VK_OBJECT_TYPE_DESCRIPTOR_SET,
+    VkCommandPool => VkObjectType_VK_OBJECT_TYPE_COMMAND_POOL,
+    VkCommandBuffer => VkObjectType_VK_OBJECT_TYPE_COMMAND_BUFFER,
+    VkQueue => VkObjectType_VK_OBJECT_TYPE_QUEUE,
+    VkSemaphore => VkObjectType_VK_OBJECT_TYPE_SEMAPHORE,
+    VkFence => VkObjectType_VK_OBJECT_TYPE_FENCE,
+    VkQueryPool => VkObjectType_VK_OBJECT_TYPE_QUERY_POOL,
+    VkShaderModule => VkObjectType_VK_OBJECT_TYPE_SHADER_MODULE,
+    VkSwapchainKHR => VkObjectType_VK_OBJECT_TYPE_SWAPCHAIN_KHR,
+}
+
+/// Color of a command buffer label, derived from its name so a pass keeps its color across
+/// frames and captures.
+pub fn label_color(name: &str) -> [f32; 4] {
+    // FNV-1a
+    let hash = name.bytes().fold(0x811c9dc5u32, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    });
+    let channel = |shift: u32| 0.35 + 0.65 * ((hash >> shift) & 0xff) as f32 / 255.0;
+
+    [channel(0), channel(8), channel(16), 1.0]
+}
+
+/// Object names and command buffer labels from `VK_EXT_debug_utils`. Every call is a no-op
+/// until `load` found the entry points, so callers don't need to check whether the extension
+/// is enabled.
+pub struct DebugUtils {
+    set_object_name: ffi::PFN_vkSetDebugUtilsObjectNameEXT,
+    cmd_begin_label: ffi::PFN_vkCmdBeginDebugUtilsLabelEXT,
+    cmd_end_label: ffi::PFN_vkCmdEndDebugUtilsLabelEXT,
+    cmd_insert_label: ffi::PFN_vkCmdInsertDebugUtilsLabelEXT,
+}
+
+impl DebugUtils {
+    pub fn new() -> Self {
+        Self {
+            set_object_name: None,
+            cmd_begin_label: None,
+            cmd_end_label: None,
+            cmd_insert_label: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.set_object_name.is_some()
+    }
+
+    /// Only call with an instance created with `VK_EXT_debug_utils` enabled.
+    pub fn load(&mut self, instance: ffi::VkInstance) {
+        let proc_addr = |name: &str| unsafe {
+            ffi::vkGetInstanceProcAddr(instance, name.as_ptr() as *const i8)
+        };
+        unsafe {
+            self.set_object_name = std::mem::transmute::<
+                ffi::PFN_vkVoidFunction,
+                ffi::PFN_vkSetDebugUtilsObjectNameEXT,
+            >(proc_addr("vkSetDebugUtilsObjectNameEXT\x00"));
+            self.cmd_begin_label = std::mem::transmute::<
+                ffi::PFN_vkVoidFunction,
+                ffi::PFN_vkCmdBeginDebugUtilsLabelEXT,
+            >(proc_addr("vkCmdBeginDebugUtilsLabelEXT\x00"));
+            self.cmd_end_label = std::mem::transmute::<
+                ffi::PFN_vkVoidFunction,
+                ffi::PFN_vkCmdEndDebugUtilsLabelEXT,
+            >(proc_addr("vkCmdEndDebugUtilsLabelEXT\x00"));
+            self.cmd_insert_label = std::mem::transmute::<
+                ffi::PFN_vkVoidFunction,
+                ffi::PFN_vkCmdInsertDebugUtilsLabelEXT,
+            >(proc_addr("vkCmdInsertDebugUtilsLabelEXT\x00"));
+        }
+    }
+
+    pub fn set_name<H: DebugHandle>(&self, device: ffi::VkDevice, handle: H, name: &str) {
+        let Some(set_object_name) = self.set_object_name else {
+            return;
+        };
+        if handle.raw() == 0 {
+            return;
+        }
+
+        let name = label_name(name);
+        let name_info = ffi::VkDebugUtilsObjectNameInfoEXT {
+            sType: ffi::VkStructureType_VK_STRUCTURE_TYPE_DEBUG_UTILS_OBJECT_NAME_INFO_EXT,
+            pNext: std::ptr::null(),
+            objectType: H::OBJECT_TYPE,
+            objectHandle: handle.raw(),
+            pObjectName: name.as_ptr(),
+        };
+        // Naming is best effort, a failure only means the name won't show up.
+        unsafe {
+            set_object_name(device, std::ptr::addr_of!(name_info));
+        }
+    }
+
+    /// Names the image, its memory and view as "`name`", "`name` memory" and "`name` view".
+    pub fn set_image_names(&self, device: ffi::VkDevice, image: &AllocatedImage, name: &str) {
+        self.set_name(device, image.image, name);
+        self.set_name(device, image.memory, &format!("{} memory", name));
+        self.set_name(device, image.view, &format!("{} view", name));
+    }
+
+    pub fn begin_label(&self, command_buffer: ffi::VkCommandBuffer, name: &str) {
+        if let Some(cmd_begin_label) = self.cmd_begin_label {
+            let name = label_name(name);
+            let label = label_info(&name);
+            unsafe {
+                cmd_begin_label(command_buffer, std::ptr::addr_of!(label));
+            }
+        }
+    }
+
+    pub fn end_label(&self, command_buffer: ffi::VkCommandBuffer) {
+        if let Some(cmd_end_label) = self.cmd_end_label {
+            unsafe {
+                cmd_end_label(command_buffer);
+            }
+        }
+    }
+
+    /// A single marker instead of a region.
+    #[allow(dead_code)]
+    pub fn insert_label(&self, command_buffer: ffi::VkCommandBuffer, name: &str) {
+        if let Some(cmd_insert_label) = self.cmd_insert_label {
+            let name = label_name(name);
+            let label = label_info(&name);
+            unsafe {
+                cmd_insert_label(command_buffer, std::ptr::addr_of!(label));
+            }
+        }
+    }
+}
+
+fn label_name(name: &str) -> CString {
+    CString::new(name.replace('\0', "")).expect("nul bytes should be removed")
+}
+
+fn label_info(name: &CString) -> ffi::VkDebugUtilsLabelEXT {
+    ffi::VkDebugUtilsLabelEXT {
+        sType: ffi::VkStructureType_VK_STRUCTURE_TYPE_DEBUG_UTILS_LABEL_EXT,
+        pNext: std::ptr::null(),
+        pLabelName: name.as_ptr(),
+        color: label_color(name.to_str().unwrap_or_default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_colors() {
+        assert_eq!(label_color("scene"), label_color("scene"));
+        assert_ne!(label_color("scene"), label_color("post: fxaa"));
+
+        for name in ["", "scene", "particle simulation"] {
+            let color = label_color(name);
+            assert!(color[..3].iter().all(|&c| (0.35..=1.0).contains(&c)));
+            assert_eq!(color[3], 1.0);
+        }
+    }
+
+    #[test]
+    fn disabled_calls_are_noops() {
+        let debug_utils = DebugUtils::new();
+        assert!(!debug_utils.is_enabled());
+
+        let buffer = 0x1234usize as ffi::VkBuffer;
+        assert_eq!(buffer.raw(), 0x1234);
+        assert_eq!(
+            <ffi::VkBuffer as DebugHandle>::OBJECT_TYPE,
+            ffi::VkObjectType_VK_OBJECT_TYPE_BUFFER
+        );
+
+        debug_utils.set_name(std::ptr::null_mut(), buffer, "buffer");
+        debug_utils.begin_label(std::ptr::null_mut(), "pass");
+        debug_utils.end_label(std::ptr::null_mut());
+        assert_eq!(
+            object_type_name(ffi::VkObjectType_VK_OBJECT_TYPE_PIPELINE),
+            "VkPipeline"
+        );
+        assert_eq!(
+            object_type_name(ffi::VkObjectType_VK_OBJECT_TYPE_UNKNOWN),
+            "object"
+        );
+        assert_eq!(label_name("a\0b").as_bytes(), b"ab");
+    }
+}
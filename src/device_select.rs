@@ -0,0 +1,277 @@
+use crate::ffi;
+
+/// Picks a specific physical device instead of the best scoring one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeviceSelector {
+    Index(usize),
+    /// PCI vendor and device IDs, written as `vendor:device` in hex.
+    VendorDevice(u32, u32),
+    /// Case-insensitive substring of the device name.
+    Name(String),
+}
+
+impl DeviceSelector {
+    pub fn matches(&self, device: &DeviceInfo) -> bool {
+        match self {
+            DeviceSelector::Index(index) => device.index == *index,
+            DeviceSelector::VendorDevice(vendor_id, device_id) => {
+                device.vendor_id == *vendor_id && device.device_id == *device_id
+            }
+            DeviceSelector::Name(name) => device
+                .name
+                .to_ascii_lowercase()
+                .contains(&name.to_ascii_lowercase()),
+        }
+    }
+}
+
+impl std::str::FromStr for DeviceSelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(String::from("Empty GPU selector"));
+        }
+
+        if let Ok(index) = s.parse::<usize>() {
+            return Ok(DeviceSelector::Index(index));
+        }
+
+        if let Some((vendor, device)) = s.split_once(':') {
+            if let (Some(vendor_id), Some(device_id)) = (parse_id(vendor), parse_id(device)) {
+                return Ok(DeviceSelector::VendorDevice(vendor_id, device_id));
+            }
+        }
+
+        Ok(DeviceSelector::Name(s.to_owned()))
+    }
+}
+
+fn parse_id(s: &str) -> Option<u32> {
+    let s = s.trim();
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => u32::from_str_radix(s, 16).ok(),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceInfo {
+    pub index: usize,
+    pub name: String,
+    pub device_type: ffi::VkPhysicalDeviceType,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    /// Total size of the device local memory heaps.
+    pub vram_bytes: u64,
+    /// Number of optional features the app can make use of.
+    pub optional_features: u32,
+    /// Why the device can't be used, empty if it is suitable.
+    pub rejections: Vec<String>,
+}
+
+impl DeviceInfo {
+    pub fn is_suitable(&self) -> bool {
+        self.rejections.is_empty()
+    }
+
+    /// Compared in order: device type, then VRAM, then optional feature support.
+    pub fn score(&self) -> (u32, u64, u32) {
+        (
+            device_type_rank(self.device_type),
+            self.vram_bytes,
+            self.optional_features,
+        )
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "[{}] {} ({}, {:04x}:{:04x}, {} MiB)",
+            self.index,
+            self.name,
+            device_type_name(self.device_type),
+            self.vendor_id,
+            self.device_id,
+            self.vram_bytes / (1024 * 1024)
+        )
+    }
+}
+
+pub fn device_type_rank(device_type: ffi::VkPhysicalDeviceType) -> u32 {
+    match device_type {
+        ffi::VkPhysicalDeviceType_VK_PHYSICAL_DEVICE_TYPE_DISCRETE_GPU => 4,
+        ffi::VkPhysicalDeviceType_VK_PHYSICAL_DEVICE_TYPE_INTEGRATED_GPU => 3,
+        ffi::VkPhysicalDeviceType_VK_PHYSICAL_DEVICE_TYPE_VIRTUAL_GPU => 2,
+        ffi::VkPhysicalDeviceType_VK_PHYSICAL_DEVICE_TYPE_CPU => 1,
+        _ => 0,
+    }
+}
+
+pub fn device_type_name(device_type: ffi::VkPhysicalDeviceType) -> &'static str {
+    match device_type {
+        ffi::VkPhysicalDeviceType_VK_PHYSICAL_DEVICE_TYPE_DISCRETE_GPU => "discrete",
+        ffi::VkPhysicalDeviceType_VK_PHYSICAL_DEVICE_TYPE_INTEGRATED_GPU => "integrated",
+        ffi::VkPhysicalDeviceType_VK_PHYSICAL_DEVICE_TYPE_VIRTUAL_GPU => "virtual",
+        ffi::VkPhysicalDeviceType_VK_PHYSICAL_DEVICE_TYPE_CPU => "cpu",
+        _ => "other",
+    }
+}
+
+/// Returns the index into `devices` to use. With a selector the first matching device is used
+/// and it is an error if that device is unsuitable, otherwise the highest scoring suitable one.
+/// An index no device has is matched against the names instead, e.g. `4090`.
+pub fn select_device(
+    devices: &[DeviceInfo],
+    selector: Option<&DeviceSelector>,
+) -> Result<usize, String> {
+    if let Some(selector) = selector {
+        let find = |selector: &DeviceSelector| {
+            devices
+                .iter()
+                .enumerate()
+                .find(|(_, device)| selector.matches(device))
+        };
+        let (idx, device) = find(selector)
+            .or_else(|| match selector {
+                DeviceSelector::Index(index) if *index >= devices.len() => {
+                    find(&DeviceSelector::Name(index.to_string()))
+                }
+                _ => None,
+            })
+            .ok_or_else(|| format!("No GPU matches {:?}!", selector))?;
+
+        if !device.is_suitable() {
+            return Err(format!(
+                "Selected GPU {} is not suitable: {}",
+                device.describe(),
+                device.rejections.join(", ")
+            ));
+        }
+
+        return Ok(idx);
+    }
+
+    devices
+        .iter()
+        .enumerate()
+        .filter(|(_, device)| device.is_suitable())
+        // Reversed so that on equal scores the first enumerated device wins.
+        .rev()
+        .max_by_key(|(_, device)| device.score())
+        .map(|(idx, _)| idx)
+        .ok_or_else(|| String::from("Failed to find a suitable GPU!"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(
+        index: usize,
+        name: &str,
+        device_type: ffi::VkPhysicalDeviceType,
+        vram_mib: u64,
+    ) -> DeviceInfo {
+        DeviceInfo {
+            index,
+            name: name.to_owned(),
+            device_type,
+            vendor_id: 0x1000 + index as u32,
+            device_id: 0x20 + index as u32,
+            vram_bytes: vram_mib * 1024 * 1024,
+            optional_features: 0,
+            rejections: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn parse_selector() {
+        assert_eq!("1".parse(), Ok(DeviceSelector::Index(1)));
+        assert_eq!(
+            "0x10de:0x2684".parse(),
+            Ok(DeviceSelector::VendorDevice(0x10de, 0x2684))
+        );
+        assert_eq!(
+            "1002:73bf".parse(),
+            Ok(DeviceSelector::VendorDevice(0x1002, 0x73bf))
+        );
+        assert_eq!(
+            "GeForce".parse(),
+            Ok(DeviceSelector::Name(String::from("GeForce")))
+        );
+        assert!("  ".parse::<DeviceSelector>().is_err());
+    }
+
+    #[test]
+    fn scoring_and_overrides() {
+        let mut devices = vec![
+            device(
+                0,
+                "llvmpipe",
+                ffi::VkPhysicalDeviceType_VK_PHYSICAL_DEVICE_TYPE_CPU,
+                0,
+            ),
+            device(
+                1,
+                "Intel UHD 770",
+                ffi::VkPhysicalDeviceType_VK_PHYSICAL_DEVICE_TYPE_INTEGRATED_GPU,
+                2048,
+            ),
+            device(
+                2,
+                "Small dGPU",
+                ffi::VkPhysicalDeviceType_VK_PHYSICAL_DEVICE_TYPE_DISCRETE_GPU,
+                2048,
+            ),
+            device(
+                3,
+                "Big dGPU",
+                ffi::VkPhysicalDeviceType_VK_PHYSICAL_DEVICE_TYPE_DISCRETE_GPU,
+                8192,
+            ),
+        ];
+
+        assert_eq!(select_device(&devices, None), Ok(3));
+
+        devices[3]
+            .rejections
+            .push(String::from("missing VK_KHR_swapchain"));
+        assert_eq!(select_device(&devices, None), Ok(2));
+
+        let by_name = DeviceSelector::Name(String::from("intel"));
+        assert_eq!(select_device(&devices, Some(&by_name)), Ok(1));
+        let by_id = DeviceSelector::VendorDevice(0x1000, 0x20);
+        assert_eq!(select_device(&devices, Some(&by_id)), Ok(0));
+        let by_number = DeviceSelector::Index(770);
+        assert_eq!(select_device(&devices, Some(&by_number)), Ok(1));
+
+        // Explicitly picking a rejected or missing device is an error.
+        assert!(select_device(&devices, Some(&DeviceSelector::Index(3))).is_err());
+        assert!(select_device(&devices, Some(&DeviceSelector::Index(9))).is_err());
+
+        for device in devices.iter_mut() {
+            device.rejections.push(String::from("no present support"));
+        }
+        assert!(select_device(&devices, None).is_err());
+    }
+
+    #[test]
+    fn equal_scores_keep_enumeration_order() {
+        let devices = vec![
+            device(
+                0,
+                "A",
+                ffi::VkPhysicalDeviceType_VK_PHYSICAL_DEVICE_TYPE_DISCRETE_GPU,
+                4096,
+            ),
+            device(
+                1,
+                "B",
+                ffi::VkPhysicalDeviceType_VK_PHYSICAL_DEVICE_TYPE_DISCRETE_GPU,
+                4096,
+            ),
+        ];
+        assert_eq!(select_device(&devices, None), Ok(0));
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::HashSet;
+use std::ffi::{CStr, CString};
+
+use crate::ffi;
+
+/// Device features the app knows how to request, across the core and Vulkan 1.1/1.2/1.3
+/// feature structs.
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Feature {
+    MultiDrawIndirect,
+    SampleRateShading,
+    PipelineStatisticsQuery,
+    OcclusionQueryPrecise,
+    FragmentStoresAndAtomics,
+    VertexPipelineStoresAndAtomics,
+    LargePoints,
+    DrawIndirectFirstInstance,
+    SamplerAnisotropy,
+    FillModeNonSolid,
+    WideLines,
+    ShaderDrawParameters,
+    TimelineSemaphore,
+    BufferDeviceAddress,
+    DescriptorIndexing,
+    HostQueryReset,
+    Synchronization2,
+    DynamicRendering,
+    Maintenance4,
+}
+
+/// Expands to a borrow (`&` or `&mut`) of the `VkBool32` backing `$feature` in `$chain`.
+macro_rules! feature_field {
+    ($feature:expr, $chain:expr, $($borrow:tt)+) => {
+        match $feature {
+            Feature::MultiDrawIndirect => $($borrow)+ $chain.features2.features.multiDrawIndirect,
+            Feature::SampleRateShading => $($borrow)+ $chain.features2.features.sampleRateShading,
+            Feature::PipelineStatisticsQuery => {
+                $($borrow)+ $chain.features2.features.pipelineStatisticsQuery
+            }
+            Feature::OcclusionQueryPrecise => {
+                $($borrow)+ $chain.features2.features.occlusionQueryPrecise
+            }
+            Feature::DrawIndirectFirstInstance => {
+                $($borrow)+ $chain.features2.features.drawIndirectFirstInstance
+            }
+            Feature::LargePoints => $($borrow)+ $chain.features2.features.largePoints,
+            Feature::VertexPipelineStoresAndAtomics => {
+                $($borrow)+ $chain.features2.features.vertexPipelineStoresAndAtomics
+            }
+            Feature::FragmentStoresAndAtomics => {
+                $($borrow)+ $chain.features2.features.fragmentStoresAndAtomics
+            }
+            Feature::SamplerAnisotropy => $($borrow)+ $chain.features2.features.samplerAnisotropy,
+            Feature::FillModeNonSolid => $($borrow)+ $chain.features2.features.fillModeNonSolid,
+            Feature::WideLines => $($borrow)+ $chain.features2.features.wideLines,
+            Feature::ShaderDrawParameters => $($borrow)+ $chain.vulkan11.shaderDrawParameters,
+            Feature::TimelineSemaphore => $($borrow)+ $chain.vulkan12.timelineSemaphore,
+            Feature::BufferDeviceAddress => $($borrow)+ $chain.vulkan12.bufferDeviceAddress,
+            Feature::DescriptorIndexing => $($borrow)+ $chain.vulkan12.descriptorIndexing,
+            Feature::HostQueryReset => $($borrow)+ $chain.vulkan12.hostQueryReset,
+            Feature::Synchronization2 => $($borrow)+ $chain.vulkan13.synchronization2,
+            Feature::DynamicRendering => $($borrow)+ $chain.vulkan13.dynamicRendering,
+            Feature::Maintenance4 => $($borrow)+ $chain.vulkan13.maintenance4,
+        }
+    };
+}
+
+impl Feature {
+    /// The Vulkan minor version whose feature struct holds this feature.
+    pub fn minor_version(self) -> u32 {
+        match self {
+            Feature::MultiDrawIndirect
+            | Feature::SampleRateShading
+            | Feature::PipelineStatisticsQuery
+            | Feature::OcclusionQueryPrecise
+            | Feature::FragmentStoresAndAtomics
+            | Feature::VertexPipelineStoresAndAtomics
+            | Feature::LargePoints
+            | Feature::DrawIndirectFirstInstance
+            | Feature::SamplerAnisotropy
+            | Feature::FillModeNonSolid
+            | Feature::WideLines => 0,
+            Feature::ShaderDrawParameters => 1,
+            Feature::TimelineSemaphore
+            | Feature::BufferDeviceAddress
+            | Feature::DescriptorIndexing
+            | Feature::HostQueryReset => 2,
+            Feature::Synchronization2 | Feature::DynamicRendering | Feature::Maintenance4 => 3,
+        }
+    }
+}
+
+/// `VkPhysicalDeviceFeatures2` with the Vulkan 1.1/1.2/1.3 feature structs chained through
+/// `pNext`, up to the API version the device is used with. Boxed so the chain pointers stay
+/// valid.
+pub struct FeatureChain {
+    pub features2: ffi::VkPhysicalDeviceFeatures2,
+    pub vulkan11: ffi::VkPhysicalDeviceVulkan11Features,
+    pub vulkan12: ffi::VkPhysicalDeviceVulkan12Features,
+    pub vulkan13: ffi::VkPhysicalDeviceVulkan13Features,
+    minor_version: u32,
+}
+
+impl FeatureChain {
+    pub fn new(api_version: u32) -> Box<Self> {
+        let mut chain: Box<Self> = Box::new(Self {
+            features2: unsafe { std::mem::zeroed() },
+            vulkan11: unsafe { std::mem::zeroed() },
+            vulkan12: unsafe { std::mem::zeroed() },
+            vulkan13: unsafe { std::mem::zeroed() },
+            minor_version: if ffi::VK_API_VERSION_MAJOR(api_version) > 1 {
+                u32::MAX
+            } else {
+                ffi::VK_API_VERSION_MINOR(api_version)
+            },
+        });
+
+        chain.features2.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_PHYSICAL_DEVICE_FEATURES_2;
+        chain.vulkan11.sType =
+            ffi::VkStructureType_VK_STRUCTURE_TYPE_PHYSICAL_DEVICE_VULKAN_1_1_FEATURES;
+        chain.vulkan12.sType =
+            ffi::VkStructureType_VK_STRUCTURE_TYPE_PHYSICAL_DEVICE_VULKAN_1_2_FEATURES;
+        chain.vulkan13.sType =
+            ffi::VkStructureType_VK_STRUCTURE_TYPE_PHYSICAL_DEVICE_VULKAN_1_3_FEATURES;
+
+        // The per-version structs are only valid on devices supporting that version.
+        let mut next: *mut std::ffi::c_void = std::ptr::null_mut();
+        if chain.minor_version >= 3 {
+            chain.vulkan13.pNext = next;
+            next = std::ptr::addr_of_mut!(chain.vulkan13) as *mut std::ffi::c_void;
+        }
+        if chain.minor_version >= 2 {
+            chain.vulkan12.pNext = next;
+            next = std::ptr::addr_of_mut!(chain.vulkan12) as *mut std::ffi::c_void;
+        }
+        // `VkPhysicalDeviceVulkan11Features` was only added in Vulkan 1.2.
+        if chain.minor_version >= 2 {
+            chain.vulkan11.pNext = next;
+            next = std::ptr::addr_of_mut!(chain.vulkan11) as *mut std::ffi::c_void;
+        }
+        chain.features2.pNext = next;
+
+        chain
+    }
+
+    /// Fills the chain with what `physical_device` supports.
+    pub fn query(physical_device: ffi::VkPhysicalDevice, api_version: u32) -> Box<Self> {
+        let mut chain = Self::new(api_version);
+        unsafe {
+            ffi::vkGetPhysicalDeviceFeatures2(
+                physical_device,
+                std::ptr::addr_of_mut!(chain.features2),
+            );
+        }
+
+        chain
+    }
+
+    fn is_chained(&self, feature: Feature) -> bool {
+        match feature.minor_version() {
+            0 => true,
+            minor => minor.max(2) <= self.minor_version,
+        }
+    }
+
+    pub fn has(&self, feature: Feature) -> bool {
+        self.is_chained(feature) && *feature_field!(feature, self, &) == ffi::VK_TRUE
+    }
+
+    pub fn enable(&mut self, feature: Feature) {
+        if self.is_chained(feature) {
+            *feature_field!(feature, self, &mut) = ffi::VK_TRUE;
+        }
+    }
+}
+
+/// What the app needs from a device. Devices without every required extension and feature
+/// are rejected, optional ones are enabled when supported.
+#[derive(Clone, Debug, Default)]
+pub struct DeviceRequirements {
+    pub required_extensions: Vec<&'static CStr>,
+    pub optional_extensions: Vec<&'static CStr>,
+    pub required_features: Vec<Feature>,
+    pub optional_features: Vec<Feature>,
+}
+
+/// The extensions and features a device was created with.
+#[derive(Clone, Debug, Default)]
+pub struct EnabledFeatures {
+    extensions: Vec<CString>,
+    features: HashSet<Feature>,
+}
+
+impl EnabledFeatures {
+    pub fn has_extension(&self, name: &CStr) -> bool {
+        self.extensions.iter().any(|ext| ext.as_c_str() == name)
+    }
+
+    pub fn has_feature(&self, feature: Feature) -> bool {
+        self.features.contains(&feature)
+    }
+
+    pub fn extension_ptrs(&self) -> Vec<*const std::ffi::c_char> {
+        self.extensions.iter().map(|ext| ext.as_ptr()).collect()
+    }
+
+    pub fn optional_count(&self, requirements: &DeviceRequirements) -> u32 {
+        let extensions = requirements
+            .optional_extensions
+            .iter()
+            .filter(|ext| self.has_extension(ext))
+            .count();
+        let features = requirements
+            .optional_features
+            .iter()
+            .filter(|feature| self.has_feature(**feature))
+            .count();
+
+        (extensions + features) as u32
+    }
+
+    /// A feature chain with exactly the enabled features set, for `VkDeviceCreateInfo::pNext`.
+    pub fn to_chain(&self, api_version: u32) -> Box<FeatureChain> {
+        let mut chain = FeatureChain::new(api_version);
+        for feature in &self.features {
+            chain.enable(*feature);
+        }
+
+        chain
+    }
+}
+
+impl DeviceRequirements {
+    /// Returns what to enable on a device with the given support, or why it can't be used.
+    pub fn resolve(
+        &self,
+        available_extensions: &[CString],
+        supported: &FeatureChain,
+    ) -> Result<EnabledFeatures, Vec<String>> {
+        let mut rejections = Vec::new();
+        let mut enabled = EnabledFeatures::default();
+
+        let available = |ext: &CStr| available_extensions.iter().any(|a| a.as_c_str() == ext);
+
+        let missing_extensions: Vec<String> = self
+            .required_extensions
+            .iter()
+            .filter(|ext| !available(ext))
+            .map(|ext| ext.to_string_lossy().into_owned())
+            .collect();
+        if !missing_extensions.is_empty() {
+            rejections.push(format!(
+                "missing extensions {}",
+                missing_extensions.join(", ")
+            ));
+        }
+
+        let missing_features: Vec<String> = self
+            .required_features
+            .iter()
+            .filter(|feature| !supported.has(**feature))
+            .map(|feature| format!("{:?}", feature))
+            .collect();
+        if !missing_features.is_empty() {
+            rejections.push(format!("missing features {}", missing_features.join(", ")));
+        }
+
+        if !rejections.is_empty() {
+            return Err(rejections);
+        }
+
+        for ext in self
+            .required_extensions
+            .iter()
+            .chain(self.optional_extensions.iter().filter(|ext| available(ext)))
+        {
+            if !enabled.has_extension(ext) {
+                enabled.extensions.push((*ext).to_owned());
+            }
+        }
+
+        for feature in self.required_features.iter().chain(
+            self.optional_features
+                .iter()
+                .filter(|feature| supported.has(**feature)),
+        ) {
+            enabled.features.insert(*feature);
+        }
+
+        Ok(enabled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_version(minor: u32) -> u32 {
+        ffi::VK_MAKE_API_VERSION(0, 1, minor, 0)
+    }
+
+    #[test]
+    fn chain_follows_api_version() {
+        // A 1.1 device doesn't know the Vulkan11Features struct yet.
+        let mut chain = FeatureChain::new(api_version(1));
+        assert!(chain.features2.pNext.is_null());
+        chain.enable(Feature::ShaderDrawParameters);
+        assert!(!chain.has(Feature::ShaderDrawParameters));
+
+        let chain = FeatureChain::new(api_version(2));
+        assert_eq!(
+            chain.features2.pNext,
+            std::ptr::addr_of!(chain.vulkan11) as *mut std::ffi::c_void
+        );
+        assert_eq!(
+            chain.vulkan11.pNext,
+            std::ptr::addr_of!(chain.vulkan12) as *mut std::ffi::c_void
+        );
+        assert!(chain.vulkan12.pNext.is_null());
+
+        let chain = FeatureChain::new(api_version(3));
+        assert_eq!(
+            chain.vulkan11.pNext,
+            std::ptr::addr_of!(chain.vulkan12) as *mut std::ffi::c_void
+        );
+        assert_eq!(
+            chain.vulkan12.pNext,
+            std::ptr::addr_of!(chain.vulkan13) as *mut std::ffi::c_void
+        );
+        assert!(chain.vulkan13.pNext.is_null());
+
+        // Features from newer versions can't be enabled on older devices.
+        let mut chain = FeatureChain::new(api_version(2));
+        chain.enable(Feature::DynamicRendering);
+        chain.enable(Feature::TimelineSemaphore);
+        assert!(!chain.has(Feature::DynamicRendering));
+        assert!(chain.has(Feature::TimelineSemaphore));
+    }
+
+    #[test]
+    fn resolve_requirements() {
+        let requirements = DeviceRequirements {
+            required_extensions: vec![c"VK_KHR_swapchain"],
+            optional_extensions: vec![c"VK_EXT_memory_budget"],
+            required_features: vec![Feature::Synchronization2],
+            optional_features: vec![Feature::MultiDrawIndirect, Feature::WideLines],
+        };
+
+        let mut supported = FeatureChain::new(api_version(3));
+        supported.enable(Feature::Synchronization2);
+        supported.enable(Feature::MultiDrawIndirect);
+
+        let enabled = requirements
+            .resolve(&[CString::from(c"VK_KHR_swapchain")], &supported)
+            .unwrap();
+        assert!(enabled.has_extension(c"VK_KHR_swapchain"));
+        assert!(!enabled.has_extension(c"VK_EXT_memory_budget"));
+        assert!(enabled.has_feature(Feature::Synchronization2));
+        assert!(enabled.has_feature(Feature::MultiDrawIndirect));
+        assert!(!enabled.has_feature(Feature::WideLines));
+        assert_eq!(enabled.optional_count(&requirements), 1);
+        assert_eq!(enabled.extension_ptrs().len(), 1);
+
+        let chain = enabled.to_chain(api_version(3));
+        assert!(chain.has(Feature::MultiDrawIndirect));
+        assert!(!chain.has(Feature::WideLines));
+
+        let mut old_device = FeatureChain::new(api_version(1));
+        old_device.enable(Feature::MultiDrawIndirect);
+        let rejections = requirements.resolve(&[], &old_device).unwrap_err();
+        assert_eq!(
+            rejections,
+            [
+                "missing extensions VK_KHR_swapchain",
+                "missing features Synchronization2"
+            ]
+        );
+    }
+}
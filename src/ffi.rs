@@ -14,3 +14,11 @@ pub fn VK_MAKE_VERSION(major: u32, minor: u32, patch: u32) -> u32 {
 pub fn VK_MAKE_API_VERSION(variant: u32, major: u32, minor: u32, patch: u32) -> u32 {
     (variant << 29) | (major << 22) | (minor << 12) | patch
 }
+
+pub fn VK_API_VERSION_MAJOR(version: u32) -> u32 {
+    (version >> 22) & 0x7F
+}
+
+pub fn VK_API_VERSION_MINOR(version: u32) -> u32 {
+    (version >> 12) & 0x3FF
+}
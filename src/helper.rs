@@ -30,3 +30,7 @@ macro_rules! cleanup_func {
         $hold_name = crate::helper::Cleanup::new($cleanup_fn);
     }
 }
+
+pub fn slice_as_bytes<T: Copy>(slice: &[T]) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(slice.as_ptr() as *const u8, std::mem::size_of_val(slice)) }
+}
@@ -0,0 +1,183 @@
+use crate::ffi;
+
+pub fn find_memory_type(
+    mem_props: &ffi::VkPhysicalDeviceMemoryProperties,
+    type_filter: u32,
+    properties: ffi::VkMemoryPropertyFlags,
+) -> Option<u32> {
+    (0..mem_props.memoryTypeCount).find(|idx| {
+        (type_filter & (1 << idx)) != 0
+            && (mem_props.memoryTypes[*idx as usize].propertyFlags & properties) == properties
+    })
+}
+
+/// A 2D, single-mip device local image together with its memory and a view of the whole image.
+#[derive(Copy, Clone)]
+pub struct AllocatedImage {
+    pub image: ffi::VkImage,
+    pub memory: ffi::VkDeviceMemory,
+    pub view: ffi::VkImageView,
+    pub format: ffi::VkFormat,
+    pub extent: ffi::VkExtent2D,
+}
+
+impl AllocatedImage {
+    pub fn create(
+        device: ffi::VkDevice,
+        mem_props: &ffi::VkPhysicalDeviceMemoryProperties,
+        format: ffi::VkFormat,
+        extent: ffi::VkExtent2D,
+        usage: ffi::VkImageUsageFlags,
+        aspect: ffi::VkImageAspectFlags,
+        samples: ffi::VkSampleCountFlagBits,
+    ) -> Result<Self, String> {
+        let mut image_info: ffi::VkImageCreateInfo = unsafe { std::mem::zeroed() };
+        image_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_IMAGE_CREATE_INFO;
+        image_info.imageType = ffi::VkImageType_VK_IMAGE_TYPE_2D;
+        image_info.format = format;
+        image_info.extent.width = extent.width;
+        image_info.extent.height = extent.height;
+        image_info.extent.depth = 1;
+        image_info.mipLevels = 1;
+        image_info.arrayLayers = 1;
+        image_info.samples = samples;
+        image_info.tiling = ffi::VkImageTiling_VK_IMAGE_TILING_OPTIMAL;
+        image_info.usage = usage;
+        image_info.sharingMode = ffi::VkSharingMode_VK_SHARING_MODE_EXCLUSIVE;
+        image_info.initialLayout = ffi::VkImageLayout_VK_IMAGE_LAYOUT_UNDEFINED;
+
+        let mut allocated = AllocatedImage {
+            image: std::ptr::null_mut(),
+            memory: std::ptr::null_mut(),
+            view: std::ptr::null_mut(),
+            format,
+            extent,
+        };
+
+        let result = unsafe {
+            ffi::vkCreateImage(
+                device,
+                std::ptr::addr_of!(image_info),
+                std::ptr::null(),
+                std::ptr::addr_of_mut!(allocated.image),
+            )
+        };
+        if result != ffi::VkResult_VK_SUCCESS {
+            return Err(String::from("Failed to create image!"));
+        }
+
+        let mut mem_reqs: ffi::VkMemoryRequirements = unsafe { std::mem::zeroed() };
+        unsafe {
+            ffi::vkGetImageMemoryRequirements(
+                device,
+                allocated.image,
+                std::ptr::addr_of_mut!(mem_reqs),
+            );
+        }
+
+        let memory_type_index = match find_memory_type(
+            mem_props,
+            mem_reqs.memoryTypeBits,
+            ffi::VkMemoryPropertyFlagBits_VK_MEMORY_PROPERTY_DEVICE_LOCAL_BIT,
+        ) {
+            Some(idx) => idx,
+            None => {
+                allocated.destroy(device);
+                return Err(String::from(
+                    "Failed to find suitable memory type for image!",
+                ));
+            }
+        };
+
+        let mut alloc_info: ffi::VkMemoryAllocateInfo = unsafe { std::mem::zeroed() };
+        alloc_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_MEMORY_ALLOCATE_INFO;
+        alloc_info.allocationSize = mem_reqs.size;
+        alloc_info.memoryTypeIndex = memory_type_index;
+
+        let result = unsafe {
+            ffi::vkAllocateMemory(
+                device,
+                std::ptr::addr_of!(alloc_info),
+                std::ptr::null(),
+                std::ptr::addr_of_mut!(allocated.memory),
+            )
+        };
+        if result != ffi::VkResult_VK_SUCCESS {
+            allocated.destroy(device);
+            return Err(String::from("Failed to allocate image memory!"));
+        }
+
+        unsafe {
+            ffi::vkBindImageMemory(device, allocated.image, allocated.memory, 0);
+        }
+
+        let mut view_info: ffi::VkImageViewCreateInfo = unsafe { std::mem::zeroed() };
+        view_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_IMAGE_VIEW_CREATE_INFO;
+        view_info.image = allocated.image;
+        view_info.viewType = ffi::VkImageViewType_VK_IMAGE_VIEW_TYPE_2D;
+        view_info.format = format;
+        view_info.components.r = ffi::VkComponentSwizzle_VK_COMPONENT_SWIZZLE_IDENTITY;
+        view_info.components.g = ffi::VkComponentSwizzle_VK_COMPONENT_SWIZZLE_IDENTITY;
+        view_info.components.b = ffi::VkComponentSwizzle_VK_COMPONENT_SWIZZLE_IDENTITY;
+        view_info.components.a = ffi::VkComponentSwizzle_VK_COMPONENT_SWIZZLE_IDENTITY;
+        view_info.subresourceRange.aspectMask = aspect;
+        view_info.subresourceRange.baseMipLevel = 0;
+        view_info.subresourceRange.levelCount = 1;
+        view_info.subresourceRange.baseArrayLayer = 0;
+        view_info.subresourceRange.layerCount = 1;
+
+        let result = unsafe {
+            ffi::vkCreateImageView(
+                device,
+                std::ptr::addr_of!(view_info),
+                std::ptr::null(),
+                std::ptr::addr_of_mut!(allocated.view),
+            )
+        };
+        if result != ffi::VkResult_VK_SUCCESS {
+            allocated.destroy(device);
+            return Err(String::from("Failed to create image view!"));
+        }
+
+        Ok(allocated)
+    }
+
+    pub fn destroy(&self, device: ffi::VkDevice) {
+        unsafe {
+            if !self.view.is_null() {
+                ffi::vkDestroyImageView(device, self.view, std::ptr::null());
+            }
+            if !self.image.is_null() {
+                ffi::vkDestroyImage(device, self.image, std::ptr::null());
+            }
+            if !self.memory.is_null() {
+                ffi::vkFreeMemory(device, self.memory, std::ptr::null());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_type_selection() {
+        let mut mem_props: ffi::VkPhysicalDeviceMemoryProperties = unsafe { std::mem::zeroed() };
+        mem_props.memoryTypeCount = 3;
+        mem_props.memoryTypes[0].propertyFlags =
+            ffi::VkMemoryPropertyFlagBits_VK_MEMORY_PROPERTY_HOST_VISIBLE_BIT;
+        mem_props.memoryTypes[1].propertyFlags =
+            ffi::VkMemoryPropertyFlagBits_VK_MEMORY_PROPERTY_DEVICE_LOCAL_BIT;
+        mem_props.memoryTypes[2].propertyFlags =
+            ffi::VkMemoryPropertyFlagBits_VK_MEMORY_PROPERTY_DEVICE_LOCAL_BIT
+                | ffi::VkMemoryPropertyFlagBits_VK_MEMORY_PROPERTY_HOST_VISIBLE_BIT;
+
+        let device_local = ffi::VkMemoryPropertyFlagBits_VK_MEMORY_PROPERTY_DEVICE_LOCAL_BIT;
+        assert_eq!(find_memory_type(&mem_props, 0b111, device_local), Some(1));
+        assert_eq!(find_memory_type(&mem_props, 0b100, device_local), Some(2));
+        assert_eq!(find_memory_type(&mem_props, 0b001, device_local), None);
+        // Types past memoryTypeCount are never picked.
+        assert_eq!(find_memory_type(&mem_props, 0b1000, 0), None);
+    }
+}
@@ -0,0 +1,125 @@
+use crate::ffi;
+
+/// A mesh living inside the shared vertex/index buffers.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MeshRange {
+    pub first_index: u32,
+    pub index_count: u32,
+    pub vertex_offset: i32,
+}
+
+/// CPU side list of indexed draws that gets uploaded into an indirect buffer.
+#[derive(Clone, Default)]
+pub struct DrawBatch {
+    commands: Vec<ffi::VkDrawIndexedIndirectCommand>,
+}
+
+impl DrawBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, mesh: MeshRange, first_instance: u32, instance_count: u32) {
+        self.commands.push(ffi::VkDrawIndexedIndirectCommand {
+            indexCount: mesh.index_count,
+            instanceCount: instance_count,
+            firstIndex: mesh.first_index,
+            vertexOffset: mesh.vertex_offset,
+            firstInstance: first_instance,
+        });
+    }
+
+    pub fn commands(&self) -> &[ffi::VkDrawIndexedIndirectCommand] {
+        &self.commands
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    #[allow(dead_code)]
+    pub fn instance_count(&self) -> u32 {
+        self.commands.iter().map(|cmd| cmd.instanceCount).sum()
+    }
+}
+
+pub const fn command_stride() -> u32 {
+    std::mem::size_of::<ffi::VkDrawIndexedIndirectCommand>() as u32
+}
+
+/// Issues `draw_count` commands from `buffer`. Without the `multiDrawIndirect` feature
+/// `drawCount` may only be 0 or 1, so each command gets its own call instead.
+pub fn record_draws(
+    command_buffer: ffi::VkCommandBuffer,
+    buffer: ffi::VkBuffer,
+    draw_count: u32,
+    multi_draw_indirect: bool,
+) {
+    if draw_count == 0 {
+        return;
+    }
+
+    unsafe {
+        if multi_draw_indirect {
+            ffi::vkCmdDrawIndexedIndirect(command_buffer, buffer, 0, draw_count, command_stride());
+        } else {
+            for idx in 0..draw_count {
+                record_draw(command_buffer, buffer, idx);
+            }
+        }
+    }
+}
+
+/// Records only the command at `index`, e.g. to wrap a single draw in a query.
+pub fn record_draw(command_buffer: ffi::VkCommandBuffer, buffer: ffi::VkBuffer, index: u32) {
+    unsafe {
+        ffi::vkCmdDrawIndexedIndirect(
+            command_buffer,
+            buffer,
+            (index * command_stride()) as ffi::VkDeviceSize,
+            1,
+            command_stride(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch_commands() {
+        let quad = MeshRange {
+            first_index: 0,
+            index_count: 6,
+            vertex_offset: 0,
+        };
+        let triangle = MeshRange {
+            first_index: 6,
+            index_count: 3,
+            vertex_offset: 4,
+        };
+
+        let mut batch = DrawBatch::new();
+        assert!(batch.is_empty());
+        batch.push(quad, 0, 10);
+        batch.push(triangle, 10, 5);
+
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch.instance_count(), 15);
+
+        let cmd = batch.commands()[1];
+        assert_eq!(cmd.indexCount, 3);
+        assert_eq!(cmd.firstIndex, 6);
+        assert_eq!(cmd.vertexOffset, 4);
+        assert_eq!(cmd.firstInstance, 10);
+        assert_eq!(cmd.instanceCount, 5);
+
+        assert_eq!(command_stride(), 20);
+    }
+}
@@ -0,0 +1,632 @@
+use std::collections::{HashMap, HashSet};
+use std::ffi::{c_void, CStr};
+
+use crate::ffi;
+
+pub const GAMEPAD_BUTTON_COUNT: usize = ffi::GLFW_GAMEPAD_BUTTON_LAST as usize + 1;
+pub const GAMEPAD_AXIS_COUNT: usize = ffi::GLFW_GAMEPAD_AXIS_LAST as usize + 1;
+
+/// Stick values below this are treated as centered.
+const GAMEPAD_DEADZONE: f32 = 0.15;
+
+/// A physical key or button, using GLFW's codes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Button {
+    Key(i32),
+    Mouse(i32),
+    Gamepad(i32),
+}
+
+const NAMED_KEYS: [(&str, u32); 15] = [
+    ("space", ffi::GLFW_KEY_SPACE),
+    ("escape", ffi::GLFW_KEY_ESCAPE),
+    ("enter", ffi::GLFW_KEY_ENTER),
+    ("tab", ffi::GLFW_KEY_TAB),
+    ("backspace", ffi::GLFW_KEY_BACKSPACE),
+    ("up", ffi::GLFW_KEY_UP),
+    ("down", ffi::GLFW_KEY_DOWN),
+    ("left", ffi::GLFW_KEY_LEFT),
+    ("right", ffi::GLFW_KEY_RIGHT),
+    ("lshift", ffi::GLFW_KEY_LEFT_SHIFT),
+    ("rshift", ffi::GLFW_KEY_RIGHT_SHIFT),
+    ("lctrl", ffi::GLFW_KEY_LEFT_CONTROL),
+    ("rctrl", ffi::GLFW_KEY_RIGHT_CONTROL),
+    ("lalt", ffi::GLFW_KEY_LEFT_ALT),
+    ("ralt", ffi::GLFW_KEY_RIGHT_ALT),
+];
+
+const MOUSE_BUTTONS: [(&str, u32); 3] = [
+    ("mouse_left", ffi::GLFW_MOUSE_BUTTON_LEFT),
+    ("mouse_right", ffi::GLFW_MOUSE_BUTTON_RIGHT),
+    ("mouse_middle", ffi::GLFW_MOUSE_BUTTON_MIDDLE),
+];
+
+const GAMEPAD_BUTTONS: [(&str, u32); 14] = [
+    ("gamepad_a", ffi::GLFW_GAMEPAD_BUTTON_A),
+    ("gamepad_b", ffi::GLFW_GAMEPAD_BUTTON_B),
+    ("gamepad_x", ffi::GLFW_GAMEPAD_BUTTON_X),
+    ("gamepad_y", ffi::GLFW_GAMEPAD_BUTTON_Y),
+    ("gamepad_lb", ffi::GLFW_GAMEPAD_BUTTON_LEFT_BUMPER),
+    ("gamepad_rb", ffi::GLFW_GAMEPAD_BUTTON_RIGHT_BUMPER),
+    ("gamepad_back", ffi::GLFW_GAMEPAD_BUTTON_BACK),
+    ("gamepad_start", ffi::GLFW_GAMEPAD_BUTTON_START),
+    ("gamepad_ls", ffi::GLFW_GAMEPAD_BUTTON_LEFT_THUMB),
+    ("gamepad_rs", ffi::GLFW_GAMEPAD_BUTTON_RIGHT_THUMB),
+    ("gamepad_up", ffi::GLFW_GAMEPAD_BUTTON_DPAD_UP),
+    ("gamepad_right", ffi::GLFW_GAMEPAD_BUTTON_DPAD_RIGHT),
+    ("gamepad_down", ffi::GLFW_GAMEPAD_BUTTON_DPAD_DOWN),
+    ("gamepad_left", ffi::GLFW_GAMEPAD_BUTTON_DPAD_LEFT),
+];
+
+impl std::str::FromStr for Button {
+    type Err = String;
+
+    /// Letters, digits, `f1`-`f12`, the names in `NAMED_KEYS`, `mouse_*` and `gamepad_*`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        let lookup = |table: &[(&str, u32)]| {
+            table
+                .iter()
+                .find(|(entry, _)| *entry == name)
+                .map(|(_, code)| *code as i32)
+        };
+
+        if let [c] = name.as_bytes() {
+            if c.is_ascii_alphanumeric() {
+                // GLFW key codes for letters and digits are their uppercase ASCII values.
+                return Ok(Button::Key(c.to_ascii_uppercase() as i32));
+            }
+        }
+        if let Some(code) = lookup(&NAMED_KEYS) {
+            return Ok(Button::Key(code));
+        }
+        if let Some(n) = name.strip_prefix('f').and_then(|n| n.parse::<u32>().ok()) {
+            if (1..=12).contains(&n) {
+                return Ok(Button::Key((ffi::GLFW_KEY_F1 + n - 1) as i32));
+            }
+        }
+        if let Some(code) = lookup(&MOUSE_BUTTONS) {
+            return Ok(Button::Mouse(code));
+        }
+        if let Some(code) = lookup(&GAMEPAD_BUTTONS) {
+            return Ok(Button::Gamepad(code));
+        }
+
+        Err(format!("Unknown key or button \"{}\"", s))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    ToggleVsync,
+    ToggleCamera,
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    /// Held to turn cursor movement into camera rotation.
+    Look,
+    PrintGpuTimings,
+    PrintGpuStats,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::ToggleVsync,
+        Action::ToggleCamera,
+        Action::MoveForward,
+        Action::MoveBack,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Look,
+        Action::PrintGpuTimings,
+        Action::PrintGpuStats,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::ToggleVsync => "vsync",
+            Action::ToggleCamera => "camera",
+            Action::MoveForward => "forward",
+            Action::MoveBack => "back",
+            Action::MoveLeft => "left",
+            Action::MoveRight => "right",
+            Action::Look => "look",
+            Action::PrintGpuTimings => "gpu-timings",
+            Action::PrintGpuStats => "gpu-stats",
+        }
+    }
+
+    fn default_buttons(self) -> Vec<Button> {
+        let key = |code: u32| Button::Key(code as i32);
+        match self {
+            Action::ToggleVsync => vec![key(ffi::GLFW_KEY_V)],
+            Action::ToggleCamera => vec![key(ffi::GLFW_KEY_C)],
+            Action::MoveForward => vec![key(ffi::GLFW_KEY_W), key(ffi::GLFW_KEY_UP)],
+            Action::MoveBack => vec![key(ffi::GLFW_KEY_S), key(ffi::GLFW_KEY_DOWN)],
+            Action::MoveLeft => vec![key(ffi::GLFW_KEY_A), key(ffi::GLFW_KEY_LEFT)],
+            Action::MoveRight => vec![key(ffi::GLFW_KEY_D), key(ffi::GLFW_KEY_RIGHT)],
+            Action::Look => vec![Button::Mouse(ffi::GLFW_MOUSE_BUTTON_LEFT as i32)],
+            Action::PrintGpuTimings => vec![key(ffi::GLFW_KEY_P)],
+            Action::PrintGpuStats => vec![key(ffi::GLFW_KEY_I)],
+        }
+    }
+}
+
+impl std::str::FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        Action::ALL
+            .into_iter()
+            .find(|action| action.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Action::ALL.iter().map(|action| action.name()).collect();
+                format!("Unknown action \"{}\", expected {}", s, names.join(", "))
+            })
+    }
+}
+
+/// Which buttons trigger each action.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bindings(pub HashMap<Action, Vec<Button>>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self(
+            Action::ALL
+                .into_iter()
+                .map(|action| (action, action.default_buttons()))
+                .collect(),
+        )
+    }
+}
+
+impl Bindings {
+    /// Applies `action=button[,button...]`, replacing the action's buttons. An empty list unbinds
+    /// it.
+    pub fn bind(&mut self, spec: &str) -> Result<(), String> {
+        let (action, buttons) = spec.split_once('=').ok_or_else(|| {
+            format!(
+                "Invalid binding \"{}\", expected <action>=<key>[,<key>...]",
+                spec
+            )
+        })?;
+        let action: Action = action.parse()?;
+        let buttons = buttons
+            .split(',')
+            .filter(|name| !name.trim().is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<Button>, String>>()?;
+
+        self.0.insert(action, buttons);
+        Ok(())
+    }
+
+    /// Applies several `;` separated bindings.
+    pub fn bind_all(&mut self, specs: &str) -> Result<(), String> {
+        specs
+            .split(';')
+            .filter(|spec| !spec.trim().is_empty())
+            .try_for_each(|spec| self.bind(spec))
+    }
+
+    pub fn buttons(&self, action: Action) -> &[Button] {
+        self.0.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+/// Button and cursor state accumulated from GLFW events. `pressed` and `released` only hold the
+/// transitions since the last `end_frame`, so a tap shorter than a frame is still seen.
+pub struct Input {
+    pub bindings: Bindings,
+    held: HashSet<Button>,
+    pressed: HashSet<Button>,
+    released: HashSet<Button>,
+    cursor: Option<[f64; 2]>,
+    cursor_delta: [f32; 2],
+    scroll: [f32; 2],
+    text: String,
+    gamepad_buttons: [bool; GAMEPAD_BUTTON_COUNT],
+    gamepad_axes: [f32; GAMEPAD_AXIS_COUNT],
+}
+
+impl Input {
+    pub fn new(bindings: Bindings) -> Self {
+        Self {
+            bindings,
+            held: HashSet::new(),
+            pressed: HashSet::new(),
+            released: HashSet::new(),
+            cursor: None,
+            cursor_delta: [0.0, 0.0],
+            scroll: [0.0, 0.0],
+            text: String::new(),
+            gamepad_buttons: [false; GAMEPAD_BUTTON_COUNT],
+            gamepad_axes: [0.0; GAMEPAD_AXIS_COUNT],
+        }
+    }
+
+    /// `action` is `GLFW_PRESS`, `GLFW_RELEASE` or `GLFW_REPEAT`, which is ignored.
+    pub fn handle_button(&mut self, button: Button, action: i32) {
+        if action == ffi::GLFW_PRESS as i32 {
+            if self.held.insert(button) {
+                self.pressed.insert(button);
+            }
+        } else if action == ffi::GLFW_RELEASE as i32 && self.held.remove(&button) {
+            self.released.insert(button);
+        }
+    }
+
+    pub fn handle_cursor(&mut self, x: f64, y: f64) {
+        if let Some([last_x, last_y]) = self.cursor {
+            self.cursor_delta[0] += (x - last_x) as f32;
+            self.cursor_delta[1] += (y - last_y) as f32;
+        }
+        self.cursor = Some([x, y]);
+    }
+
+    /// The next cursor position only sets the origin again, e.g. after the cursor mode changed
+    /// and the position jumped.
+    pub fn reset_cursor(&mut self) {
+        self.cursor = None;
+    }
+
+    pub fn handle_scroll(&mut self, x: f64, y: f64) {
+        self.scroll[0] += x as f32;
+        self.scroll[1] += y as f32;
+    }
+
+    pub fn handle_char(&mut self, codepoint: u32) {
+        if let Some(c) = char::from_u32(codepoint) {
+            self.text.push(c);
+        }
+    }
+
+    /// Feeds the latest state of the active gamepad, `None` when none is connected.
+    pub fn handle_gamepad(&mut self, state: Option<&ffi::GLFWgamepadstate>) {
+        let (buttons, axes) = match state {
+            Some(state) => (
+                state.buttons.map(|button| button == ffi::GLFW_PRESS as u8),
+                state.axes.map(|axis| {
+                    if axis.abs() < GAMEPAD_DEADZONE {
+                        0.0
+                    } else {
+                        axis
+                    }
+                }),
+            ),
+            None => ([false; GAMEPAD_BUTTON_COUNT], [0.0; GAMEPAD_AXIS_COUNT]),
+        };
+
+        for (idx, &down) in buttons.iter().enumerate() {
+            if down != self.gamepad_buttons[idx] {
+                let action = if down {
+                    ffi::GLFW_PRESS
+                } else {
+                    ffi::GLFW_RELEASE
+                };
+                self.handle_button(Button::Gamepad(idx as i32), action as i32);
+            }
+        }
+        self.gamepad_buttons = buttons;
+        self.gamepad_axes = axes;
+    }
+
+    /// Polls the first connected gamepad, GLFW has no callbacks for gamepad buttons.
+    pub fn poll_gamepad(&mut self) {
+        let mut state: ffi::GLFWgamepadstate = unsafe { std::mem::zeroed() };
+        let connected = (ffi::GLFW_JOYSTICK_1..=ffi::GLFW_JOYSTICK_LAST).any(|jid| unsafe {
+            ffi::glfwJoystickIsGamepad(jid as i32) != 0
+                && ffi::glfwGetGamepadState(jid as i32, std::ptr::addr_of_mut!(state)) != 0
+        });
+
+        self.handle_gamepad(connected.then_some(&state));
+    }
+
+    /// Clears the per-frame transitions, deltas and text once the frame has consumed them.
+    pub fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.released.clear();
+        self.cursor_delta = [0.0, 0.0];
+        self.scroll = [0.0, 0.0];
+        self.text.clear();
+    }
+
+    pub fn is_held(&self, button: Button) -> bool {
+        self.held.contains(&button)
+    }
+
+    pub fn is_pressed(&self, button: Button) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    #[allow(dead_code)]
+    pub fn is_released(&self, button: Button) -> bool {
+        self.released.contains(&button)
+    }
+
+    pub fn action_held(&self, action: Action) -> bool {
+        self.bindings
+            .buttons(action)
+            .iter()
+            .any(|&button| self.is_held(button))
+    }
+
+    pub fn action_pressed(&self, action: Action) -> bool {
+        self.bindings
+            .buttons(action)
+            .iter()
+            .any(|&button| self.is_pressed(button))
+    }
+
+    #[allow(dead_code)]
+    pub fn action_released(&self, action: Action) -> bool {
+        self.bindings
+            .buttons(action)
+            .iter()
+            .any(|&button| self.is_released(button))
+    }
+
+    /// 1 while only `positive` is held, -1 while only `negative` is.
+    pub fn action_axis(&self, positive: Action, negative: Action) -> f32 {
+        self.action_held(positive) as i32 as f32 - self.action_held(negative) as i32 as f32
+    }
+
+    pub fn cursor_delta(&self) -> [f32; 2] {
+        self.cursor_delta
+    }
+
+    pub fn scroll(&self) -> [f32; 2] {
+        self.scroll
+    }
+
+    /// Characters typed this frame.
+    #[allow(dead_code)]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// `axis` is a `GLFW_GAMEPAD_AXIS_*`, 0 without a gamepad.
+    pub fn gamepad_axis(&self, axis: u32) -> f32 {
+        self.gamepad_axes[axis as usize]
+    }
+}
+
+/// Receives the window events routed by `install_callbacks`.
+pub trait InputHandler {
+    fn input(&mut self) -> &mut Input;
+    fn framebuffer_resized(&mut self, width: i32, height: i32);
+}
+
+/// The only place the window user pointer is turned back into its handler.
+unsafe fn handler<'a, T: InputHandler>(window: *mut ffi::GLFWwindow) -> Option<&'a mut T> {
+    (ffi::glfwGetWindowUserPointer(window) as *mut T).as_mut()
+}
+
+/// Points the window's user pointer at `target` and routes every GLFW callback to it.
+///
+/// # Safety
+/// `target` must stay valid and not move for as long as the window exists.
+pub unsafe fn install_callbacks<T: InputHandler>(window: *mut ffi::GLFWwindow, target: *mut T) {
+    ffi::glfwSetWindowUserPointer(window, target as *mut c_void);
+    ffi::glfwSetFramebufferSizeCallback(window, Some(framebuffer_size_callback::<T>));
+    ffi::glfwSetKeyCallback(window, Some(key_callback::<T>));
+    ffi::glfwSetMouseButtonCallback(window, Some(mouse_button_callback::<T>));
+    ffi::glfwSetCursorPosCallback(window, Some(cursor_pos_callback::<T>));
+    ffi::glfwSetScrollCallback(window, Some(scroll_callback::<T>));
+    ffi::glfwSetCharCallback(window, Some(char_callback::<T>));
+    ffi::glfwSetJoystickCallback(Some(joystick_callback));
+}
+
+extern "C" fn framebuffer_size_callback<T: InputHandler>(
+    window: *mut ffi::GLFWwindow,
+    width: i32,
+    height: i32,
+) {
+    if let Some(handler) = unsafe { handler::<T>(window) } {
+        handler.framebuffer_resized(width, height);
+    }
+}
+
+extern "C" fn key_callback<T: InputHandler>(
+    window: *mut ffi::GLFWwindow,
+    key: i32,
+    _scancode: i32,
+    action: i32,
+    _mods: i32,
+) {
+    if let Some(handler) = unsafe { handler::<T>(window) } {
+        handler.input().handle_button(Button::Key(key), action);
+    }
+}
+
+extern "C" fn mouse_button_callback<T: InputHandler>(
+    window: *mut ffi::GLFWwindow,
+    button: i32,
+    action: i32,
+    _mods: i32,
+) {
+    if let Some(handler) = unsafe { handler::<T>(window) } {
+        handler.input().handle_button(Button::Mouse(button), action);
+    }
+}
+
+extern "C" fn cursor_pos_callback<T: InputHandler>(window: *mut ffi::GLFWwindow, x: f64, y: f64) {
+    if let Some(handler) = unsafe { handler::<T>(window) } {
+        handler.input().handle_cursor(x, y);
+    }
+}
+
+extern "C" fn scroll_callback<T: InputHandler>(window: *mut ffi::GLFWwindow, x: f64, y: f64) {
+    if let Some(handler) = unsafe { handler::<T>(window) } {
+        handler.input().handle_scroll(x, y);
+    }
+}
+
+extern "C" fn char_callback<T: InputHandler>(window: *mut ffi::GLFWwindow, codepoint: u32) {
+    if let Some(handler) = unsafe { handler::<T>(window) } {
+        handler.input().handle_char(codepoint);
+    }
+}
+
+/// Joystick events aren't tied to a window, the state itself is polled in `poll_gamepad`.
+extern "C" fn joystick_callback(jid: i32, event: i32) {
+    if event == ffi::GLFW_CONNECTED as i32 {
+        let name = unsafe { ffi::glfwGetGamepadName(jid) };
+        if !name.is_null() {
+            let name = unsafe { CStr::from_ptr(name) };
+            println!("Gamepad connected: {}", name.to_string_lossy());
+        }
+    } else {
+        println!("Gamepad {} disconnected", jid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRESS: i32 = ffi::GLFW_PRESS as i32;
+    const RELEASE: i32 = ffi::GLFW_RELEASE as i32;
+    const REPEAT: i32 = ffi::GLFW_REPEAT as i32;
+
+    fn key(code: u32) -> Button {
+        Button::Key(code as i32)
+    }
+
+    #[test]
+    fn parse_buttons() {
+        assert_eq!("w".parse(), Ok(key(ffi::GLFW_KEY_W)));
+        assert_eq!("7".parse(), Ok(key(ffi::GLFW_KEY_7)));
+        assert_eq!("Space".parse(), Ok(key(ffi::GLFW_KEY_SPACE)));
+        assert_eq!("f12".parse(), Ok(key(ffi::GLFW_KEY_F12)));
+        assert_eq!(
+            "mouse_right".parse(),
+            Ok(Button::Mouse(ffi::GLFW_MOUSE_BUTTON_RIGHT as i32))
+        );
+        assert_eq!(
+            "gamepad_start".parse(),
+            Ok(Button::Gamepad(ffi::GLFW_GAMEPAD_BUTTON_START as i32))
+        );
+        assert!("f13".parse::<Button>().is_err());
+        assert!("ww".parse::<Button>().is_err());
+    }
+
+    #[test]
+    fn bindings() {
+        let mut bindings = Bindings::default();
+        assert_eq!(
+            bindings.buttons(Action::ToggleVsync),
+            [key(ffi::GLFW_KEY_V)]
+        );
+
+        bindings
+            .bind_all("vsync=f1, gamepad_y;look=mouse_right")
+            .unwrap();
+        assert_eq!(
+            bindings.buttons(Action::ToggleVsync),
+            [
+                key(ffi::GLFW_KEY_F1),
+                Button::Gamepad(ffi::GLFW_GAMEPAD_BUTTON_Y as i32)
+            ]
+        );
+        assert_eq!(
+            bindings.buttons(Action::Look),
+            [Button::Mouse(ffi::GLFW_MOUSE_BUTTON_RIGHT as i32)]
+        );
+
+        bindings.bind("camera=").unwrap();
+        assert!(bindings.buttons(Action::ToggleCamera).is_empty());
+
+        assert!(bindings.bind("vsync").is_err());
+        assert!(bindings.bind("jump=space").is_err());
+        assert!(bindings.bind("vsync=hyper").is_err());
+    }
+
+    #[test]
+    fn pressed_held_released() {
+        let mut input = Input::new(Bindings::default());
+        let w = key(ffi::GLFW_KEY_W);
+
+        input.handle_button(w, PRESS);
+        input.handle_button(w, REPEAT);
+        assert!(input.is_pressed(w) && input.is_held(w));
+        assert!(input.action_pressed(Action::MoveForward));
+        assert_eq!(
+            input.action_axis(Action::MoveForward, Action::MoveBack),
+            1.0
+        );
+
+        input.end_frame();
+        assert!(!input.is_pressed(w) && input.is_held(w));
+
+        input.handle_button(w, RELEASE);
+        assert!(input.is_released(w) && !input.is_held(w));
+        assert!(input.action_released(Action::MoveForward));
+        input.end_frame();
+        assert!(!input.is_released(w));
+
+        // A tap within one frame is still seen as a press.
+        let v = key(ffi::GLFW_KEY_V);
+        input.handle_button(v, PRESS);
+        input.handle_button(v, RELEASE);
+        assert!(input.action_pressed(Action::ToggleVsync));
+        assert!(!input.action_held(Action::ToggleVsync));
+    }
+
+    #[test]
+    fn cursor_scroll_and_text() {
+        let mut input = Input::new(Bindings::default());
+
+        // The first position only sets the origin.
+        input.handle_cursor(100.0, 100.0);
+        assert_eq!(input.cursor_delta(), [0.0, 0.0]);
+        input.handle_cursor(110.0, 95.0);
+        input.handle_cursor(115.0, 90.0);
+        input.handle_scroll(0.0, 1.0);
+        input.handle_scroll(0.0, 2.0);
+        input.handle_char('h' as u32);
+        input.handle_char('i' as u32);
+        assert_eq!(input.cursor_delta(), [15.0, -10.0]);
+        assert_eq!(input.scroll(), [0.0, 3.0]);
+        assert_eq!(input.text(), "hi");
+
+        input.end_frame();
+        assert_eq!(input.cursor_delta(), [0.0, 0.0]);
+        assert_eq!(input.scroll(), [0.0, 0.0]);
+        assert_eq!(input.text(), "");
+
+        input.reset_cursor();
+        input.handle_cursor(500.0, 20.0);
+        assert_eq!(input.cursor_delta(), [0.0, 0.0]);
+        input.handle_cursor(502.0, 21.0);
+        assert_eq!(input.cursor_delta(), [2.0, 1.0]);
+    }
+
+    #[test]
+    fn gamepad_state() {
+        let mut input = Input::new(Bindings::default());
+        let a = Button::Gamepad(ffi::GLFW_GAMEPAD_BUTTON_A as i32);
+
+        let mut state: ffi::GLFWgamepadstate = unsafe { std::mem::zeroed() };
+        state.buttons[ffi::GLFW_GAMEPAD_BUTTON_A as usize] = ffi::GLFW_PRESS as u8;
+        state.axes[ffi::GLFW_GAMEPAD_AXIS_LEFT_X as usize] = 0.5;
+        state.axes[ffi::GLFW_GAMEPAD_AXIS_LEFT_Y as usize] = 0.1;
+
+        input.handle_gamepad(Some(&state));
+        assert!(input.is_pressed(a));
+        assert_eq!(input.gamepad_axis(ffi::GLFW_GAMEPAD_AXIS_LEFT_X), 0.5);
+        assert_eq!(input.gamepad_axis(ffi::GLFW_GAMEPAD_AXIS_LEFT_Y), 0.0);
+
+        input.end_frame();
+        input.handle_gamepad(Some(&state));
+        assert!(!input.is_pressed(a) && input.is_held(a));
+
+        // Disconnecting releases everything.
+        input.handle_gamepad(None);
+        assert!(input.is_released(a));
+        assert_eq!(input.gamepad_axis(ffi::GLFW_GAMEPAD_AXIS_LEFT_X), 0.0);
+    }
+}
@@ -0,0 +1,108 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Debug,
+    Info,
+    #[default]
+    Warn,
+    Error,
+}
+
+impl Level {
+    const ALL: [Level; 4] = [Level::Debug, Level::Info, Level::Warn, Level::Error];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Debug => "debug",
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+        }
+    }
+}
+
+impl std::fmt::Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl std::str::FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "debug" | "verbose" => Ok(Level::Debug),
+            "info" => Ok(Level::Info),
+            "warn" | "warning" => Ok(Level::Warn),
+            "error" => Ok(Level::Error),
+            _ => Err(format!(
+                "Unknown log level \"{}\", expected debug, info, warn or error",
+                s
+            )),
+        }
+    }
+}
+
+static MIN_LEVEL: AtomicU8 = AtomicU8::new(Level::Warn as u8);
+
+/// Messages below `level` are dropped from then on.
+pub fn set_min_level(level: Level) {
+    MIN_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn min_level() -> Level {
+    Level::ALL[MIN_LEVEL.load(Ordering::Relaxed) as usize]
+}
+
+pub fn enabled(level: Level) -> bool {
+    level >= min_level()
+}
+
+pub fn format_line(level: Level, target: &str, message: &str) -> String {
+    format!(
+        "[{} {}] {}",
+        level.name().to_ascii_uppercase(),
+        target,
+        message
+    )
+}
+
+/// Warnings and errors go to stderr, the rest to stdout.
+pub fn log(level: Level, target: &str, message: &str) {
+    if !enabled(level) {
+        return;
+    }
+
+    let line = format_line(level, target, message);
+    if level >= Level::Warn {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levels() {
+        assert!(Level::Debug < Level::Info && Level::Warn < Level::Error);
+        assert_eq!(Level::default(), Level::Warn);
+        assert_eq!("WARNING".parse::<Level>(), Ok(Level::Warn));
+        assert_eq!("verbose".parse::<Level>(), Ok(Level::Debug));
+        assert!("loud".parse::<Level>().is_err());
+
+        for level in Level::ALL {
+            assert_eq!(level.name().parse::<Level>(), Ok(level));
+            assert_eq!(Level::ALL[level as usize], level);
+        }
+
+        assert_eq!(
+            format_line(Level::Error, "validation", "oops"),
+            "[ERROR validation] oops"
+        );
+    }
+}
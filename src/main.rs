@@ -1,34 +1,120 @@
+mod barrier;
+mod camera;
+mod compute;
+mod config;
+mod debug_utils;
+mod device_select;
+mod features;
 mod ffi;
 mod helper;
+mod image;
+mod indirect;
+mod input;
+mod logger;
 mod math3d;
+mod multisample;
+mod particles;
+mod pipeline;
+mod post_process;
+mod profiler;
+mod queries;
+mod render_graph;
+mod rendering;
+mod swapchain;
+mod timeline;
+mod timing;
+mod validation;
 
 use std::collections::HashSet;
 use std::ffi::{c_void, CStr, CString};
-use std::ops::Deref;
-use std::pin::Pin;
-
-use math3d::Vertex;
-
+use std::time::Instant;
+
+use barrier::{Barriers, Usage};
+use camera::{Camera, CameraController, CameraInput, CameraMode, CameraPushConstants};
+use compute::DescriptorKind;
+use config::AppConfig;
+use debug_utils::DebugUtils;
+use device_select::DeviceInfo;
+use features::{DeviceRequirements, EnabledFeatures, Feature, FeatureChain};
+use image::AllocatedImage;
+use indirect::{DrawBatch, MeshRange};
+use input::{Action, Input, InputHandler};
+use math3d::{InstanceData, Vertex};
+use particles::{EmitterConfig, Particle, ParticleSystem};
+use pipeline::{BlendMode, GraphicsPipelineBuilder};
+use post_process::{ColorLut, PostProcessChain, PostTarget};
+use profiler::GpuProfiler;
+use queries::GpuQueries;
+use render_graph::{
+    CompiledGraph, GraphImage, ImageDesc, ImageSize, RenderGraph, ResourceId, TransientPool,
+};
+use rendering::FramebufferCache;
+use swapchain::{OutputEncoding, PresentModePolicy, RetiredSwapchain};
+use timeline::{Release, TimelineScheduler};
+use timing::{FixedTimestep, FrameClock, FrameLimiter, FrameStats, FRAME_STATS_WINDOW};
+use validation::{ErrorAction, ValidationFeature, ValidationLog};
+
+/// The scene is rendered linear into an HDR target, the last post-process pass encodes it for
+/// the swapchain.
+const SCENE_ENCODING: OutputEncoding = OutputEncoding::Linear;
+
+/// Cursor pixels per second the right stick is worth at full deflection.
+const GAMEPAD_LOOK_SPEED: f32 = 600.0;
+
+const WINDOW_TITLE: &str = "Vulkan";
 const WINDOW_WIDTH: i32 = 800;
 const WINDOW_HEIGHT: i32 = 600;
 
-#[cfg(debug_assertions)]
-const ENABLE_VALIDATION_LAYERS: bool = true;
-#[cfg(not(debug_assertions))]
-const ENABLE_VALIDATION_LAYERS: bool = false;
-
 const VALIDATION_LAYER_STR_0: &str = "VK_LAYER_KHRONOS_validation\x00";
 const VALIDATION_LAYERS: [*const u8; 1] = [VALIDATION_LAYER_STR_0.as_ptr()];
 
-const DYNAMIC_STATES: [ffi::VkDynamicState; 2] = [
-    ffi::VkDynamicState_VK_DYNAMIC_STATE_VIEWPORT,
-    ffi::VkDynamicState_VK_DYNAMIC_STATE_SCISSOR,
-];
+/// The validation features that instrument shaders add a few optional requirements.
+fn device_requirements(config: &AppConfig) -> DeviceRequirements {
+    let mut requirements = DeviceRequirements {
+        required_extensions: vec![
+            CStr::from_bytes_with_nul(ffi::VK_KHR_SWAPCHAIN_EXTENSION_NAME).unwrap(),
+        ],
+        optional_extensions: Vec::new(),
+        required_features: vec![
+            Feature::TimelineSemaphore,
+            Feature::DrawIndirectFirstInstance,
+        ],
+        optional_features: vec![
+            Feature::MultiDrawIndirect,
+            Feature::SampleRateShading,
+            Feature::DynamicRendering,
+            Feature::Synchronization2,
+            Feature::PipelineStatisticsQuery,
+            Feature::OcclusionQueryPrecise,
+            Feature::LargePoints,
+        ],
+    };
+
+    let validation_features = if config.validation_enabled() {
+        config.validation_features.as_slice()
+    } else {
+        &[]
+    };
+    // Core in 1.3, but devices keep advertising it, so enabling it is harmless there.
+    if validation_features.contains(&ValidationFeature::DebugPrintf) {
+        requirements.optional_extensions.push(
+            CStr::from_bytes_with_nul(ffi::VK_KHR_SHADER_NON_SEMANTIC_INFO_EXTENSION_NAME).unwrap(),
+        );
+    }
+    if validation_features.contains(&ValidationFeature::GpuAssisted) {
+        requirements.optional_features.extend(GPU_ASSISTED_FEATURES);
+    }
+
+    requirements
+}
 
-const DEVICE_EXTENSIONS: [*const i8; 1] =
-    [ffi::VK_KHR_SWAPCHAIN_EXTENSION_NAME as *const u8 as *const i8];
+/// GPU-assisted validation writes its results from every shader stage.
+const GPU_ASSISTED_FEATURES: [Feature; 2] = [
+    Feature::VertexPipelineStoresAndAtomics,
+    Feature::FragmentStoresAndAtomics,
+];
 
-const VERTICES: [Vertex; 4] = [
+const VERTICES: [Vertex; 7] = [
     Vertex {
         pos: [-0.5, -0.5],
         color: [1.0, 0.0, 0.0],
@@ -45,9 +131,80 @@ const VERTICES: [Vertex; 4] = [
         pos: [-0.5, 0.5],
         color: [1.0, 1.0, 1.0],
     },
+    Vertex {
+        pos: [0.0, -0.5],
+        color: [1.0, 1.0, 0.0],
+    },
+    Vertex {
+        pos: [0.5, 0.5],
+        color: [0.0, 1.0, 1.0],
+    },
+    Vertex {
+        pos: [-0.5, 0.5],
+        color: [1.0, 0.0, 1.0],
+    },
 ];
 
-const VERTEX_INDICES: [u16; 6] = [0, 1, 2, 2, 3, 0];
+const VERTEX_INDICES: [u16; 9] = [0, 1, 2, 2, 3, 0, 0, 1, 2];
+
+const QUAD_MESH: MeshRange = MeshRange {
+    first_index: 0,
+    index_count: 6,
+    vertex_offset: 0,
+};
+
+const TRIANGLE_MESH: MeshRange = MeshRange {
+    first_index: 6,
+    index_count: 3,
+    vertex_offset: 4,
+};
+
+const VERTEX_ANIM_LOCAL_SIZE: u32 = 64;
+
+/// How often the frame rate in the window title is refreshed.
+const TITLE_REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// How long `main_loop` sleeps waiting for events while the window is minimized, in seconds.
+const MINIMIZED_WAIT_TIMEOUT: f64 = 0.1;
+
+/// The quad is drawn as an `INSTANCE_GRID_SIZE` x `INSTANCE_GRID_SIZE` grid of instances.
+const INSTANCE_GRID_SIZE: u32 = 48;
+
+/// The version `create_instance` asks for, devices can't be used beyond it.
+fn instance_api_version() -> u32 {
+    ffi::VK_MAKE_API_VERSION(0, 1, 3, 0)
+}
+
+fn usable_api_version(device_api_version: u32) -> u32 {
+    device_api_version.min(instance_api_version())
+}
+
+/// With a `layer`, checks the extensions that layer provides instead of the implementation's.
+fn check_instance_extension_support(layer: Option<&CStr>, ext_name: &CStr) -> bool {
+    let layer_name = layer.map_or(std::ptr::null(), CStr::as_ptr);
+    let mut ext_count: u32 = 0;
+    unsafe {
+        ffi::vkEnumerateInstanceExtensionProperties(
+            layer_name,
+            std::ptr::addr_of_mut!(ext_count),
+            std::ptr::null_mut(),
+        );
+    }
+
+    let mut exts: Vec<ffi::VkExtensionProperties> = Vec::with_capacity(ext_count as usize);
+    exts.resize(ext_count as usize, unsafe { std::mem::zeroed() });
+
+    unsafe {
+        ffi::vkEnumerateInstanceExtensionProperties(
+            layer_name,
+            std::ptr::addr_of_mut!(ext_count),
+            exts.as_mut_ptr(),
+        );
+    }
+
+    exts.iter()
+        .any(|ext| unsafe { CStr::from_ptr(ext.extensionName.as_ptr()) } == ext_name)
+}
 
 fn check_validation_layer_support() -> bool {
     let mut layer_count: u32 = 0;
@@ -88,29 +245,6 @@ fn check_validation_layer_support() -> bool {
     true
 }
 
-extern "C" fn validation_debug_callback(
-    _message_severity: ffi::VkDebugUtilsMessageSeverityFlagBitsEXT,
-    _message_type: ffi::VkDebugUtilsMessageTypeFlagsEXT,
-    callback_data: *const ffi::VkDebugUtilsMessengerCallbackDataEXT,
-    _user_data: *mut std::ffi::c_void,
-) -> u32 {
-    let message: &CStr = unsafe { CStr::from_ptr((*callback_data).pMessage) };
-
-    println!(
-        "validation layer: {}",
-        message.to_str().unwrap_or("INVALID UTF-8 STRING")
-    );
-
-    ffi::VK_FALSE
-}
-
-extern "C" fn framebuffer_resize_callback(window: *mut ffi::GLFWwindow, _width: i32, _height: i32) {
-    unsafe {
-        let app: *mut VulkanApp = ffi::glfwGetWindowUserPointer(window) as *mut VulkanApp;
-        (*app).set_resize_flag();
-    }
-}
-
 fn create_debug_utils_messenger_ext(
     instance: ffi::VkInstance,
     create_info: *const ffi::VkDebugUtilsMessengerCreateInfoEXT,
@@ -131,18 +265,6 @@ fn create_debug_utils_messenger_ext(
     }
 }
 
-fn create_debug_messenger_create_info() -> ffi::VkDebugUtilsMessengerCreateInfoEXT {
-    ffi::VkDebugUtilsMessengerCreateInfoEXT {
-        sType: ffi::VkStructureType_VK_STRUCTURE_TYPE_DEBUG_UTILS_MESSENGER_CREATE_INFO_EXT,
-        pNext: std::ptr::null(),
-        flags: 0,
-        messageSeverity: ffi::VkDebugUtilsMessageSeverityFlagBitsEXT_VK_DEBUG_UTILS_MESSAGE_SEVERITY_VERBOSE_BIT_EXT | ffi::VkDebugUtilsMessageSeverityFlagBitsEXT_VK_DEBUG_UTILS_MESSAGE_SEVERITY_WARNING_BIT_EXT | ffi::VkDebugUtilsMessageSeverityFlagBitsEXT_VK_DEBUG_UTILS_MESSAGE_SEVERITY_ERROR_BIT_EXT,
-        messageType: ffi::VkDebugUtilsMessageTypeFlagBitsEXT_VK_DEBUG_UTILS_MESSAGE_TYPE_GENERAL_BIT_EXT | ffi::VkDebugUtilsMessageTypeFlagBitsEXT_VK_DEBUG_UTILS_MESSAGE_TYPE_VALIDATION_BIT_EXT | ffi::VkDebugUtilsMessageTypeFlagBitsEXT_VK_DEBUG_UTILS_MESSAGE_TYPE_PERFORMANCE_BIT_EXT,
-        pfnUserCallback: Some(validation_debug_callback),
-        pUserData: std::ptr::null_mut(),
-    }
-}
-
 struct QueueFamilyIndices {
     graphics_family: Option<u32>,
     present_family: Option<u32>,
@@ -154,6 +276,38 @@ impl QueueFamilyIndices {
     }
 }
 
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+struct VertexAnimPushConstants {
+    time: f32,
+    vertex_count: u32,
+}
+
+/// Passes of the frame graph built in `build_frame_graph`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum FramePass {
+    VertexAnimation,
+    ParticleSimulation,
+    /// Renders into `msaa` and resolves into `color` with MSAA, otherwise into `color`.
+    Scene {
+        color: ResourceId,
+        msaa: Option<ResourceId>,
+    },
+    Post {
+        /// Index into `PostProcessChain::passes`.
+        pass: usize,
+        input: ResourceId,
+        output: ResourceId,
+    },
+}
+
+/// Images of a compiled graph are resolved by `allocate` before any pass is recorded.
+fn graph_image(graph: &RenderGraph<FramePass>, resource: ResourceId) -> GraphImage {
+    graph
+        .image(resource)
+        .expect("graph images should be allocated before recording")
+}
+
 struct SwapChainSupportDetails {
     capabilities: ffi::VkSurfaceCapabilitiesKHR,
     formats: Vec<ffi::VkSurfaceFormatKHR>,
@@ -192,9 +346,12 @@ impl Drop for ShaderModuleWrapper {
 }
 
 struct VulkanApp {
+    config: AppConfig,
     window: *mut ffi::GLFWwindow,
     vk_instance: ffi::VkInstance,
     debug_messenger: ffi::VkDebugUtilsMessengerEXT,
+    debug_utils: DebugUtils,
+    validation: Box<ValidationLog>,
     surface: ffi::VkSurfaceKHR,
     physical_device: ffi::VkPhysicalDevice,
     device: ffi::VkDevice,
@@ -203,30 +360,83 @@ struct VulkanApp {
     swap_chain: ffi::VkSwapchainKHR,
     swap_chain_images: Vec<ffi::VkImage>,
     swap_chain_image_format: ffi::VkFormat,
+    swap_chain_color_space: ffi::VkColorSpaceKHR,
     swap_chain_extent: ffi::VkExtent2D,
     swap_chain_image_views: Vec<ffi::VkImageView>,
     render_pass: ffi::VkRenderPass,
     pipeline_layout: ffi::VkPipelineLayout,
     graphics_pipeline: ffi::VkPipeline,
     swap_chain_framebuffers: Vec<ffi::VkFramebuffer>,
+    msaa_samples: ffi::VkSampleCountFlagBits,
     command_pool: ffi::VkCommandPool,
     command_buffer: ffi::VkCommandBuffer,
     image_available_semaphore: ffi::VkSemaphore,
     render_finished_semaphore: ffi::VkSemaphore,
-    in_flight_fence: ffi::VkFence,
+    timeline: TimelineScheduler,
+    transient_images: TransientPool,
+    graph_framebuffers: FramebufferCache,
+    frame_timeline_value: u64,
     framebuffer_resized: bool,
+    minimized: bool,
+    present_mode_policy: PresentModePolicy,
+    present_mode_changed: bool,
     vertex_buffer: ffi::VkBuffer,
     vertex_buffer_memory: ffi::VkDeviceMemory,
     index_buffer: ffi::VkBuffer,
     index_buffer_memory: ffi::VkDeviceMemory,
+    instance_buffer: ffi::VkBuffer,
+    instance_buffer_memory: ffi::VkDeviceMemory,
+    instance_count: u32,
+    indirect_buffer: ffi::VkBuffer,
+    indirect_buffer_memory: ffi::VkDeviceMemory,
+    draw_batch: DrawBatch,
+    device_requirements: DeviceRequirements,
+    device_api_version: u32,
+    enabled_features: EnabledFeatures,
+    base_vertex_buffer: ffi::VkBuffer,
+    base_vertex_buffer_memory: ffi::VkDeviceMemory,
+    descriptor_pool: ffi::VkDescriptorPool,
+    compute_descriptor_set_layout: ffi::VkDescriptorSetLayout,
+    compute_pipeline_layout: ffi::VkPipelineLayout,
+    compute_pipeline: ffi::VkPipeline,
+    compute_descriptor_set: ffi::VkDescriptorSet,
+    clock: FrameClock,
+    frame_stats: FrameStats,
+    frame_limiter: FrameLimiter,
+    fixed_step: Option<FixedTimestep>,
+    last_title_update: Instant,
+    particles: ParticleSystem,
+    post: PostProcessChain,
+    profiler: GpuProfiler,
+    queries: GpuQueries,
+    camera: Camera,
+    camera_controller: CameraController,
+    input: Input,
 }
 
 impl VulkanApp {
-    fn new() -> Self {
+    fn new(config: AppConfig) -> Self {
         Self {
+            present_mode_policy: config.present_mode,
+            device_requirements: device_requirements(&config),
+            present_mode_changed: false,
+            post: PostProcessChain::new(&config.post_chain, config.post_settings),
+            camera: Camera::default(),
+            camera_controller: CameraController::new(config.camera, &Camera::default()),
+            input: Input::new(config.bindings.clone()),
+            profiler: GpuProfiler::new(config.gpu_trace.is_some()),
+            queries: GpuQueries::new(),
+            frame_limiter: FrameLimiter::new(config.fps_cap),
+            fixed_step: config.fixed_step.map(FixedTimestep::new),
+            validation: Box::new(ValidationLog::new(
+                config.suppress_validation.clone(),
+                config.validation_errors,
+            )),
+            config,
             window: std::ptr::null_mut(),
             vk_instance: std::ptr::null_mut(),
             debug_messenger: std::ptr::null_mut(),
+            debug_utils: DebugUtils::new(),
             surface: std::ptr::null_mut(),
             physical_device: std::ptr::null_mut(),
             device: std::ptr::null_mut(),
@@ -235,27 +445,52 @@ impl VulkanApp {
             swap_chain: std::ptr::null_mut(),
             swap_chain_images: Vec::new(),
             swap_chain_image_format: 0,
+            swap_chain_color_space: 0,
             swap_chain_extent: unsafe { std::mem::zeroed() },
             swap_chain_image_views: Vec::new(),
             render_pass: std::ptr::null_mut(),
             pipeline_layout: std::ptr::null_mut(),
             graphics_pipeline: std::ptr::null_mut(),
             swap_chain_framebuffers: Vec::new(),
+            msaa_samples: ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_1_BIT,
             command_pool: std::ptr::null_mut(),
             command_buffer: std::ptr::null_mut(),
             image_available_semaphore: std::ptr::null_mut(),
             render_finished_semaphore: std::ptr::null_mut(),
-            in_flight_fence: std::ptr::null_mut(),
+            timeline: TimelineScheduler::new(),
+            transient_images: TransientPool::new(),
+            graph_framebuffers: FramebufferCache::new(),
+            frame_timeline_value: 0,
             framebuffer_resized: false,
+            minimized: false,
             vertex_buffer: std::ptr::null_mut(),
             vertex_buffer_memory: std::ptr::null_mut(),
             index_buffer: std::ptr::null_mut(),
             index_buffer_memory: std::ptr::null_mut(),
+            instance_buffer: std::ptr::null_mut(),
+            instance_buffer_memory: std::ptr::null_mut(),
+            instance_count: 0,
+            indirect_buffer: std::ptr::null_mut(),
+            indirect_buffer_memory: std::ptr::null_mut(),
+            draw_batch: DrawBatch::new(),
+            device_api_version: 0,
+            enabled_features: EnabledFeatures::default(),
+            base_vertex_buffer: std::ptr::null_mut(),
+            base_vertex_buffer_memory: std::ptr::null_mut(),
+            descriptor_pool: std::ptr::null_mut(),
+            compute_descriptor_set_layout: std::ptr::null_mut(),
+            compute_pipeline_layout: std::ptr::null_mut(),
+            compute_pipeline: std::ptr::null_mut(),
+            compute_descriptor_set: std::ptr::null_mut(),
+            clock: FrameClock::new(),
+            frame_stats: FrameStats::new(FRAME_STATS_WINDOW),
+            last_title_update: Instant::now(),
+            particles: ParticleSystem::new(EmitterConfig::default()),
         }
     }
 
     fn init_glfw(&mut self) {
-        let app_title = CString::new("Vulkan").unwrap();
+        let app_title = CString::new(WINDOW_TITLE).unwrap();
         unsafe {
             ffi::glfwInit();
             ffi::glfwWindowHint(ffi::GLFW_CLIENT_API as i32, ffi::GLFW_NO_API as i32);
@@ -271,15 +506,15 @@ impl VulkanApp {
                 panic!("ERROR: Failed to create glfw window!");
             }
 
-            ffi::glfwSetWindowUserPointer(self.window, self as *mut Self as *mut c_void);
-            ffi::glfwSetFramebufferSizeCallback(self.window, Some(framebuffer_resize_callback));
+            input::install_callbacks(self.window, self as *mut Self);
         }
+        self.update_cursor_mode();
     }
 
     fn init_vulkan(&mut self) {
         // Check validation layers before creating instance.
-        if ENABLE_VALIDATION_LAYERS && !check_validation_layer_support() {
-            panic!("Validation layers requested, but not available!");
+        if self.config.validation_enabled() && !check_validation_layer_support() {
+            panic!("Validation layers requested, but not available! Run with --no-validation.");
         }
 
         self.create_instance().unwrap();
@@ -287,17 +522,34 @@ impl VulkanApp {
         self.create_surface().unwrap();
         self.pick_physical_device().unwrap();
         self.create_logical_device().unwrap();
-        self.create_swap_chain().unwrap();
+        self.create_profiler().unwrap();
+        self.create_queries().unwrap();
+        self.create_sync_objects().unwrap();
+        self.create_swap_chain(std::ptr::null_mut()).unwrap();
         self.create_image_views().unwrap();
-        self.create_render_pass().unwrap();
+        if !self.uses_dynamic_rendering() {
+            self.create_render_pass().unwrap();
+        }
         self.create_graphics_pipeline()
             .expect("Should be able to set up graphics pipeline");
-        self.create_framebuffers().unwrap();
+        if !self.uses_dynamic_rendering() {
+            self.create_framebuffers().unwrap();
+        }
         self.create_command_pool().unwrap();
         self.create_vertex_buffer().unwrap();
         self.create_index_buffer().unwrap();
+        self.create_instance_buffer().unwrap();
+        self.create_indirect_buffer().unwrap();
+        self.create_descriptor_pool().unwrap();
+        self.create_compute_pipeline()
+            .expect("Should be able to set up compute pipeline");
+        self.create_particle_system()
+            .expect("Should be able to set up particle system");
+        self.create_post_process()
+            .expect("Should be able to set up post-processing");
         self.create_command_buffer().unwrap();
-        self.create_sync_objects().unwrap();
+        self.name_objects();
+        self.name_swap_chain_objects();
     }
 
     fn create_instance(&mut self) -> Result<(), String> {
@@ -310,7 +562,7 @@ impl VulkanApp {
             applicationVersion: ffi::VK_MAKE_VERSION(1, 0, 0),
             pEngineName: engine_name.as_ptr(),
             engineVersion: ffi::VK_MAKE_VERSION(1, 0, 0),
-            apiVersion: ffi::VK_MAKE_API_VERSION(0, 1, 0, 0),
+            apiVersion: instance_api_version(),
         };
 
         // Populate VkInstanceCreateInfo.
@@ -322,17 +574,55 @@ impl VulkanApp {
             exts = ffi::glfwGetRequiredInstanceExtensions(std::ptr::addr_of_mut!(ext_count));
         }
 
-        let mut exts_with_validation: Vec<*const std::ffi::c_char> =
-            Vec::with_capacity(ext_count as usize + 1);
-        let validation_string: *const std::ffi::c_char =
-            ffi::VK_EXT_DEBUG_UTILS_EXTENSION_NAME.as_ptr() as *const i8;
-        if ENABLE_VALIDATION_LAYERS {
-            let exts_slice: &[*const std::ffi::c_char] =
-                unsafe { std::slice::from_raw_parts(exts, ext_count as usize) };
-            for ext in exts_slice {
-                exts_with_validation.push(*ext);
-            }
-            exts_with_validation.push(validation_string);
+        let mut enabled_exts: Vec<*const std::ffi::c_char> =
+            Vec::with_capacity(ext_count as usize + 2);
+        let exts_slice: &[*const std::ffi::c_char] =
+            unsafe { std::slice::from_raw_parts(exts, ext_count as usize) };
+        enabled_exts.extend_from_slice(exts_slice);
+        let validation = self.config.validation_enabled();
+        // Object names and labels show up in captures even without validation.
+        let debug_utils_ext =
+            CStr::from_bytes_with_nul(ffi::VK_EXT_DEBUG_UTILS_EXTENSION_NAME).unwrap();
+        let debug_utils = validation || check_instance_extension_support(None, debug_utils_ext);
+        if debug_utils {
+            enabled_exts.push(debug_utils_ext.as_ptr());
+        }
+
+        // Provided by the validation layer itself.
+        let validation_features_ext =
+            CStr::from_bytes_with_nul(ffi::VK_EXT_VALIDATION_FEATURES_EXTENSION_NAME).unwrap();
+        let validation_layer =
+            CStr::from_bytes_with_nul(VALIDATION_LAYER_STR_0.as_bytes()).unwrap();
+        let feature_enables = validation::feature_enables(&self.config.validation_features);
+        let use_validation_features = validation
+            && !feature_enables.is_empty()
+            && check_instance_extension_support(Some(validation_layer), validation_features_ext);
+        if use_validation_features {
+            enabled_exts.push(validation_features_ext.as_ptr());
+            let names: Vec<&str> = self
+                .config
+                .validation_features
+                .iter()
+                .map(|feature| feature.name())
+                .collect();
+            println!("Validation features: {}", names.join(", "));
+        } else if validation && !feature_enables.is_empty() {
+            println!(
+                "WARNING: {:?} is not available, validation features are ignored",
+                validation_features_ext
+            );
+        }
+
+        // Needed for the HDR10 and scRGB color spaces to be reported by the surface.
+        let colorspace_ext =
+            CStr::from_bytes_with_nul(ffi::VK_EXT_SWAPCHAIN_COLOR_SPACE_EXTENSION_NAME).unwrap();
+        if check_instance_extension_support(None, colorspace_ext) {
+            enabled_exts.push(colorspace_ext.as_ptr());
+        } else if self.config.surface_format.needs_swapchain_colorspace() {
+            println!(
+                "WARNING: {:?} is not available, HDR surface formats can't be used",
+                colorspace_ext
+            );
         }
 
         // Second populate the struct with necessary info.
@@ -343,20 +633,24 @@ impl VulkanApp {
             pApplicationInfo: std::ptr::addr_of!(app_info),
             enabledLayerCount: 0,
             ppEnabledLayerNames: std::ptr::null(),
-            enabledExtensionCount: if ENABLE_VALIDATION_LAYERS {
-                ext_count + 1
-            } else {
-                ext_count
-            },
-            ppEnabledExtensionNames: if ENABLE_VALIDATION_LAYERS {
-                exts_with_validation.as_ptr()
-            } else {
-                exts
-            },
+            enabledExtensionCount: enabled_exts.len() as u32,
+            ppEnabledExtensionNames: enabled_exts.as_ptr(),
         };
 
-        let debug_messenger_create_info = create_debug_messenger_create_info();
-        if ENABLE_VALIDATION_LAYERS {
+        let validation_features = ffi::VkValidationFeaturesEXT {
+            sType: ffi::VkStructureType_VK_STRUCTURE_TYPE_VALIDATION_FEATURES_EXT,
+            pNext: std::ptr::null(),
+            enabledValidationFeatureCount: feature_enables.len() as u32,
+            pEnabledValidationFeatures: feature_enables.as_ptr(),
+            disabledValidationFeatureCount: 0,
+            pDisabledValidationFeatures: std::ptr::null(),
+        };
+        let mut debug_messenger_create_info = self.validation.messenger_create_info();
+        if use_validation_features {
+            debug_messenger_create_info.pNext =
+                std::ptr::addr_of!(validation_features) as *const std::ffi::c_void;
+        }
+        if validation {
             create_info.enabledLayerCount = VALIDATION_LAYERS.len() as u32;
             create_info.ppEnabledLayerNames = VALIDATION_LAYERS.as_ptr() as *const *const i8;
 
@@ -376,11 +670,15 @@ impl VulkanApp {
             return Err(String::from("Failed to create Vulkan instance"));
         }
 
+        if debug_utils {
+            self.debug_utils.load(self.vk_instance);
+        }
+
         Ok(())
     }
 
     fn setup_debug_messenger(&mut self) -> Result<(), String> {
-        if !ENABLE_VALIDATION_LAYERS {
+        if !self.config.validation_enabled() {
             return Ok(());
         }
 
@@ -390,7 +688,7 @@ impl VulkanApp {
             ));
         }
 
-        let create_info = create_debug_messenger_create_info();
+        let create_info = self.validation.messenger_create_info();
 
         let result = create_debug_utils_messenger_ext(
             self.vk_instance,
@@ -430,15 +728,80 @@ impl VulkanApp {
             );
         }
 
-        for phys_dev in phys_dev_handles_vec {
-            if self.is_device_suitable(phys_dev)? {
-                self.physical_device = phys_dev;
-                break;
+        let mut devices: Vec<DeviceInfo> = Vec::with_capacity(phys_dev_handles_vec.len());
+        for (idx, phys_dev) in phys_dev_handles_vec.iter().enumerate() {
+            devices.push(self.query_device_info(idx, *phys_dev)?);
+        }
+
+        if self.config.list_devices {
+            for device in &devices {
+                if device.is_suitable() {
+                    println!("{}: suitable", device.describe());
+                } else {
+                    println!(
+                        "{}: rejected, {}",
+                        device.describe(),
+                        device.rejections.join(", ")
+                    );
+                }
             }
         }
 
-        if self.physical_device.is_null() {
-            return Err(String::from("Failed to find a suitable GPU!"));
+        let idx = device_select::select_device(&devices, self.config.gpu.as_ref())?;
+        self.physical_device = phys_dev_handles_vec[idx];
+
+        let mut dev_props: ffi::VkPhysicalDeviceProperties = unsafe { std::mem::zeroed() };
+        unsafe {
+            ffi::vkGetPhysicalDeviceProperties(
+                self.physical_device,
+                std::ptr::addr_of_mut!(dev_props),
+            );
+        }
+        self.device_api_version = usable_api_version(dev_props.apiVersion);
+        self.enabled_features = self
+            .resolve_device_features(self.physical_device, self.device_api_version)
+            .map_err(|missing| missing.join(", "))?;
+        self.particles.config.point_size = particles::usable_point_size(
+            self.particles.config.point_size,
+            self.enabled_features.has_feature(Feature::LargePoints),
+            dev_props.limits.pointSizeRange,
+        );
+        self.msaa_samples = multisample::choose_sample_count(
+            self.config.msaa,
+            multisample::usable_sample_counts(&dev_props.limits),
+        );
+        println!("Using GPU {}", devices[idx].describe());
+        println!("MSAA: {}x", self.msaa_samples);
+        if self.config.sample_shading.is_some()
+            && !self
+                .enabled_features
+                .has_feature(Feature::SampleRateShading)
+        {
+            println!("WARNING: Sample shading requested, but not supported by the device");
+        }
+        if self.config.validation_enabled()
+            && self
+                .config
+                .validation_features
+                .contains(&ValidationFeature::GpuAssisted)
+            && !GPU_ASSISTED_FEATURES
+                .iter()
+                .all(|feature| self.enabled_features.has_feature(*feature))
+        {
+            println!(
+                "WARNING: GPU-assisted validation needs vertexPipelineStoresAndAtomics and \
+                 fragmentStoresAndAtomics, which the device doesn't support"
+            );
+        }
+
+        Ok(())
+    }
+
+    fn list_devices(&mut self) -> Result<(), String> {
+        self.create_instance()?;
+        self.create_surface()?;
+        if let Err(e) = self.pick_physical_device() {
+            println!("{}", e);
         }
 
         Ok(())
@@ -471,18 +834,22 @@ impl VulkanApp {
             dev_queue_create_infos.push(dev_queue_create_info);
         }
 
-        let phys_dev_feat: ffi::VkPhysicalDeviceFeatures = unsafe { std::mem::zeroed() };
+        let feature_chain = self.enabled_features.to_chain(self.device_api_version);
+        let enabled_extensions = self.enabled_features.extension_ptrs();
 
         let mut dev_create_info: ffi::VkDeviceCreateInfo = unsafe { std::mem::zeroed() };
         dev_create_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_DEVICE_CREATE_INFO;
+        dev_create_info.pNext =
+            std::ptr::addr_of!(feature_chain.features2) as *const std::ffi::c_void;
         dev_create_info.pQueueCreateInfos = dev_queue_create_infos.as_ptr();
         dev_create_info.queueCreateInfoCount = dev_queue_create_infos.len() as u32;
-        dev_create_info.pEnabledFeatures = std::ptr::addr_of!(phys_dev_feat);
+        // Features are passed through the VkPhysicalDeviceFeatures2 chain instead.
+        dev_create_info.pEnabledFeatures = std::ptr::null();
 
-        dev_create_info.ppEnabledExtensionNames = DEVICE_EXTENSIONS.as_ptr();
-        dev_create_info.enabledExtensionCount = DEVICE_EXTENSIONS.len() as u32;
+        dev_create_info.ppEnabledExtensionNames = enabled_extensions.as_ptr();
+        dev_create_info.enabledExtensionCount = enabled_extensions.len() as u32;
 
-        if ENABLE_VALIDATION_LAYERS {
+        if self.config.validation_enabled() {
             dev_create_info.enabledLayerCount = VALIDATION_LAYERS.len() as u32;
             dev_create_info.ppEnabledLayerNames = VALIDATION_LAYERS.as_ptr() as *const *const i8;
         } else {
@@ -549,9 +916,37 @@ impl VulkanApp {
                 if ffi::glfwWindowShouldClose(self.window) != 0 {
                     break 'outer;
                 }
-                ffi::glfwPollEvents();
+                if self.minimized {
+                    // Nothing is rendered, so don't spin while waiting to be restored.
+                    ffi::glfwWaitEventsTimeout(MINIMIZED_WAIT_TIMEOUT);
+                } else {
+                    ffi::glfwPollEvents();
+                }
+            }
+
+            if self.minimized && self.framebuffer_size() != (0, 0) {
+                self.recreate_swap_chain().unwrap();
+            }
+
+            self.update();
+            if !self.minimized {
+                self.draw_frame().unwrap();
+                self.validation.check();
+                self.frame_limiter.wait();
+            }
+        }
+
+        if let Some(summary) = self.frame_stats.summary() {
+            println!("Last {} frames: {}", summary.frames, summary);
+        }
+        if self.config.validation_errors == ErrorAction::Record {
+            println!("{} validation errors", self.validation.error_count());
+        }
+        if let Some(path) = &self.config.gpu_trace {
+            match self.profiler.write_trace(path) {
+                Ok(()) => println!("Wrote GPU trace to {}", path),
+                Err(e) => println!("WARNING: {}", e),
             }
-            self.draw_frame().unwrap();
         }
 
         unsafe {
@@ -559,12 +954,10 @@ impl VulkanApp {
         }
     }
 
-    fn find_queue_families(&self, dev: ffi::VkPhysicalDevice) -> QueueFamilyIndices {
-        let mut queue_fam = QueueFamilyIndices {
-            graphics_family: None,
-            present_family: None,
-        };
-
+    fn queue_family_properties(
+        &self,
+        dev: ffi::VkPhysicalDevice,
+    ) -> Vec<ffi::VkQueueFamilyProperties> {
         let mut queue_family_count: u32 = 0;
         unsafe {
             ffi::vkGetPhysicalDeviceQueueFamilyProperties(
@@ -585,6 +978,16 @@ impl VulkanApp {
             );
         }
 
+        queue_family_props
+    }
+
+    fn find_queue_families(&self, dev: ffi::VkPhysicalDevice) -> QueueFamilyIndices {
+        let mut queue_fam = QueueFamilyIndices {
+            graphics_family: None,
+            present_family: None,
+        };
+
+        let queue_family_props = self.queue_family_properties(dev);
         for (idx, queue_family_prop) in queue_family_props.iter().enumerate() {
             let mut present_support: ffi::VkBool32 = ffi::VK_FALSE;
             unsafe {
@@ -598,7 +1001,11 @@ impl VulkanApp {
             if present_support != ffi::VK_FALSE {
                 queue_fam.present_family = Some(idx as u32);
             }
-            if queue_family_prop.queueFlags & ffi::VkQueueFlagBits_VK_QUEUE_GRAPHICS_BIT != 0 {
+            // Compute work is recorded into the same command buffer as the draw, so the
+            // graphics family must also support compute.
+            let graphics_compute = ffi::VkQueueFlagBits_VK_QUEUE_GRAPHICS_BIT
+                | ffi::VkQueueFlagBits_VK_QUEUE_COMPUTE_BIT;
+            if queue_family_prop.queueFlags & graphics_compute == graphics_compute {
                 queue_fam.graphics_family = Some(idx as u32);
             }
 
@@ -610,42 +1017,81 @@ impl VulkanApp {
         queue_fam
     }
 
-    fn is_device_suitable(&self, dev: ffi::VkPhysicalDevice) -> Result<bool, String> {
+    fn query_device_info(
+        &self,
+        index: usize,
+        dev: ffi::VkPhysicalDevice,
+    ) -> Result<DeviceInfo, String> {
         let mut dev_props: ffi::VkPhysicalDeviceProperties = unsafe { std::mem::zeroed() };
         unsafe {
             ffi::vkGetPhysicalDeviceProperties(dev, std::ptr::addr_of_mut!(dev_props));
         }
 
-        let mut dev_feat: ffi::VkPhysicalDeviceFeatures = unsafe { std::mem::zeroed() };
+        let mut mem_props: ffi::VkPhysicalDeviceMemoryProperties = unsafe { std::mem::zeroed() };
         unsafe {
-            ffi::vkGetPhysicalDeviceFeatures(dev, std::ptr::addr_of_mut!(dev_feat));
+            ffi::vkGetPhysicalDeviceMemoryProperties(dev, std::ptr::addr_of_mut!(mem_props));
         }
+        let vram_bytes = mem_props.memoryHeaps[..mem_props.memoryHeapCount as usize]
+            .iter()
+            .filter(|heap| {
+                heap.flags & ffi::VkMemoryHeapFlagBits_VK_MEMORY_HEAP_DEVICE_LOCAL_BIT != 0
+            })
+            .map(|heap| heap.size)
+            .sum();
 
-        // dev_props.deviceType == ffi::VkPhysicalDeviceType_VK_PHYSICAL_DEVICE_TYPE_DISCRETE_GPU
-        // && dev_feat.geometryShader != 0
-
-        // Use previous checks for specifics, but for now, accept GPUs with required support.
-        let extensions_supported = self.check_device_extensions_support(dev);
+        let mut rejections = Vec::new();
 
-        let mut swap_chain_adequate = false;
-        if extensions_supported {
-            let swap_chain_support = self.query_swap_chain_support(dev)?;
-            swap_chain_adequate = !swap_chain_support.formats.is_empty()
-                && !swap_chain_support.present_modes.is_empty();
+        let queue_families = self.find_queue_families(dev);
+        if queue_families.graphics_family.is_none() {
+            rejections.push(String::from("no queue family with graphics and compute"));
+        }
+        if queue_families.present_family.is_none() {
+            rejections.push(String::from("can't present to the window surface"));
         }
 
-        Ok(self.find_queue_families(dev).is_complete()
-            && extensions_supported
-            && swap_chain_adequate)
+        let mut optional_features = 0;
+        match self.resolve_device_features(dev, usable_api_version(dev_props.apiVersion)) {
+            Ok(enabled) => {
+                optional_features = enabled.optional_count(&self.device_requirements);
+
+                let swap_chain_support = self.query_swap_chain_support(dev)?;
+                if swap_chain_support.formats.is_empty() {
+                    rejections.push(String::from("no surface formats"));
+                }
+                if swap_chain_support.present_modes.is_empty() {
+                    rejections.push(String::from("no present modes"));
+                }
+            }
+            Err(mut missing) => rejections.append(&mut missing),
+        }
+
+        Ok(DeviceInfo {
+            index,
+            name: unsafe { CStr::from_ptr(dev_props.deviceName.as_ptr()) }
+                .to_string_lossy()
+                .into_owned(),
+            device_type: dev_props.deviceType,
+            vendor_id: dev_props.vendorID,
+            device_id: dev_props.deviceID,
+            vram_bytes,
+            optional_features,
+            rejections,
+        })
     }
 
-    fn check_device_extensions_support(&self, dev: ffi::VkPhysicalDevice) -> bool {
-        let mut req_extensions: HashSet<CString> = HashSet::new();
-        for dev_ext in DEVICE_EXTENSIONS {
-            let cstr = unsafe { CStr::from_ptr(dev_ext) };
-            req_extensions.insert(cstr.to_owned());
-        }
+    fn resolve_device_features(
+        &self,
+        dev: ffi::VkPhysicalDevice,
+        api_version: u32,
+    ) -> Result<EnabledFeatures, Vec<String>> {
+        let available_extensions = self.available_device_extensions(dev);
+        let supported = FeatureChain::query(dev, api_version);
+
+        self.device_requirements
+            .resolve(&available_extensions, &supported)
+    }
 
+    fn available_device_extensions(&self, dev: ffi::VkPhysicalDevice) -> Vec<CString> {
         let mut extension_count: u32 = 0;
         unsafe {
             ffi::vkEnumerateDeviceExtensionProperties(
@@ -668,13 +1114,12 @@ impl VulkanApp {
             );
         }
 
-        for available in available_extensions {
-            let cstr = unsafe { CStr::from_ptr(&available.extensionName as *const i8) };
-            let cstring = cstr.to_owned();
-            req_extensions.remove(&cstring);
-        }
-
-        req_extensions.is_empty()
+        available_extensions
+            .iter()
+            .map(|available| {
+                unsafe { CStr::from_ptr(&available.extensionName as *const i8) }.to_owned()
+            })
+            .collect()
     }
 
     fn query_swap_chain_support(
@@ -750,34 +1195,44 @@ impl VulkanApp {
         &self,
         available_formats: &[ffi::VkSurfaceFormatKHR],
     ) -> Option<usize> {
-        if available_formats.is_empty() {
-            return None;
+        let idx = self.config.surface_format.choose(available_formats)?;
+
+        let chosen = &available_formats[idx];
+        let preferred = self.config.surface_format.preferred_formats()[0];
+        if (chosen.format, chosen.colorSpace) != preferred {
+            println!(
+                "WARNING: Surface format \"{}\" is not supported, falling back to format {} with color space {}",
+                self.config.surface_format.name(),
+                chosen.format,
+                chosen.colorSpace
+            );
         }
 
-        for (idx, format) in available_formats.iter().enumerate() {
-            if format.format == ffi::VkFormat_VK_FORMAT_B8G8R8A8_SRGB
-                && format.colorSpace == ffi::VkColorSpaceKHR_VK_COLOR_SPACE_SRGB_NONLINEAR_KHR
-            {
-                return Some(idx);
-            }
-        }
+        Some(idx)
+    }
 
-        Some(0)
+    fn output_encoding(&self) -> OutputEncoding {
+        OutputEncoding::for_surface_format(&ffi::VkSurfaceFormatKHR {
+            format: self.swap_chain_image_format,
+            colorSpace: self.swap_chain_color_space,
+        })
     }
 
     fn choose_swap_present_mode(
         &self,
-        _available_present_modes: &[ffi::VkPresentModeKHR],
+        available_present_modes: &[ffi::VkPresentModeKHR],
     ) -> ffi::VkPresentModeKHR {
-        // Default to FIFO, don't check for MAILBOX.
+        let mode = self.present_mode_policy.choose(available_present_modes);
 
-        //for mode in available_present_modes {
-        //    if *mode == ffi::VkPresentModeKHR_VK_PRESENT_MODE_MAILBOX_KHR {
-        //        return ffi::VkPresentModeKHR_VK_PRESENT_MODE_MAILBOX_KHR;
-        //    }
-        //}
+        if mode != self.present_mode_policy.preferred_modes()[0] {
+            println!(
+                "WARNING: Present mode \"{}\" is not supported, falling back to {}",
+                self.present_mode_policy.name(),
+                swapchain::present_mode_name(mode)
+            );
+        }
 
-        ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_KHR
+        mode
     }
 
     fn choose_swap_extent(&self, capabilities: &ffi::VkSurfaceCapabilitiesKHR) -> ffi::VkExtent2D {
@@ -812,7 +1267,7 @@ impl VulkanApp {
         actual_extent
     }
 
-    fn create_swap_chain(&mut self) -> Result<(), String> {
+    fn create_swap_chain(&mut self, old_swap_chain: ffi::VkSwapchainKHR) -> Result<(), String> {
         let swap_chain_support = self.query_swap_chain_support(self.physical_device)?;
 
         let surface_format_idx = self
@@ -863,7 +1318,8 @@ impl VulkanApp {
         create_info.presentMode = present_mode;
         create_info.clipped = ffi::VK_TRUE;
 
-        create_info.oldSwapchain = std::ptr::null_mut();
+        // Null on first creation. On recreation the old one has already been retired.
+        create_info.oldSwapchain = old_swap_chain;
 
         let result = unsafe {
             ffi::vkCreateSwapchainKHR(
@@ -895,6 +1351,7 @@ impl VulkanApp {
         }
 
         self.swap_chain_image_format = swap_chain_support.formats[surface_format_idx].format;
+        self.swap_chain_color_space = swap_chain_support.formats[surface_format_idx].colorSpace;
         self.swap_chain_extent = extent;
 
         Ok(())
@@ -941,146 +1398,57 @@ impl VulkanApp {
     }
 
     fn create_graphics_pipeline(&mut self) -> Result<(), String> {
-        let vert_shader_module = self.create_vertex_shader_module()?;
-        let frag_shader_module = self.create_fragment_shader_module()?;
-
-        let mut vert_shader_stage_info: ffi::VkPipelineShaderStageCreateInfo =
-            unsafe { std::mem::zeroed() };
-        vert_shader_stage_info.sType =
-            ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_SHADER_STAGE_CREATE_INFO;
-        vert_shader_stage_info.stage = ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_VERTEX_BIT;
-        vert_shader_stage_info.module = vert_shader_module.get_module();
-        vert_shader_stage_info.pName = "main\x00".as_ptr() as *const i8;
-
-        let mut frag_shader_stage_info: ffi::VkPipelineShaderStageCreateInfo =
-            unsafe { std::mem::zeroed() };
-        frag_shader_stage_info.sType =
-            ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_SHADER_STAGE_CREATE_INFO;
-        frag_shader_stage_info.stage = ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_FRAGMENT_BIT;
-        frag_shader_stage_info.module = frag_shader_module.get_module();
-        frag_shader_stage_info.pName = "main\x00".as_ptr() as *const i8;
-
-        let shader_stages: [ffi::VkPipelineShaderStageCreateInfo; 2] =
-            [vert_shader_stage_info, frag_shader_stage_info];
-
-        let (vertex_input_info, _bind_desc, _attr_descs) =
-            Self::create_vertex_input_state_info_struct()?;
-
-        let mut input_assembly: ffi::VkPipelineInputAssemblyStateCreateInfo =
-            unsafe { std::mem::zeroed() };
-        input_assembly.sType =
-            ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_INPUT_ASSEMBLY_STATE_CREATE_INFO;
-        input_assembly.topology = ffi::VkPrimitiveTopology_VK_PRIMITIVE_TOPOLOGY_TRIANGLE_LIST;
-        input_assembly.primitiveRestartEnable = ffi::VK_FALSE;
-
-        let dynamic_state_info_struct = Self::create_dynamic_state_info_struct();
-
-        let viewport_state = Self::create_viewport_state_info_struct();
-
-        let rasterizer_info = Self::create_rasterizer_info_struct();
-
-        let multisampling_info = Self::create_multisampling_info_struct();
-
-        let color_blend_attachment = Self::create_color_blend_attach_state_struct();
-
-        let color_blend_info_struct =
-            Self::create_color_blend_state_info_struct(std::ptr::addr_of!(color_blend_attachment));
+        let vert_shader_module =
+            self.create_shader_module(std::include_bytes!(concat!(env!("OUT_DIR"), "/vert.spv")))?;
+        let frag_shader_module =
+            self.create_shader_module(std::include_bytes!(concat!(env!("OUT_DIR"), "/frag.spv")))?;
+
+        self.pipeline_layout = compute::create_pipeline_layout(
+            self.device,
+            &[],
+            std::mem::size_of::<CameraPushConstants>() as u32,
+            ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_VERTEX_BIT,
+        )?;
 
-        let mut pipeline_layout_info: ffi::VkPipelineLayoutCreateInfo =
-            unsafe { std::mem::zeroed() };
-        pipeline_layout_info.sType =
-            ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_LAYOUT_CREATE_INFO;
-        pipeline_layout_info.setLayoutCount = 0;
-        pipeline_layout_info.pSetLayouts = std::ptr::null();
-        pipeline_layout_info.pushConstantRangeCount = 0;
-        pipeline_layout_info.pPushConstantRanges = std::ptr::null();
+        let mut vertex_attributes = Vertex::get_attribute_descriptions().to_vec();
+        vertex_attributes.extend_from_slice(&InstanceData::get_attribute_descriptions());
 
-        let result = unsafe {
-            ffi::vkCreatePipelineLayout(
-                self.device,
-                std::ptr::addr_of!(pipeline_layout_info),
-                std::ptr::null(),
-                std::ptr::addr_of_mut!(self.pipeline_layout),
+        self.graphics_pipeline = self
+            .graphics_pipeline_builder(self.pipeline_layout)
+            .shader_stage(
+                ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_VERTEX_BIT,
+                vert_shader_module.get_module(),
             )
-        };
-        if result != ffi::VkResult_VK_SUCCESS {
-            return Err(String::from("Failed to create pipeline layout!"));
-        }
-
-        let mut pipeline_info: ffi::VkGraphicsPipelineCreateInfo = unsafe { std::mem::zeroed() };
-        pipeline_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_GRAPHICS_PIPELINE_CREATE_INFO;
-        pipeline_info.stageCount = 2;
-        pipeline_info.pStages = shader_stages.as_ptr();
-
-        pipeline_info.pVertexInputState = std::ptr::addr_of!(vertex_input_info);
-        pipeline_info.pInputAssemblyState = std::ptr::addr_of!(input_assembly);
-        pipeline_info.pViewportState = std::ptr::addr_of!(viewport_state);
-        pipeline_info.pRasterizationState = std::ptr::addr_of!(rasterizer_info);
-        pipeline_info.pMultisampleState = std::ptr::addr_of!(multisampling_info);
-        pipeline_info.pDepthStencilState = std::ptr::null();
-        pipeline_info.pColorBlendState = std::ptr::addr_of!(color_blend_info_struct);
-        pipeline_info.pDynamicState = std::ptr::addr_of!(dynamic_state_info_struct);
-
-        pipeline_info.layout = self.pipeline_layout;
-
-        pipeline_info.renderPass = self.render_pass;
-        pipeline_info.subpass = 0;
-
-        pipeline_info.basePipelineHandle = std::ptr::null_mut();
-        pipeline_info.basePipelineIndex = -1;
-
-        let result = unsafe {
-            ffi::vkCreateGraphicsPipelines(
-                self.device,
-                std::ptr::null_mut(),
-                1,
-                std::ptr::addr_of!(pipeline_info),
-                std::ptr::null(),
-                std::ptr::addr_of_mut!(self.graphics_pipeline),
+            .shader_stage(
+                ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_FRAGMENT_BIT,
+                frag_shader_module.get_module(),
             )
-        };
-        if result != ffi::VkResult_VK_SUCCESS {
-            return Err(String::from("Failed to create a graphics pipeline!"));
-        }
+            .specialization_constant(
+                ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_FRAGMENT_BIT,
+                0,
+                SCENE_ENCODING as u32,
+            )
+            .vertex_input(
+                &[
+                    Vertex::get_binding_description(),
+                    InstanceData::get_binding_description(),
+                ],
+                &vertex_attributes,
+            )
+            .topology(ffi::VkPrimitiveTopology_VK_PRIMITIVE_TOPOLOGY_TRIANGLE_LIST)
+            // The grid is a flat plane the cameras can look at from either side.
+            .cull_mode(ffi::VkCullModeFlagBits_VK_CULL_MODE_NONE)
+            .blend_mode(BlendMode::Opaque)
+            .build(self.device)?;
 
-        // TODO: Use the *_shader_stage_info structs before vert/frag_shader_module is cleaned up.
         Ok(())
     }
 
-    fn create_vertex_shader_module(&mut self) -> Result<ShaderModuleWrapper, String> {
-        let vertex_shader = std::include_bytes!(concat!(env!("OUT_DIR"), "/vert.spv"));
-
-        let mut create_info: ffi::VkShaderModuleCreateInfo = unsafe { std::mem::zeroed() };
-        create_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_SHADER_MODULE_CREATE_INFO;
-        create_info.codeSize = vertex_shader.len();
-        create_info.pCode = vertex_shader.as_ptr() as *const u32;
-
-        let mut shader_module: ffi::VkShaderModule = unsafe { std::mem::zeroed() };
-        let result = unsafe {
-            ffi::vkCreateShaderModule(
-                self.device,
-                std::ptr::addr_of!(create_info),
-                std::ptr::null(),
-                std::ptr::addr_of_mut!(shader_module),
-            )
-        };
-        if result != ffi::VkResult_VK_SUCCESS {
-            Err(String::from("Failed to create vertex shader module!"))
-        } else {
-            Ok(ShaderModuleWrapper {
-                module: shader_module,
-                device: self.device,
-            })
-        }
-    }
-
-    fn create_fragment_shader_module(&mut self) -> Result<ShaderModuleWrapper, String> {
-        let fragment_shader = std::include_bytes!(concat!(env!("OUT_DIR"), "/frag.spv"));
-
+    fn create_shader_module(&self, code: &[u8]) -> Result<ShaderModuleWrapper, String> {
         let mut create_info: ffi::VkShaderModuleCreateInfo = unsafe { std::mem::zeroed() };
         create_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_SHADER_MODULE_CREATE_INFO;
-        create_info.codeSize = fragment_shader.len();
-        create_info.pCode = fragment_shader.as_ptr() as *const u32;
+        create_info.codeSize = code.len();
+        create_info.pCode = code.as_ptr() as *const u32;
 
         let mut shader_module: ffi::VkShaderModule = unsafe { std::mem::zeroed() };
         let result = unsafe {
@@ -1092,7 +1460,7 @@ impl VulkanApp {
             )
         };
         if result != ffi::VkResult_VK_SUCCESS {
-            Err(String::from("Failed to create fragment shader module!"))
+            Err(String::from("Failed to create shader module!"))
         } else {
             Ok(ShaderModuleWrapper {
                 module: shader_module,
@@ -1101,17 +1469,6 @@ impl VulkanApp {
         }
     }
 
-    fn create_dynamic_state_info_struct() -> ffi::VkPipelineDynamicStateCreateInfo {
-        let mut dynamic_state: ffi::VkPipelineDynamicStateCreateInfo =
-            unsafe { std::mem::zeroed() };
-        dynamic_state.sType =
-            ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_DYNAMIC_STATE_CREATE_INFO;
-        dynamic_state.dynamicStateCount = DYNAMIC_STATES.len() as u32;
-        dynamic_state.pDynamicStates = DYNAMIC_STATES.as_ptr();
-
-        dynamic_state
-    }
-
     fn create_viewport(&self) -> ffi::VkViewport {
         let mut viewport: ffi::VkViewport = unsafe { std::mem::zeroed() };
         viewport.x = 0.0;
@@ -1131,102 +1488,26 @@ impl VulkanApp {
         }
     }
 
-    fn create_viewport_state_info_struct() -> ffi::VkPipelineViewportStateCreateInfo {
-        let mut viewport_state: ffi::VkPipelineViewportStateCreateInfo =
-            unsafe { std::mem::zeroed() };
-        viewport_state.sType =
-            ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_VIEWPORT_STATE_CREATE_INFO;
-        viewport_state.viewportCount = 1;
-        viewport_state.scissorCount = 1;
-
-        viewport_state
-    }
-
-    fn create_rasterizer_info_struct() -> ffi::VkPipelineRasterizationStateCreateInfo {
-        let mut rasterizer_info: ffi::VkPipelineRasterizationStateCreateInfo =
-            unsafe { std::mem::zeroed() };
-        rasterizer_info.sType =
-            ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_RASTERIZATION_STATE_CREATE_INFO;
-        rasterizer_info.depthClampEnable = ffi::VK_FALSE;
-        rasterizer_info.rasterizerDiscardEnable = ffi::VK_FALSE;
-        rasterizer_info.polygonMode = ffi::VkPolygonMode_VK_POLYGON_MODE_FILL;
-        rasterizer_info.lineWidth = 1.0;
-        rasterizer_info.cullMode = ffi::VkCullModeFlagBits_VK_CULL_MODE_BACK_BIT;
-        rasterizer_info.frontFace = ffi::VkFrontFace_VK_FRONT_FACE_CLOCKWISE;
-        rasterizer_info.depthBiasEnable = ffi::VK_FALSE;
-        rasterizer_info.depthBiasConstantFactor = 0.0;
-        rasterizer_info.depthBiasClamp = 0.0;
-        rasterizer_info.depthBiasSlopeFactor = 0.0;
-
-        rasterizer_info
-    }
-
-    fn create_multisampling_info_struct() -> ffi::VkPipelineMultisampleStateCreateInfo {
-        let mut multisampling_info: ffi::VkPipelineMultisampleStateCreateInfo =
-            unsafe { std::mem::zeroed() };
-        multisampling_info.sType =
-            ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_MULTISAMPLE_STATE_CREATE_INFO;
-        multisampling_info.sampleShadingEnable = ffi::VK_FALSE;
-        multisampling_info.rasterizationSamples = ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_1_BIT;
-        multisampling_info.minSampleShading = 1.0;
-        multisampling_info.pSampleMask = std::ptr::null();
-        multisampling_info.alphaToCoverageEnable = ffi::VK_FALSE;
-        multisampling_info.alphaToOneEnable = ffi::VK_FALSE;
-
-        multisampling_info
-    }
-
-    fn create_color_blend_attach_state_struct() -> ffi::VkPipelineColorBlendAttachmentState {
-        let mut color_blend_attachment: ffi::VkPipelineColorBlendAttachmentState =
-            unsafe { std::mem::zeroed() };
-        color_blend_attachment.colorWriteMask =
-            ffi::VkColorComponentFlagBits_VK_COLOR_COMPONENT_R_BIT
-                | ffi::VkColorComponentFlagBits_VK_COLOR_COMPONENT_G_BIT
-                | ffi::VkColorComponentFlagBits_VK_COLOR_COMPONENT_B_BIT
-                | ffi::VkColorComponentFlagBits_VK_COLOR_COMPONENT_A_BIT;
-        color_blend_attachment.blendEnable = ffi::VK_FALSE;
-        color_blend_attachment.srcColorBlendFactor = ffi::VkBlendFactor_VK_BLEND_FACTOR_ONE;
-        color_blend_attachment.dstColorBlendFactor = ffi::VkBlendFactor_VK_BLEND_FACTOR_ZERO;
-        color_blend_attachment.colorBlendOp = ffi::VkBlendOp_VK_BLEND_OP_ADD;
-        color_blend_attachment.srcAlphaBlendFactor = ffi::VkBlendFactor_VK_BLEND_FACTOR_ONE;
-        color_blend_attachment.dstAlphaBlendFactor = ffi::VkBlendFactor_VK_BLEND_FACTOR_ZERO;
-        color_blend_attachment.alphaBlendOp = ffi::VkBlendOp_VK_BLEND_OP_ADD;
-
-        color_blend_attachment
-    }
-
-    fn create_color_blend_state_info_struct(
-        color_blend_attach_ptr: *const ffi::VkPipelineColorBlendAttachmentState,
-    ) -> ffi::VkPipelineColorBlendStateCreateInfo {
-        let mut color_blending: ffi::VkPipelineColorBlendStateCreateInfo =
-            unsafe { std::mem::zeroed() };
-        color_blending.sType =
-            ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_COLOR_BLEND_STATE_CREATE_INFO;
-        color_blending.logicOpEnable = ffi::VK_FALSE;
-        color_blending.logicOp = ffi::VkLogicOp_VK_LOGIC_OP_COPY;
-        color_blending.attachmentCount = 1;
-        color_blending.pAttachments = color_blend_attach_ptr;
-        color_blending.blendConstants[0] = 0.0;
-        color_blending.blendConstants[1] = 0.0;
-        color_blending.blendConstants[2] = 0.0;
-        color_blending.blendConstants[3] = 0.0;
-
-        color_blending
-    }
-
     fn create_render_pass(&mut self) -> Result<(), String> {
         let mut color_attachment: ffi::VkAttachmentDescription = unsafe { std::mem::zeroed() };
-        color_attachment.format = self.swap_chain_image_format;
-        color_attachment.samples = ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_1_BIT;
+        color_attachment.format = post_process::HDR_FORMAT;
+        color_attachment.samples = self.msaa_samples;
 
         color_attachment.loadOp = ffi::VkAttachmentLoadOp_VK_ATTACHMENT_LOAD_OP_CLEAR;
-        color_attachment.storeOp = ffi::VkAttachmentStoreOp_VK_ATTACHMENT_STORE_OP_STORE;
+        // Only the resolved image is needed after the pass.
+        color_attachment.storeOp = if self.uses_msaa() {
+            ffi::VkAttachmentStoreOp_VK_ATTACHMENT_STORE_OP_DONT_CARE
+        } else {
+            ffi::VkAttachmentStoreOp_VK_ATTACHMENT_STORE_OP_STORE
+        };
 
         color_attachment.stencilLoadOp = ffi::VkAttachmentLoadOp_VK_ATTACHMENT_LOAD_OP_DONT_CARE;
         color_attachment.stencilStoreOp = ffi::VkAttachmentStoreOp_VK_ATTACHMENT_STORE_OP_DONT_CARE;
 
-        color_attachment.initialLayout = ffi::VkImageLayout_VK_IMAGE_LAYOUT_UNDEFINED;
-        color_attachment.finalLayout = ffi::VkImageLayout_VK_IMAGE_LAYOUT_PRESENT_SRC_KHR;
+        // The frame graph transitions the image before and after the pass.
+        color_attachment.initialLayout =
+            ffi::VkImageLayout_VK_IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL;
+        color_attachment.finalLayout = ffi::VkImageLayout_VK_IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL;
 
         let mut color_attachment_ref: ffi::VkAttachmentReference = unsafe { std::mem::zeroed() };
         color_attachment_ref.attachment = 0;
@@ -1238,10 +1519,27 @@ impl VulkanApp {
         subpass.colorAttachmentCount = 1;
         subpass.pColorAttachments = std::ptr::addr_of!(color_attachment_ref);
 
+        // With MSAA the scene target is only the target of the resolve at the end of the subpass.
+        let mut resolve_attachment = color_attachment;
+        resolve_attachment.samples = ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_1_BIT;
+        resolve_attachment.loadOp = ffi::VkAttachmentLoadOp_VK_ATTACHMENT_LOAD_OP_DONT_CARE;
+        resolve_attachment.storeOp = ffi::VkAttachmentStoreOp_VK_ATTACHMENT_STORE_OP_STORE;
+
+        let mut resolve_attachment_ref = color_attachment_ref;
+        resolve_attachment_ref.attachment = 1;
+
+        let attachments = [color_attachment, resolve_attachment];
+        let attachment_count = if self.uses_msaa() {
+            subpass.pResolveAttachments = std::ptr::addr_of!(resolve_attachment_ref);
+            2
+        } else {
+            1
+        };
+
         let mut render_pass_info: ffi::VkRenderPassCreateInfo = unsafe { std::mem::zeroed() };
         render_pass_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_RENDER_PASS_CREATE_INFO;
-        render_pass_info.attachmentCount = 1;
-        render_pass_info.pAttachments = std::ptr::addr_of!(color_attachment);
+        render_pass_info.attachmentCount = attachment_count;
+        render_pass_info.pAttachments = attachments.as_ptr();
         render_pass_info.subpassCount = 1;
         render_pass_info.pSubpasses = std::ptr::addr_of!(subpass);
 
@@ -1273,35 +1571,28 @@ impl VulkanApp {
             return Err(String::from("Failed to create render pass!"));
         }
 
+        self.post.hdr_render_pass =
+            post_process::create_render_pass(self.device, post_process::HDR_FORMAT)?;
+        self.post.output_render_pass =
+            post_process::create_render_pass(self.device, self.swap_chain_image_format)?;
+
         Ok(())
     }
 
-    fn create_framebuffers(&mut self) -> Result<(), String> {
-        self.swap_chain_framebuffers
-            .resize(self.swap_chain_image_views.len(), std::ptr::null_mut());
-
-        for (idx, image_view) in self.swap_chain_image_views.iter().enumerate() {
-            let mut framebuffer_info: ffi::VkFramebufferCreateInfo = unsafe { std::mem::zeroed() };
-            framebuffer_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_FRAMEBUFFER_CREATE_INFO;
-            framebuffer_info.renderPass = self.render_pass;
-            framebuffer_info.attachmentCount = 1;
-            framebuffer_info.pAttachments = image_view as *const ffi::VkImageView;
-            framebuffer_info.width = self.swap_chain_extent.width;
-            framebuffer_info.height = self.swap_chain_extent.height;
-            framebuffer_info.layers = 1;
-
-            let result = unsafe {
-                ffi::vkCreateFramebuffer(
-                    self.device,
-                    std::ptr::addr_of!(framebuffer_info),
-                    std::ptr::null(),
-                    std::ptr::addr_of_mut!(self.swap_chain_framebuffers[idx]),
-                )
-            };
+    fn uses_msaa(&self) -> bool {
+        self.msaa_samples != ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_1_BIT
+    }
 
-            if result != ffi::VkResult_VK_SUCCESS {
-                return Err(String::from("Failed to create framebuffer!"));
-            }
+    fn create_framebuffers(&mut self) -> Result<(), String> {
+        // The last post-process pass renders into the swapchain image.
+        for image_view in &self.swap_chain_image_views {
+            let framebuffer = rendering::create_framebuffer(
+                self.device,
+                self.post.output_render_pass,
+                &[*image_view],
+                self.swap_chain_extent,
+            )?;
+            self.swap_chain_framebuffers.push(framebuffer);
         }
 
         Ok(())
@@ -1354,40 +1645,68 @@ impl VulkanApp {
         Ok(())
     }
 
-    fn record_command_buffer(
-        &mut self,
-        command_buffer: ffi::VkCommandBuffer,
-        image_index: usize,
-    ) -> Result<(), String> {
-        let mut begin_info: ffi::VkCommandBufferBeginInfo = unsafe { std::mem::zeroed() };
-        begin_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_COMMAND_BUFFER_BEGIN_INFO;
-        begin_info.flags = 0;
-        begin_info.pInheritanceInfo = std::ptr::null();
+    fn uses_dynamic_rendering(&self) -> bool {
+        self.enabled_features.has_feature(Feature::DynamicRendering)
+    }
 
-        let result =
-            unsafe { ffi::vkBeginCommandBuffer(command_buffer, std::ptr::addr_of!(begin_info)) };
-        if result != ffi::VkResult_VK_SUCCESS {
-            return Err(String::from("Failed to begin recording command buffer!"));
-        }
+    fn uses_synchronization2(&self) -> bool {
+        self.enabled_features.has_feature(Feature::Synchronization2)
+    }
 
-        let mut render_pass_info: ffi::VkRenderPassBeginInfo = unsafe { std::mem::zeroed() };
-        render_pass_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_RENDER_PASS_BEGIN_INFO;
-        render_pass_info.renderPass = self.render_pass;
-        render_pass_info.framebuffer = self.swap_chain_framebuffers[image_index];
+    fn graphics_pipeline_builder(&self, layout: ffi::VkPipelineLayout) -> GraphicsPipelineBuilder {
+        let sample_shading = self.config.sample_shading.filter(|_| {
+            self.enabled_features
+                .has_feature(Feature::SampleRateShading)
+        });
+        let builder = GraphicsPipelineBuilder::new(layout, self.render_pass)
+            .samples(self.msaa_samples)
+            .sample_shading(sample_shading);
+        if self.uses_dynamic_rendering() {
+            builder.dynamic_rendering(
+                &[post_process::HDR_FORMAT],
+                ffi::VkFormat_VK_FORMAT_UNDEFINED,
+            )
+        } else {
+            builder
+        }
+    }
 
-        render_pass_info.renderArea.offset.x = 0;
-        render_pass_info.renderArea.offset.y = 0;
+    /// Starts rendering into `view`, either with dynamic rendering or with `render_pass` and
+    /// `framebuffer`, which has to contain the same views.
+    fn begin_color_pass(
+        &self,
+        command_buffer: ffi::VkCommandBuffer,
+        render_pass: ffi::VkRenderPass,
+        framebuffer: ffi::VkFramebuffer,
+        view: ffi::VkImageView,
+        resolve_view: Option<ffi::VkImageView>,
+    ) {
+        let clear_color = [0.0, 0.0, 0.0, 1.0];
+
+        if self.uses_dynamic_rendering() {
+            rendering::begin_color_rendering(
+                command_buffer,
+                view,
+                resolve_view,
+                self.swap_chain_extent,
+                clear_color,
+            );
+            return;
+        }
+
+        let mut render_pass_info: ffi::VkRenderPassBeginInfo = unsafe { std::mem::zeroed() };
+        render_pass_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_RENDER_PASS_BEGIN_INFO;
+        render_pass_info.renderPass = render_pass;
+        render_pass_info.framebuffer = framebuffer;
+
+        render_pass_info.renderArea.offset.x = 0;
+        render_pass_info.renderArea.offset.y = 0;
         render_pass_info.renderArea.extent = self.swap_chain_extent;
 
-        let mut clear_color: ffi::VkClearValue = unsafe { std::mem::zeroed() };
-        unsafe {
-            clear_color.color.float32[0] = 0.0;
-            clear_color.color.float32[1] = 0.0;
-            clear_color.color.float32[2] = 0.0;
-            clear_color.color.float32[3] = 1.0;
-        }
+        let mut clear_value: ffi::VkClearValue = unsafe { std::mem::zeroed() };
+        clear_value.color.float32 = clear_color;
         render_pass_info.clearValueCount = 1;
-        render_pass_info.pClearValues = std::ptr::addr_of!(clear_color);
+        render_pass_info.pClearValues = std::ptr::addr_of!(clear_value);
 
         unsafe {
             ffi::vkCmdBeginRenderPass(
@@ -1395,6 +1714,149 @@ impl VulkanApp {
                 std::ptr::addr_of!(render_pass_info),
                 ffi::VkSubpassContents_VK_SUBPASS_CONTENTS_INLINE,
             );
+        }
+    }
+
+    fn end_color_pass(&self, command_buffer: ffi::VkCommandBuffer) {
+        if self.uses_dynamic_rendering() {
+            rendering::end_color_rendering(command_buffer);
+        } else {
+            unsafe {
+                ffi::vkCmdEndRenderPass(command_buffer);
+            }
+        }
+    }
+
+    fn swap_chain_graph_image(&self, image_index: usize) -> GraphImage {
+        GraphImage {
+            image: self.swap_chain_images[image_index],
+            view: self.swap_chain_image_views[image_index],
+            format: self.swap_chain_image_format,
+            extent: self.swap_chain_extent,
+        }
+    }
+
+    fn build_frame_graph(&self, image_index: usize) -> RenderGraph<FramePass> {
+        let mut graph = RenderGraph::new();
+        let color_aspect = ffi::VkImageAspectFlagBits_VK_IMAGE_ASPECT_COLOR_BIT;
+
+        let swap_chain_image = graph.import_image(
+            "swapchain",
+            self.swap_chain_graph_image(image_index),
+            color_aspect,
+            Usage::AcquiredImage,
+            Usage::Present,
+        );
+        let vertices = graph.import_buffer(
+            "vertices",
+            self.vertex_buffer,
+            std::mem::size_of_val(&VERTICES) as ffi::VkDeviceSize,
+            Usage::VertexBuffer,
+        );
+        let particles = graph.import_buffer(
+            "particles",
+            self.particles.buffer,
+            self.particles.buffer_size(),
+            Usage::VertexBuffer,
+        );
+
+        // Swapchain sized, so the pool replaces them on resize.
+        let target_desc =
+            |usage: ffi::VkImageUsageFlags, samples: ffi::VkSampleCountFlagBits| ImageDesc {
+                format: post_process::HDR_FORMAT,
+                size: ImageSize::Swapchain,
+                usage,
+                aspect: color_aspect,
+                samples,
+            };
+        let sampled_target = target_desc(
+            ffi::VkImageUsageFlagBits_VK_IMAGE_USAGE_COLOR_ATTACHMENT_BIT
+                | ffi::VkImageUsageFlagBits_VK_IMAGE_USAGE_SAMPLED_BIT,
+            ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_1_BIT,
+        );
+        let msaa_color = self.uses_msaa().then(|| {
+            graph.create_image(
+                "msaa color",
+                target_desc(
+                    ffi::VkImageUsageFlagBits_VK_IMAGE_USAGE_COLOR_ATTACHMENT_BIT
+                        | ffi::VkImageUsageFlagBits_VK_IMAGE_USAGE_TRANSIENT_ATTACHMENT_BIT,
+                    self.msaa_samples,
+                ),
+            )
+        });
+        let scene_color = graph.create_image("scene color", sampled_target);
+        let post_targets: Vec<_> = (0..post_process::intermediate_count(self.post.pass_count()))
+            .map(|idx| graph.create_image(&format!("post target {}", idx), sampled_target))
+            .collect();
+        let resource = |target: PostTarget| match target {
+            PostTarget::Scene => scene_color,
+            PostTarget::Intermediate(idx) => post_targets[idx],
+            PostTarget::Swapchain => swap_chain_image,
+        };
+
+        graph
+            .add_pass("vertex animation", FramePass::VertexAnimation)
+            .write(vertices, Usage::ComputeShaderWrite);
+        graph
+            .add_pass("particle simulation", FramePass::ParticleSimulation)
+            .write(particles, Usage::ComputeShaderWrite);
+        let scene = graph
+            .add_pass(
+                "scene",
+                FramePass::Scene {
+                    color: scene_color,
+                    msaa: msaa_color,
+                },
+            )
+            .read(vertices, Usage::VertexBuffer)
+            .read(particles, Usage::VertexBuffer)
+            .write(scene_color, Usage::ColorAttachment);
+        if let Some(msaa_color) = msaa_color {
+            scene.write(msaa_color, Usage::ColorAttachment);
+        }
+
+        let pass_count = self.post.pass_count();
+        for (pass, effect) in self.post.passes.iter().enumerate() {
+            let input = resource(post_process::pass_input(pass));
+            let output = resource(post_process::pass_output(pass, pass_count));
+            graph
+                .add_pass(
+                    &format!("post: {}", effect.name()),
+                    FramePass::Post {
+                        pass,
+                        input,
+                        output,
+                    },
+                )
+                .read(input, Usage::FragmentShaderRead)
+                .write(output, Usage::ColorAttachment);
+        }
+
+        graph
+    }
+
+    fn record_scene(
+        &self,
+        command_buffer: ffi::VkCommandBuffer,
+        graph: &RenderGraph<FramePass>,
+        color: ResourceId,
+        msaa: Option<ResourceId>,
+    ) {
+        let color_view = graph_image(graph, color).view;
+        let (view, resolve_view) = match msaa {
+            Some(msaa) => (graph_image(graph, msaa).view, Some(color_view)),
+            None => (color_view, None),
+        };
+        let framebuffer = self.graph_framebuffer(self.render_pass, view, resolve_view);
+        self.begin_color_pass(
+            command_buffer,
+            self.render_pass,
+            framebuffer,
+            view,
+            resolve_view,
+        );
+
+        unsafe {
             ffi::vkCmdBindPipeline(
                 command_buffer,
                 ffi::VkPipelineBindPoint_VK_PIPELINE_BIND_POINT_GRAPHICS,
@@ -1402,13 +1864,29 @@ impl VulkanApp {
             );
         }
 
-        let offsets: [ffi::VkDeviceSize; 1] = [0];
+        // Recomputed every frame so the projection follows `swap_chain_extent` across resizes.
+        let camera_constants = CameraPushConstants {
+            view_proj: self.camera.view_projection(self.swap_chain_extent),
+        };
+        unsafe {
+            ffi::vkCmdPushConstants(
+                command_buffer,
+                self.pipeline_layout,
+                ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_VERTEX_BIT,
+                0,
+                std::mem::size_of::<CameraPushConstants>() as u32,
+                std::ptr::addr_of!(camera_constants) as *const c_void,
+            );
+        }
+
+        let vertex_buffers: [ffi::VkBuffer; 2] = [self.vertex_buffer, self.instance_buffer];
+        let offsets: [ffi::VkDeviceSize; 2] = [0, 0];
         unsafe {
             ffi::vkCmdBindVertexBuffers(
                 command_buffer,
                 0,
-                1,
-                std::ptr::addr_of!(self.vertex_buffer),
+                vertex_buffers.len() as u32,
+                vertex_buffers.as_ptr(),
                 offsets.as_ptr(),
             );
             ffi::vkCmdBindIndexBuffer(
@@ -1429,9 +1907,241 @@ impl VulkanApp {
 
         unsafe {
             ffi::vkCmdSetScissor(command_buffer, 0, 1, std::ptr::addr_of!(scissor));
-            ffi::vkCmdDrawIndexed(command_buffer, VERTEX_INDICES.len() as u32, 1, 0, 0, 0);
-            ffi::vkCmdEndRenderPass(command_buffer);
+        }
+
+        if self.queries.occlusion_enabled() {
+            for draw in 0..self.draw_batch.len() as u32 {
+                self.queries.begin_draw(command_buffer, draw);
+                indirect::record_draw(command_buffer, self.indirect_buffer, draw);
+                self.queries.end_draw(command_buffer, draw);
+            }
+        } else {
+            indirect::record_draws(
+                command_buffer,
+                self.indirect_buffer,
+                self.draw_batch.len() as u32,
+                self.enabled_features
+                    .has_feature(Feature::MultiDrawIndirect),
+            );
+        }
+
+        self.particles
+            .record_draw(command_buffer, &camera_constants.view_proj);
+
+        self.end_color_pass(command_buffer);
+    }
+
+    fn record_post_pass(
+        &self,
+        command_buffer: ffi::VkCommandBuffer,
+        graph: &RenderGraph<FramePass>,
+        pass: usize,
+        output: ResourceId,
+        image_index: usize,
+    ) {
+        let output_view = graph_image(graph, output).view;
+        let (render_pass, framebuffer) =
+            match post_process::pass_output(pass, self.post.pass_count()) {
+                PostTarget::Swapchain => (
+                    self.post.output_render_pass,
+                    self.swap_chain_framebuffers
+                        .get(image_index)
+                        .copied()
+                        .unwrap_or(std::ptr::null_mut()),
+                ),
+                _ => (
+                    self.post.hdr_render_pass,
+                    self.graph_framebuffer(self.post.hdr_render_pass, output_view, None),
+                ),
+            };
+
+        self.begin_color_pass(command_buffer, render_pass, framebuffer, output_view, None);
+        self.post
+            .record_pass(command_buffer, pass, self.swap_chain_extent);
+        self.end_color_pass(command_buffer);
+    }
+
+    /// Attachments of a render pass object in the order of `create_render_pass`: the rendered
+    /// view first, then the resolve target.
+    fn framebuffer_attachments(
+        view: ffi::VkImageView,
+        resolve_view: Option<ffi::VkImageView>,
+    ) -> Vec<ffi::VkImageView> {
+        std::iter::once(view).chain(resolve_view).collect()
+    }
+
+    /// Null with dynamic rendering, which doesn't use framebuffers.
+    fn graph_framebuffer(
+        &self,
+        render_pass: ffi::VkRenderPass,
+        view: ffi::VkImageView,
+        resolve_view: Option<ffi::VkImageView>,
+    ) -> ffi::VkFramebuffer {
+        if self.uses_dynamic_rendering() {
+            return std::ptr::null_mut();
+        }
+        let attachments = Self::framebuffer_attachments(view, resolve_view);
+        self.graph_framebuffers
+            .get(render_pass, &attachments)
+            .expect("framebuffers should be created before recording")
+    }
+
+    /// Creates the framebuffers the passes of `compiled` render into that aren't cached yet.
+    fn create_graph_framebuffers(
+        &mut self,
+        graph: &RenderGraph<FramePass>,
+        compiled: &CompiledGraph,
+    ) -> Result<(), String> {
+        for step in &compiled.steps {
+            let (render_pass, attachments) = match *graph.payload(step.pass) {
+                FramePass::Scene { color, msaa } => {
+                    let color_view = graph_image(graph, color).view;
+                    let attachments = match msaa {
+                        Some(msaa) => Self::framebuffer_attachments(
+                            graph_image(graph, msaa).view,
+                            Some(color_view),
+                        ),
+                        None => vec![color_view],
+                    };
+                    (self.render_pass, attachments)
+                }
+                FramePass::Post { pass, output, .. }
+                    if post_process::pass_output(pass, self.post.pass_count())
+                        != PostTarget::Swapchain =>
+                {
+                    (
+                        self.post.hdr_render_pass,
+                        vec![graph_image(graph, output).view],
+                    )
+                }
+                _ => continue,
+            };
+            if self
+                .graph_framebuffers
+                .get(render_pass, &attachments)
+                .is_some()
+            {
+                continue;
+            }
+
+            let framebuffer = rendering::create_framebuffer(
+                self.device,
+                render_pass,
+                &attachments,
+                self.swap_chain_extent,
+            )?;
+            self.debug_utils.set_name(
+                self.device,
+                framebuffer,
+                &format!("{} framebuffer", graph.pass_name(step.pass)),
+            );
+            self.graph_framebuffers
+                .insert(render_pass, attachments, framebuffer);
+        }
+
+        Ok(())
+    }
+
+    fn record_command_buffer(
+        &mut self,
+        command_buffer: ffi::VkCommandBuffer,
+        image_index: usize,
+    ) -> Result<(), String> {
+        let mut begin_info: ffi::VkCommandBufferBeginInfo = unsafe { std::mem::zeroed() };
+        begin_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_COMMAND_BUFFER_BEGIN_INFO;
+        begin_info.flags = 0;
+        begin_info.pInheritanceInfo = std::ptr::null();
+
+        let result =
+            unsafe { ffi::vkBeginCommandBuffer(command_buffer, std::ptr::addr_of!(begin_info)) };
+        if result != ffi::VkResult_VK_SUCCESS {
+            return Err(String::from("Failed to begin recording command buffer!"));
+        }
+
+        self.profiler.begin_frame(command_buffer);
+        self.queries.begin_frame(command_buffer);
+
+        let mut graph = self.build_frame_graph(image_index);
+        let compiled = graph.compile()?;
+
+        let mem_props = self.memory_properties();
+        self.transient_images.begin_frame();
+        let pooled_images = self.transient_images.len();
+        graph.allocate(
+            &compiled,
+            &mut self.transient_images,
+            self.device,
+            &mem_props,
+            self.swap_chain_extent,
+        )?;
+        if self.transient_images.len() != pooled_images {
+            for (name, image) in graph.transient_images() {
+                self.debug_utils.set_name(self.device, image.image, name);
+                self.debug_utils
+                    .set_name(self.device, image.view, &format!("{} view", name));
+            }
+        }
+        // Left over from a different extent or frame setup, the last frame may still use them.
+        let unused_images = self.transient_images.take_unused();
+        if !unused_images.is_empty() {
+            for framebuffer in self.graph_framebuffers.take_all() {
+                self.timeline.defer_release(
+                    self.timeline.last_submitted(),
+                    Release::Framebuffer(framebuffer),
+                );
+            }
+        }
+        for image in unused_images {
+            self.timeline
+                .defer_release(self.timeline.last_submitted(), Release::Image(image));
+        }
+        if !self.uses_dynamic_rendering() {
+            self.create_graph_framebuffers(&graph, &compiled)?;
+        }
+
+        for step in &compiled.steps {
+            if let FramePass::Post { pass, input, .. } = *graph.payload(step.pass) {
+                self.post
+                    .write_input(self.device, pass, graph_image(&graph, input).view);
+            }
+        }
+
+        let mut scope_names = Vec::new();
+        graph.execute(
+            &compiled,
+            command_buffer,
+            self.uses_synchronization2(),
+            |name, pass| {
+                let scope = scope_names.len() as u32;
+                scope_names.push(name.to_owned());
+                self.debug_utils
+                    .begin_label(command_buffer, &scope_names[scope as usize]);
+                self.profiler.begin_scope(command_buffer, scope);
+                self.queries.begin_scope(command_buffer, scope);
+                match pass {
+                    FramePass::VertexAnimation => self.record_vertex_animation(command_buffer),
+                    FramePass::ParticleSimulation => self.particles.record_simulation(
+                        command_buffer,
+                        self.clock.elapsed(),
+                        self.uses_synchronization2(),
+                    ),
+                    FramePass::Scene { color, msaa } => {
+                        self.record_scene(command_buffer, &graph, *color, *msaa)
+                    }
+                    FramePass::Post { pass, output, .. } => {
+                        self.record_post_pass(command_buffer, &graph, *pass, *output, image_index)
+                    }
+                }
+                self.queries.end_scope(command_buffer, scope);
+                self.profiler.end_scope(command_buffer, scope);
+                self.debug_utils.end_label(command_buffer);
+            },
+        );
+        self.queries
+            .end_frame(scope_names.clone(), self.draw_batch.len() as u32);
+        self.profiler.end_frame(command_buffer, scope_names);
 
+        unsafe {
             if ffi::vkEndCommandBuffer(command_buffer) != ffi::VkResult_VK_SUCCESS {
                 return Err(String::from("Failed to record command buffer!"));
             }
@@ -1441,15 +2151,10 @@ impl VulkanApp {
     }
 
     fn draw_frame(&mut self) -> Result<(), String> {
-        unsafe {
-            ffi::vkWaitForFences(
-                self.device,
-                1,
-                std::ptr::addr_of!(self.in_flight_fence),
-                ffi::VK_TRUE,
-                u64::MAX,
-            );
-        }
+        self.timeline
+            .wait(self.device, self.frame_timeline_value, u64::MAX)?;
+        self.profiler.collect(self.device)?;
+        self.queries.collect(self.device)?;
 
         let mut image_index: u32 = 0;
 
@@ -1472,8 +2177,6 @@ impl VulkanApp {
                 return Err(String::from("Failed to acquire swap chain image!"));
             }
 
-            ffi::vkResetFences(self.device, 1, std::ptr::addr_of!(self.in_flight_fence));
-
             ffi::vkResetCommandBuffer(self.command_buffer, 0);
             self.record_command_buffer(self.command_buffer, image_index as usize)?;
         }
@@ -1481,30 +2184,51 @@ impl VulkanApp {
         let mut submit_info: ffi::VkSubmitInfo = unsafe { std::mem::zeroed() };
         submit_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_SUBMIT_INFO;
 
-        let wait_stages: ffi::VkPipelineStageFlags =
-            ffi::VkPipelineStageFlagBits_VK_PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT;
-        submit_info.waitSemaphoreCount = 1;
-        submit_info.pWaitSemaphores = std::ptr::addr_of!(self.image_available_semaphore);
-        submit_info.pWaitDstStageMask = std::ptr::addr_of!(wait_stages);
+        // Waiting on the last submitted value makes uploads recorded since the previous
+        // frame visible. Values for the binary semaphores are ignored.
+        let wait_semaphores: [ffi::VkSemaphore; 2] =
+            [self.image_available_semaphore, self.timeline.semaphore];
+        let wait_values: [u64; 2] = [0, self.timeline.last_submitted()];
+        let wait_stages: [ffi::VkPipelineStageFlags; 2] = [
+            ffi::VkPipelineStageFlagBits_VK_PIPELINE_STAGE_COLOR_ATTACHMENT_OUTPUT_BIT,
+            ffi::VkPipelineStageFlagBits_VK_PIPELINE_STAGE_ALL_COMMANDS_BIT,
+        ];
+        submit_info.waitSemaphoreCount = wait_semaphores.len() as u32;
+        submit_info.pWaitSemaphores = wait_semaphores.as_ptr();
+        submit_info.pWaitDstStageMask = wait_stages.as_ptr();
 
         submit_info.commandBufferCount = 1;
         submit_info.pCommandBuffers = std::ptr::addr_of!(self.command_buffer);
 
-        submit_info.signalSemaphoreCount = 1;
-        submit_info.pSignalSemaphores = std::ptr::addr_of!(self.render_finished_semaphore);
+        let frame_timeline_value = self.timeline.next_value();
+        let signal_semaphores: [ffi::VkSemaphore; 2] =
+            [self.render_finished_semaphore, self.timeline.semaphore];
+        let signal_values: [u64; 2] = [0, frame_timeline_value];
+        submit_info.signalSemaphoreCount = signal_semaphores.len() as u32;
+        submit_info.pSignalSemaphores = signal_semaphores.as_ptr();
+
+        let mut timeline_info: ffi::VkTimelineSemaphoreSubmitInfo = unsafe { std::mem::zeroed() };
+        timeline_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_TIMELINE_SEMAPHORE_SUBMIT_INFO;
+        timeline_info.waitSemaphoreValueCount = wait_values.len() as u32;
+        timeline_info.pWaitSemaphoreValues = wait_values.as_ptr();
+        timeline_info.signalSemaphoreValueCount = signal_values.len() as u32;
+        timeline_info.pSignalSemaphoreValues = signal_values.as_ptr();
+        submit_info.pNext = std::ptr::addr_of!(timeline_info) as *const c_void;
 
         let result = unsafe {
             ffi::vkQueueSubmit(
                 self.graphics_queue,
                 1,
                 std::ptr::addr_of!(submit_info),
-                self.in_flight_fence,
+                std::ptr::null_mut(),
             )
         };
 
         if result != ffi::VkResult_VK_SUCCESS {
             return Err(String::from("Failed to submit draw command buffer!"));
         }
+        self.timeline.mark_submitted(frame_timeline_value);
+        self.frame_timeline_value = frame_timeline_value;
 
         let mut present_info: ffi::VkPresentInfoKHR = unsafe { std::mem::zeroed() };
         present_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_PRESENT_INFO_KHR;
@@ -1524,8 +2248,10 @@ impl VulkanApp {
             if result == ffi::VkResult_VK_ERROR_OUT_OF_DATE_KHR
                 || result == ffi::VkResult_VK_SUBOPTIMAL_KHR
                 || self.framebuffer_resized
+                || self.present_mode_changed
             {
                 self.framebuffer_resized = false;
+                self.present_mode_changed = false;
                 self.recreate_swap_chain()?;
             } else if result != ffi::VkResult_VK_SUCCESS {
                 return Err(String::from("Failed to present swap chain image!"));
@@ -1539,10 +2265,6 @@ impl VulkanApp {
         let mut semaphore_info: ffi::VkSemaphoreCreateInfo = unsafe { std::mem::zeroed() };
         semaphore_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_SEMAPHORE_CREATE_INFO;
 
-        let mut fence_info: ffi::VkFenceCreateInfo = unsafe { std::mem::zeroed() };
-        fence_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_FENCE_CREATE_INFO;
-        fence_info.flags = ffi::VkFenceCreateFlagBits_VK_FENCE_CREATE_SIGNALED_BIT;
-
         unsafe {
             if ffi::vkCreateSemaphore(
                 self.device,
@@ -1556,54 +2278,65 @@ impl VulkanApp {
                     std::ptr::null(),
                     std::ptr::addr_of_mut!(self.render_finished_semaphore),
                 ) != ffi::VkResult_VK_SUCCESS
-                || ffi::vkCreateFence(
-                    self.device,
-                    std::ptr::addr_of!(fence_info),
-                    std::ptr::null(),
-                    std::ptr::addr_of_mut!(self.in_flight_fence),
-                ) != ffi::VkResult_VK_SUCCESS
             {
-                return Err(String::from("Failed to create semaphores/fence!"));
+                return Err(String::from("Failed to create semaphores!"));
             }
         }
 
-        Ok(())
+        self.timeline.create_semaphore(self.device)
     }
 
-    fn recreate_swap_chain(&mut self) -> Result<(), String> {
+    fn framebuffer_size(&self) -> (i32, i32) {
         let mut width: i32 = 0;
         let mut height: i32 = 0;
-        while width == 0 || height == 0 {
-            unsafe {
-                ffi::glfwGetFramebufferSize(
-                    self.window,
-                    std::ptr::addr_of_mut!(width),
-                    std::ptr::addr_of_mut!(height),
-                );
-                ffi::glfwWaitEvents();
-            }
+        unsafe {
+            ffi::glfwGetFramebufferSize(
+                self.window,
+                std::ptr::addr_of_mut!(width),
+                std::ptr::addr_of_mut!(height),
+            );
         }
 
-        unsafe {
-            ffi::vkDeviceWaitIdle(self.device);
+        (width, height)
+    }
+
+    fn recreate_swap_chain(&mut self) -> Result<(), String> {
+        // A swapchain can't have a zero sized extent, so stop rendering until the window is
+        // restored. `main_loop` calls this again once it has a size.
+        let (width, height) = self.framebuffer_size();
+        self.minimized = width == 0 || height == 0;
+        if self.minimized {
+            return Ok(());
         }
 
-        self.cleanup_swap_chain()?;
+        // The previous frame may still be using these, so destroy them once it is done.
+        // Null until the new one exists, so a failed recreation can't destroy the retired one
+        // a second time.
+        let old_swap_chain = std::mem::replace(&mut self.swap_chain, std::ptr::null_mut());
+        let retired = RetiredSwapchain {
+            swap_chain: old_swap_chain,
+            image_views: std::mem::take(&mut self.swap_chain_image_views),
+            framebuffers: std::mem::take(&mut self.swap_chain_framebuffers),
+        };
+        self.timeline
+            .defer_release(self.timeline.last_submitted(), Release::Swapchain(retired));
 
-        self.create_swap_chain()?;
+        self.create_swap_chain(old_swap_chain)?;
         self.create_image_views()?;
-        self.create_framebuffers()?;
+        if !self.uses_dynamic_rendering() {
+            self.create_framebuffers()?;
+        }
+        self.name_swap_chain_objects();
 
         Ok(())
     }
 
     fn cleanup_swap_chain(&mut self) -> Result<(), String> {
-        for framebuffer in &self.swap_chain_framebuffers {
+        for framebuffer in std::mem::take(&mut self.swap_chain_framebuffers) {
             unsafe {
-                ffi::vkDestroyFramebuffer(self.device, *framebuffer, std::ptr::null());
+                ffi::vkDestroyFramebuffer(self.device, framebuffer, std::ptr::null());
             }
         }
-        self.swap_chain_framebuffers.clear();
 
         for view in &self.swap_chain_image_views {
             unsafe {
@@ -1622,21 +2355,50 @@ impl VulkanApp {
         Ok(())
     }
 
+    pub fn toggle_vsync(&mut self) {
+        self.present_mode_policy = self
+            .present_mode_policy
+            .toggle_vsync(self.config.present_mode);
+        self.present_mode_changed = true;
+        println!("Present mode: {}", self.present_mode_policy.name());
+    }
+
     pub fn set_resize_flag(&mut self) {
         self.framebuffer_resized = true;
     }
 
     fn create_vertex_buffer(&mut self) -> Result<(), String> {
-        let buffer_size: ffi::VkDeviceSize =
-            (std::mem::size_of::<Vertex>() * VERTICES.len()) as u64;
+        let (buffer, buffer_mem) = self.create_device_local_buffer(
+            helper::slice_as_bytes(&VERTICES),
+            ffi::VkBufferUsageFlagBits_VK_BUFFER_USAGE_VERTEX_BUFFER_BIT
+                | ffi::VkBufferUsageFlagBits_VK_BUFFER_USAGE_STORAGE_BUFFER_BIT,
+        )?;
 
-        let (staging_buffer, staging_buffer_mem) = self.create_buffer(
-            buffer_size,
-            ffi::VkBufferUsageFlagBits_VK_BUFFER_USAGE_TRANSFER_SRC_BIT,
-            ffi::VkMemoryPropertyFlagBits_VK_MEMORY_PROPERTY_HOST_VISIBLE_BIT
-                | ffi::VkMemoryPropertyFlagBits_VK_MEMORY_PROPERTY_HOST_COHERENT_BIT,
+        self.vertex_buffer = buffer;
+        self.vertex_buffer_memory = buffer_mem;
+
+        // The compute pass reads the untransformed vertices from here and writes the
+        // animated ones into the vertex buffer.
+        let (base_buffer, base_buffer_mem) = self.create_device_local_buffer(
+            helper::slice_as_bytes(&VERTICES),
+            ffi::VkBufferUsageFlagBits_VK_BUFFER_USAGE_STORAGE_BUFFER_BIT,
         )?;
 
+        self.base_vertex_buffer = base_buffer;
+        self.base_vertex_buffer_memory = base_buffer_mem;
+
+        Ok(())
+    }
+
+    fn create_device_local_buffer(
+        &mut self,
+        data: &[u8],
+        usage: ffi::VkBufferUsageFlags,
+    ) -> Result<(ffi::VkBuffer, ffi::VkDeviceMemory), String> {
+        let buffer_size: ffi::VkDeviceSize = data.len() as u64;
+
+        let (staging_buffer, staging_buffer_mem) = self.create_staging_buffer(data)?;
+
         let _inst;
         cleanup_func!(
             func: move || unsafe {
@@ -1649,7 +2411,40 @@ impl VulkanApp {
             var_pair: staging_buffer_mem, staging_buf_mem_copy
         );
 
-        let mut data_ptr: *mut c_void = unsafe { std::mem::zeroed() };
+        let (buffer, buffer_mem) = self.create_buffer(
+            buffer_size,
+            ffi::VkBufferUsageFlagBits_VK_BUFFER_USAGE_TRANSFER_DST_BIT | usage,
+            ffi::VkMemoryPropertyFlagBits_VK_MEMORY_PROPERTY_DEVICE_LOCAL_BIT,
+        )?;
+
+        let upload_value = self.copy_buffer(staging_buffer, buffer, buffer_size)?;
+
+        // The copy is still in flight, so hand the staging buffer to the timeline instead of
+        // destroying it when `_inst` goes out of scope.
+        std::mem::forget(_inst);
+        self.timeline.defer_release(
+            upload_value,
+            Release::Buffer(staging_buffer, staging_buffer_mem),
+        );
+
+        Ok((buffer, buffer_mem))
+    }
+
+    /// A host visible buffer holding a copy of `data`.
+    fn create_staging_buffer(
+        &mut self,
+        data: &[u8],
+    ) -> Result<(ffi::VkBuffer, ffi::VkDeviceMemory), String> {
+        let buffer_size: ffi::VkDeviceSize = data.len() as u64;
+
+        let (staging_buffer, staging_buffer_mem) = self.create_buffer(
+            buffer_size,
+            ffi::VkBufferUsageFlagBits_VK_BUFFER_USAGE_TRANSFER_SRC_BIT,
+            ffi::VkMemoryPropertyFlagBits_VK_MEMORY_PROPERTY_HOST_VISIBLE_BIT
+                | ffi::VkMemoryPropertyFlagBits_VK_MEMORY_PROPERTY_HOST_COHERENT_BIT,
+        )?;
+
+        let mut data_ptr: *mut c_void = std::ptr::null_mut();
         unsafe {
             ffi::vkMapMemory(
                 self.device,
@@ -1659,22 +2454,73 @@ impl VulkanApp {
                 0,
                 std::ptr::addr_of_mut!(data_ptr),
             );
-            let data_ptr_vertices: *mut [Vertex; VERTICES.len()] = std::mem::transmute(data_ptr);
-            *data_ptr_vertices = VERTICES;
+            std::ptr::copy_nonoverlapping(data.as_ptr(), data_ptr as *mut u8, data.len());
             ffi::vkUnmapMemory(self.device, staging_buffer_mem);
         }
 
-        let (buffer, buffer_mem) = self.create_buffer(
-            buffer_size,
-            ffi::VkBufferUsageFlagBits_VK_BUFFER_USAGE_TRANSFER_DST_BIT
-                | ffi::VkBufferUsageFlagBits_VK_BUFFER_USAGE_VERTEX_BUFFER_BIT,
-            ffi::VkMemoryPropertyFlagBits_VK_MEMORY_PROPERTY_DEVICE_LOCAL_BIT,
-        )?;
+        Ok((staging_buffer, staging_buffer_mem))
+    }
 
-        self.vertex_buffer = buffer;
-        self.vertex_buffer_memory = buffer_mem;
+    /// Copies `pixels` into the whole of `image` and leaves it ready to be sampled by fragment
+    /// shaders.
+    fn upload_image(&mut self, image: &AllocatedImage, pixels: &[u8]) -> Result<(), String> {
+        let (staging_buffer, staging_buffer_mem) = self.create_staging_buffer(pixels)?;
+
+        let _inst;
+        cleanup_func!(
+            func: move || unsafe {
+                ffi::vkDestroyBuffer(device_copy, staging_buf_copy, std::ptr::null());
+                ffi::vkFreeMemory(device_copy, staging_buf_mem_copy, std::ptr::null());
+            },
+            hold_name: _inst,
+            var_pair: self.device, device_copy,
+            var_pair: staging_buffer, staging_buf_copy,
+            var_pair: staging_buffer_mem, staging_buf_mem_copy
+        );
 
-        self.copy_buffer(staging_buffer, self.vertex_buffer, buffer_size)?;
+        let mut region: ffi::VkBufferImageCopy = unsafe { std::mem::zeroed() };
+        region.imageSubresource.aspectMask = ffi::VkImageAspectFlagBits_VK_IMAGE_ASPECT_COLOR_BIT;
+        region.imageSubresource.layerCount = 1;
+        region.imageExtent.width = image.extent.width;
+        region.imageExtent.height = image.extent.height;
+        region.imageExtent.depth = 1;
+
+        let synchronization2 = self.uses_synchronization2();
+        let target = image.image;
+        let upload_value = self.submit_one_time_commands(|command_buffer| {
+            Barriers::new()
+                .image(
+                    target,
+                    barrier::color_subresource_range(),
+                    Usage::Nothing,
+                    Usage::TransferWrite,
+                )
+                .record(command_buffer, synchronization2);
+            unsafe {
+                ffi::vkCmdCopyBufferToImage(
+                    command_buffer,
+                    staging_buffer,
+                    target,
+                    ffi::VkImageLayout_VK_IMAGE_LAYOUT_TRANSFER_DST_OPTIMAL,
+                    1,
+                    std::ptr::addr_of!(region),
+                );
+            }
+            Barriers::new()
+                .image(
+                    target,
+                    barrier::color_subresource_range(),
+                    Usage::TransferWrite,
+                    Usage::FragmentShaderRead,
+                )
+                .record(command_buffer, synchronization2);
+        })?;
+
+        std::mem::forget(_inst);
+        self.timeline.defer_release(
+            upload_value,
+            Release::Buffer(staging_buffer, staging_buffer_mem),
+        );
 
         Ok(())
     }
@@ -1690,6 +2536,11 @@ impl VulkanApp {
             ));
         }
 
+        image::find_memory_type(&self.memory_properties(), type_filter, properties)
+            .ok_or_else(|| String::from("Failed to find suitable memory type!"))
+    }
+
+    fn memory_properties(&self) -> ffi::VkPhysicalDeviceMemoryProperties {
         let mut mem_props: ffi::VkPhysicalDeviceMemoryProperties = unsafe { std::mem::zeroed() };
         unsafe {
             ffi::vkGetPhysicalDeviceMemoryProperties(
@@ -1697,42 +2548,7 @@ impl VulkanApp {
                 std::ptr::addr_of_mut!(mem_props),
             );
         }
-
-        for idx in 0..mem_props.memoryTypeCount {
-            if (type_filter & (1 << idx)) != 0
-                && (mem_props.memoryTypes[idx as usize].propertyFlags & properties) == properties
-            {
-                return Ok(idx);
-            }
-        }
-
-        Err(String::from("Failed to find suitable memory type!"))
-    }
-
-    #[allow(clippy::type_complexity)]
-    fn create_vertex_input_state_info_struct() -> Result<
-        (
-            ffi::VkPipelineVertexInputStateCreateInfo,
-            Pin<Box<ffi::VkVertexInputBindingDescription>>,
-            Pin<Box<[ffi::VkVertexInputAttributeDescription; 2]>>,
-        ),
-        String,
-    > {
-        let mut vertex_input_info: ffi::VkPipelineVertexInputStateCreateInfo =
-            unsafe { std::mem::zeroed() };
-        vertex_input_info.sType =
-            ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_VERTEX_INPUT_STATE_CREATE_INFO;
-
-        let bind_desc = Box::pin(Vertex::get_binding_description());
-        let attr_descs = Box::pin(Vertex::get_attribute_descriptions());
-
-        vertex_input_info.vertexBindingDescriptionCount = 1;
-        vertex_input_info.vertexAttributeDescriptionCount = attr_descs.len() as u32;
-
-        vertex_input_info.pVertexBindingDescriptions = bind_desc.deref();
-        vertex_input_info.pVertexAttributeDescriptions = attr_descs.as_ptr();
-
-        Ok((vertex_input_info, bind_desc, attr_descs))
+        mem_props
     }
 
     fn create_buffer(
@@ -1800,14 +2616,36 @@ impl VulkanApp {
         src_buffer: ffi::VkBuffer,
         dst_buffer: ffi::VkBuffer,
         size: ffi::VkDeviceSize,
-    ) -> Result<(), String> {
-        let mut alloc_info: ffi::VkCommandBufferAllocateInfo = unsafe { std::mem::zeroed() };
-        alloc_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_COMMAND_BUFFER_ALLOCATE_INFO;
-        alloc_info.level = ffi::VkCommandBufferLevel_VK_COMMAND_BUFFER_LEVEL_PRIMARY;
-        alloc_info.commandPool = self.command_pool;
-        alloc_info.commandBufferCount = 1;
-
-        let mut command_buffer: ffi::VkCommandBuffer = std::ptr::null_mut();
+    ) -> Result<u64, String> {
+        let mut copy_region: ffi::VkBufferCopy = unsafe { std::mem::zeroed() };
+        copy_region.srcOffset = 0;
+        copy_region.dstOffset = 0;
+        copy_region.size = size;
+
+        self.submit_one_time_commands(|command_buffer| unsafe {
+            ffi::vkCmdCopyBuffer(
+                command_buffer,
+                src_buffer,
+                dst_buffer,
+                1,
+                std::ptr::addr_of!(copy_region),
+            );
+        })
+    }
+
+    /// Records a command buffer with `record` and submits it, returning the timeline value that
+    /// is signaled once it completes.
+    fn submit_one_time_commands<F>(&mut self, record: F) -> Result<u64, String>
+    where
+        F: FnOnce(ffi::VkCommandBuffer),
+    {
+        let mut alloc_info: ffi::VkCommandBufferAllocateInfo = unsafe { std::mem::zeroed() };
+        alloc_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_COMMAND_BUFFER_ALLOCATE_INFO;
+        alloc_info.level = ffi::VkCommandBufferLevel_VK_COMMAND_BUFFER_LEVEL_PRIMARY;
+        alloc_info.commandPool = self.command_pool;
+        alloc_info.commandBufferCount = 1;
+
+        let mut command_buffer: ffi::VkCommandBuffer = std::ptr::null_mut();
 
         unsafe {
             ffi::vkAllocateCommandBuffers(
@@ -1826,19 +2664,9 @@ impl VulkanApp {
             ffi::vkBeginCommandBuffer(command_buffer, std::ptr::addr_of!(begin_info));
         }
 
-        let mut copy_region: ffi::VkBufferCopy = unsafe { std::mem::zeroed() };
-        copy_region.srcOffset = 0;
-        copy_region.dstOffset = 0;
-        copy_region.size = size;
+        record(command_buffer);
 
         unsafe {
-            ffi::vkCmdCopyBuffer(
-                command_buffer,
-                src_buffer,
-                dst_buffer,
-                1,
-                std::ptr::addr_of!(copy_region),
-            );
             ffi::vkEndCommandBuffer(command_buffer);
         }
 
@@ -1847,82 +2675,836 @@ impl VulkanApp {
         submit_info.commandBufferCount = 1;
         submit_info.pCommandBuffers = std::ptr::addr_of!(command_buffer);
 
-        unsafe {
+        let signal_value = self.timeline.next_value();
+        submit_info.signalSemaphoreCount = 1;
+        submit_info.pSignalSemaphores = std::ptr::addr_of!(self.timeline.semaphore);
+
+        let mut timeline_info: ffi::VkTimelineSemaphoreSubmitInfo = unsafe { std::mem::zeroed() };
+        timeline_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_TIMELINE_SEMAPHORE_SUBMIT_INFO;
+        timeline_info.signalSemaphoreValueCount = 1;
+        timeline_info.pSignalSemaphoreValues = std::ptr::addr_of!(signal_value);
+        submit_info.pNext = std::ptr::addr_of!(timeline_info) as *const c_void;
+
+        let result = unsafe {
             ffi::vkQueueSubmit(
                 self.graphics_queue,
                 1,
                 std::ptr::addr_of!(submit_info),
                 std::ptr::null_mut(),
-            );
-            ffi::vkQueueWaitIdle(self.graphics_queue);
-            ffi::vkFreeCommandBuffers(
-                self.device,
-                self.command_pool,
-                1,
-                std::ptr::addr_of!(command_buffer),
-            );
+            )
+        };
+        if result != ffi::VkResult_VK_SUCCESS {
+            return Err(String::from("Failed to submit upload commands!"));
         }
+        self.timeline.mark_submitted(signal_value);
 
-        Ok(())
+        self.timeline.defer_release(
+            signal_value,
+            Release::CommandBuffer(self.command_pool, command_buffer),
+        );
+
+        Ok(signal_value)
     }
 
     fn create_index_buffer(&mut self) -> Result<(), String> {
-        let buffer_size: ffi::VkDeviceSize =
-            (std::mem::size_of::<u16>() * VERTEX_INDICES.len()) as u64;
+        let (idx_buf, idx_buf_mem) = self.create_device_local_buffer(
+            helper::slice_as_bytes(&VERTEX_INDICES),
+            ffi::VkBufferUsageFlagBits_VK_BUFFER_USAGE_INDEX_BUFFER_BIT,
+        )?;
 
-        let (buf, buf_mem) = self.create_buffer(
-            buffer_size,
-            ffi::VkBufferUsageFlagBits_VK_BUFFER_USAGE_TRANSFER_SRC_BIT,
-            ffi::VkMemoryPropertyFlagBits_VK_MEMORY_PROPERTY_HOST_VISIBLE_BIT
-                | ffi::VkMemoryPropertyFlagBits_VK_MEMORY_PROPERTY_HOST_COHERENT_BIT,
+        self.index_buffer = idx_buf;
+        self.index_buffer_memory = idx_buf_mem;
+
+        Ok(())
+    }
+
+    fn create_instance_buffer(&mut self) -> Result<(), String> {
+        let instances = create_instance_grid(INSTANCE_GRID_SIZE);
+
+        let (buffer, buffer_mem) = self.create_device_local_buffer(
+            helper::slice_as_bytes(&instances),
+            ffi::VkBufferUsageFlagBits_VK_BUFFER_USAGE_VERTEX_BUFFER_BIT,
         )?;
-        let _staging_inst;
-        cleanup_func!(
-            func: move || unsafe {
-                ffi::vkDestroyBuffer(device_copy, buf_copy, std::ptr::null());
-                ffi::vkFreeMemory(device_copy, buf_mem_copy, std::ptr::null());
+
+        self.instance_buffer = buffer;
+        self.instance_buffer_memory = buffer_mem;
+        self.instance_count = instances.len() as u32;
+
+        Ok(())
+    }
+
+    fn create_indirect_buffer(&mut self) -> Result<(), String> {
+        // Half of the instances are drawn as quads, the rest as triangles. The triangles start
+        // at a non-zero `firstInstance`, which is why `drawIndirectFirstInstance` is required.
+        let quad_count = self.instance_count / 2;
+        let mut draw_batch = DrawBatch::new();
+        draw_batch.push(QUAD_MESH, 0, quad_count);
+        draw_batch.push(TRIANGLE_MESH, quad_count, self.instance_count - quad_count);
+
+        // Storage usage so a culling compute pass can rewrite the commands on the GPU.
+        let (buffer, buffer_mem) = self.create_device_local_buffer(
+            helper::slice_as_bytes(draw_batch.commands()),
+            ffi::VkBufferUsageFlagBits_VK_BUFFER_USAGE_INDIRECT_BUFFER_BIT
+                | ffi::VkBufferUsageFlagBits_VK_BUFFER_USAGE_STORAGE_BUFFER_BIT,
+        )?;
+
+        self.indirect_buffer = buffer;
+        self.indirect_buffer_memory = buffer_mem;
+        self.draw_batch = draw_batch;
+
+        Ok(())
+    }
+
+    fn create_descriptor_pool(&mut self) -> Result<(), String> {
+        let pool_sizes: [ffi::VkDescriptorPoolSize; 3] = [
+            ffi::VkDescriptorPoolSize {
+                type_: ffi::VkDescriptorType_VK_DESCRIPTOR_TYPE_STORAGE_BUFFER,
+                descriptorCount: 16,
             },
-            hold_name: _staging_inst,
-            var_pair: self.device, device_copy,
-            var_pair: buf, buf_copy,
-            var_pair: buf_mem, buf_mem_copy
-        );
+            ffi::VkDescriptorPoolSize {
+                type_: ffi::VkDescriptorType_VK_DESCRIPTOR_TYPE_STORAGE_IMAGE,
+                descriptorCount: 4,
+            },
+            ffi::VkDescriptorPoolSize {
+                type_: ffi::VkDescriptorType_VK_DESCRIPTOR_TYPE_COMBINED_IMAGE_SAMPLER,
+                descriptorCount: 16,
+            },
+        ];
 
-        let mut data_ptr: *mut c_void = std::ptr::null_mut();
-        unsafe {
-            ffi::vkMapMemory(
+        let mut pool_info: ffi::VkDescriptorPoolCreateInfo = unsafe { std::mem::zeroed() };
+        pool_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_DESCRIPTOR_POOL_CREATE_INFO;
+        pool_info.poolSizeCount = pool_sizes.len() as u32;
+        pool_info.pPoolSizes = pool_sizes.as_ptr();
+        pool_info.maxSets = 16;
+
+        let result = unsafe {
+            ffi::vkCreateDescriptorPool(
                 self.device,
-                buf_mem,
-                0,
-                buffer_size,
+                std::ptr::addr_of!(pool_info),
+                std::ptr::null(),
+                std::ptr::addr_of_mut!(self.descriptor_pool),
+            )
+        };
+        if result != ffi::VkResult_VK_SUCCESS {
+            return Err(String::from("Failed to create descriptor pool!"));
+        }
+
+        Ok(())
+    }
+
+    fn create_compute_pipeline(&mut self) -> Result<(), String> {
+        let comp_shader_module = self.create_shader_module(std::include_bytes!(concat!(
+            env!("OUT_DIR"),
+            "/vertex_anim.spv"
+        )))?;
+
+        self.compute_descriptor_set_layout = compute::create_descriptor_set_layout(
+            self.device,
+            &[
+                (0, DescriptorKind::StorageBuffer),
+                (1, DescriptorKind::StorageBuffer),
+            ],
+            ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_COMPUTE_BIT,
+        )?;
+
+        self.compute_pipeline_layout = compute::create_pipeline_layout(
+            self.device,
+            &[self.compute_descriptor_set_layout],
+            std::mem::size_of::<VertexAnimPushConstants>() as u32,
+            ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_COMPUTE_BIT,
+        )?;
+
+        self.compute_pipeline = compute::create_compute_pipeline(
+            self.device,
+            comp_shader_module.get_module(),
+            self.compute_pipeline_layout,
+        )?;
+
+        self.compute_descriptor_set = compute::allocate_descriptor_set(
+            self.device,
+            self.descriptor_pool,
+            self.compute_descriptor_set_layout,
+        )?;
+
+        let vertices_size = std::mem::size_of_val(&VERTICES) as ffi::VkDeviceSize;
+        compute::write_storage_buffer(
+            self.device,
+            self.compute_descriptor_set,
+            0,
+            self.base_vertex_buffer,
+            vertices_size,
+        );
+        compute::write_storage_buffer(
+            self.device,
+            self.compute_descriptor_set,
+            1,
+            self.vertex_buffer,
+            vertices_size,
+        );
+
+        Ok(())
+    }
+
+    fn record_vertex_animation(&self, command_buffer: ffi::VkCommandBuffer) {
+        let push_constants = VertexAnimPushConstants {
+            time: self.clock.elapsed(),
+            vertex_count: VERTICES.len() as u32,
+        };
+
+        compute::record_dispatch(
+            command_buffer,
+            self.compute_pipeline,
+            self.compute_pipeline_layout,
+            self.compute_descriptor_set,
+            &push_constants,
+            [
+                compute::group_count(VERTICES.len() as u32, VERTEX_ANIM_LOCAL_SIZE),
+                1,
+                1,
+            ],
+        );
+    }
+
+    fn create_particle_system(&mut self) -> Result<(), String> {
+        let comp_shader_module = self.create_shader_module(std::include_bytes!(concat!(
+            env!("OUT_DIR"),
+            "/particles.spv"
+        )))?;
+        let vert_shader_module = self.create_shader_module(std::include_bytes!(concat!(
+            env!("OUT_DIR"),
+            "/particle_vert.spv"
+        )))?;
+        let frag_shader_module = self.create_shader_module(std::include_bytes!(concat!(
+            env!("OUT_DIR"),
+            "/particle_frag.spv"
+        )))?;
+
+        // Every particle starts out dead (age == lifetime == 0) until the emitter reaches it.
+        let initial = vec![Particle::default(); self.particles.config.max_particles as usize];
+        let (buffer, buffer_mem) = self.create_device_local_buffer(
+            helper::slice_as_bytes(&initial),
+            ffi::VkBufferUsageFlagBits_VK_BUFFER_USAGE_VERTEX_BUFFER_BIT
+                | ffi::VkBufferUsageFlagBits_VK_BUFFER_USAGE_STORAGE_BUFFER_BIT,
+        )?;
+        self.particles.buffer = buffer;
+        self.particles.buffer_memory = buffer_mem;
+
+        self.particles.descriptor_set_layout = compute::create_descriptor_set_layout(
+            self.device,
+            &[(0, DescriptorKind::StorageBuffer)],
+            ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_COMPUTE_BIT,
+        )?;
+
+        self.particles.sim_pipeline_layout = compute::create_pipeline_layout(
+            self.device,
+            &[self.particles.descriptor_set_layout],
+            std::mem::size_of::<particles::ParticleSimPushConstants>() as u32,
+            ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_COMPUTE_BIT,
+        )?;
+
+        self.particles.sim_pipeline = compute::create_compute_pipeline(
+            self.device,
+            comp_shader_module.get_module(),
+            self.particles.sim_pipeline_layout,
+        )?;
+
+        self.particles.descriptor_set = compute::allocate_descriptor_set(
+            self.device,
+            self.descriptor_pool,
+            self.particles.descriptor_set_layout,
+        )?;
+        compute::write_storage_buffer(
+            self.device,
+            self.particles.descriptor_set,
+            0,
+            self.particles.buffer,
+            self.particles.buffer_size(),
+        );
+
+        self.particles.render_pipeline_layout = compute::create_pipeline_layout(
+            self.device,
+            &[],
+            std::mem::size_of::<particles::ParticleDrawPushConstants>() as u32,
+            ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_VERTEX_BIT,
+        )?;
+
+        self.particles.render_pipeline = self
+            .graphics_pipeline_builder(self.particles.render_pipeline_layout)
+            .shader_stage(
+                ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_VERTEX_BIT,
+                vert_shader_module.get_module(),
+            )
+            .shader_stage(
+                ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_FRAGMENT_BIT,
+                frag_shader_module.get_module(),
+            )
+            .specialization_constant(
+                ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_FRAGMENT_BIT,
                 0,
-                std::ptr::addr_of_mut!(data_ptr),
+                SCENE_ENCODING as u32,
+            )
+            .vertex_input(
+                &[Particle::get_binding_description()],
+                &Particle::get_attribute_descriptions(),
+            )
+            .topology(ffi::VkPrimitiveTopology_VK_PRIMITIVE_TOPOLOGY_POINT_LIST)
+            .cull_mode(ffi::VkCullModeFlagBits_VK_CULL_MODE_NONE)
+            .blend_mode(BlendMode::Additive)
+            .build(self.device)?;
+
+        Ok(())
+    }
+
+    fn create_profiler(&mut self) -> Result<(), String> {
+        let mut dev_props: ffi::VkPhysicalDeviceProperties = unsafe { std::mem::zeroed() };
+        unsafe {
+            ffi::vkGetPhysicalDeviceProperties(
+                self.physical_device,
+                std::ptr::addr_of_mut!(dev_props),
             );
-            let data_ptr_indices: *mut [u16; VERTEX_INDICES.len()] = std::mem::transmute(data_ptr);
-            *data_ptr_indices = VERTEX_INDICES;
-            ffi::vkUnmapMemory(self.device, buf_mem);
         }
 
-        let (idx_buf, idx_buf_mem) = self.create_buffer(
-            buffer_size,
-            ffi::VkBufferUsageFlagBits_VK_BUFFER_USAGE_TRANSFER_DST_BIT
-                | ffi::VkBufferUsageFlagBits_VK_BUFFER_USAGE_INDEX_BUFFER_BIT,
-            ffi::VkMemoryPropertyFlagBits_VK_MEMORY_PROPERTY_DEVICE_LOCAL_BIT,
+        let graphics_family = self
+            .find_queue_families(self.physical_device)
+            .graphics_family
+            .ok_or_else(|| String::from("Missing graphics queue family!"))?;
+        let valid_bits = self.queue_family_properties(self.physical_device)
+            [graphics_family as usize]
+            .timestampValidBits;
+
+        self.profiler
+            .create(self.device, &dev_props.limits, valid_bits)
+    }
+
+    /// Names the objects that live as long as the device, see `name_swap_chain_objects` for the
+    /// rest.
+    fn name_objects(&self) {
+        let debug = &self.debug_utils;
+        if !debug.is_enabled() {
+            return;
+        }
+        let device = self.device;
+
+        debug.set_name(device, self.graphics_queue, "graphics queue");
+        if self.present_queue != self.graphics_queue {
+            debug.set_name(device, self.present_queue, "present queue");
+        }
+        debug.set_name(
+            device,
+            self.image_available_semaphore,
+            "image available semaphore",
+        );
+        debug.set_name(
+            device,
+            self.render_finished_semaphore,
+            "render finished semaphore",
+        );
+        debug.set_name(device, self.timeline.semaphore, "frame timeline");
+        debug.set_name(device, self.command_pool, "command pool");
+        debug.set_name(device, self.command_buffer, "frame command buffer");
+        debug.set_name(device, self.descriptor_pool, "descriptor pool");
+
+        let buffers = [
+            (
+                self.vertex_buffer,
+                self.vertex_buffer_memory,
+                "vertex buffer",
+            ),
+            (
+                self.base_vertex_buffer,
+                self.base_vertex_buffer_memory,
+                "base vertex buffer",
+            ),
+            (self.index_buffer, self.index_buffer_memory, "index buffer"),
+            (
+                self.instance_buffer,
+                self.instance_buffer_memory,
+                "instance buffer",
+            ),
+            (
+                self.indirect_buffer,
+                self.indirect_buffer_memory,
+                "indirect buffer",
+            ),
+            (
+                self.particles.buffer,
+                self.particles.buffer_memory,
+                "particle buffer",
+            ),
+        ];
+        for (buffer, memory, name) in buffers {
+            debug.set_name(device, buffer, name);
+            debug.set_name(device, memory, &format!("{} memory", name));
+        }
+
+        debug.set_name(device, self.render_pass, "scene render pass");
+        debug.set_name(device, self.pipeline_layout, "scene pipeline layout");
+        debug.set_name(device, self.graphics_pipeline, "scene pipeline");
+        debug.set_name(
+            device,
+            self.compute_descriptor_set_layout,
+            "vertex animation descriptor set layout",
+        );
+        debug.set_name(
+            device,
+            self.compute_descriptor_set,
+            "vertex animation descriptor set",
+        );
+        debug.set_name(
+            device,
+            self.compute_pipeline_layout,
+            "vertex animation pipeline layout",
+        );
+        debug.set_name(device, self.compute_pipeline, "vertex animation pipeline");
+
+        let particles = &self.particles;
+        debug.set_name(
+            device,
+            particles.descriptor_set_layout,
+            "particle descriptor set layout",
+        );
+        debug.set_name(device, particles.descriptor_set, "particle descriptor set");
+        debug.set_name(
+            device,
+            particles.sim_pipeline_layout,
+            "particle simulation pipeline layout",
+        );
+        debug.set_name(
+            device,
+            particles.sim_pipeline,
+            "particle simulation pipeline",
+        );
+        debug.set_name(
+            device,
+            particles.render_pipeline_layout,
+            "particle pipeline layout",
+        );
+        debug.set_name(device, particles.render_pipeline, "particle pipeline");
+
+        let post = &self.post;
+        debug.set_name(device, post.sampler, "post sampler");
+        debug.set_name(
+            device,
+            post.descriptor_set_layout,
+            "post descriptor set layout",
+        );
+        debug.set_name(device, post.pipeline_layout, "post pipeline layout");
+        debug.set_name(device, post.hdr_render_pass, "post HDR render pass");
+        debug.set_name(device, post.output_render_pass, "post output render pass");
+        for (pass, effect) in post.passes.iter().enumerate() {
+            let name = format!("post: {}", effect.name());
+            debug.set_name(device, post.pipelines[pass], &format!("{} pipeline", name));
+            debug.set_name(
+                device,
+                post.descriptor_sets[pass],
+                &format!("{} descriptor set", name),
+            );
+        }
+        if let Some(lut) = &post.lut {
+            debug.set_image_names(device, lut, "color LUT");
+        }
+
+        debug.set_name(device, self.profiler.query_pool, "timestamp queries");
+        debug.set_name(
+            device,
+            self.queries.statistics_pool,
+            "pipeline statistics queries",
+        );
+        debug.set_name(device, self.queries.occlusion_pool, "occlusion queries");
+    }
+
+    /// Names everything `recreate_swap_chain` replaces.
+    fn name_swap_chain_objects(&self) {
+        let debug = &self.debug_utils;
+        if !debug.is_enabled() {
+            return;
+        }
+        let device = self.device;
+
+        debug.set_name(device, self.swap_chain, "swapchain");
+        for (idx, (&image, &view)) in self
+            .swap_chain_images
+            .iter()
+            .zip(&self.swap_chain_image_views)
+            .enumerate()
+        {
+            debug.set_name(device, image, &format!("swapchain image {}", idx));
+            debug.set_name(device, view, &format!("swapchain image {} view", idx));
+        }
+        for (idx, &framebuffer) in self.swap_chain_framebuffers.iter().enumerate() {
+            debug.set_name(
+                device,
+                framebuffer,
+                &format!("swapchain framebuffer {}", idx),
+            );
+        }
+    }
+
+    fn create_queries(&mut self) -> Result<(), String> {
+        if !self.config.gpu_stats {
+            return Ok(());
+        }
+
+        let statistics = self
+            .enabled_features
+            .has_feature(Feature::PipelineStatisticsQuery);
+        if !statistics {
+            println!("pipelineStatisticsQuery isn't supported, only occlusion queries are enabled");
+        }
+        let precise = self
+            .enabled_features
+            .has_feature(Feature::OcclusionQueryPrecise);
+
+        self.queries.create(self.device, statistics, precise)
+    }
+
+    fn create_post_process(&mut self) -> Result<(), String> {
+        let vert_shader_module = self.create_shader_module(std::include_bytes!(concat!(
+            env!("OUT_DIR"),
+            "/post_vert.spv"
+        )))?;
+        let frag_shader_module = self.create_shader_module(std::include_bytes!(concat!(
+            env!("OUT_DIR"),
+            "/post_frag.spv"
+        )))?;
+
+        if self.post.has_lut_pass() {
+            let lut = match &self.config.lut {
+                Some(path) => ColorLut::load(path)?,
+                None => ColorLut::identity(post_process::DEFAULT_LUT_SIZE),
+            };
+            let lut_image = AllocatedImage::create(
+                self.device,
+                &self.memory_properties(),
+                post_process::LUT_FORMAT,
+                lut.strip_extent(),
+                ffi::VkImageUsageFlagBits_VK_IMAGE_USAGE_SAMPLED_BIT
+                    | ffi::VkImageUsageFlagBits_VK_IMAGE_USAGE_TRANSFER_DST_BIT,
+                ffi::VkImageAspectFlagBits_VK_IMAGE_ASPECT_COLOR_BIT,
+                ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_1_BIT,
+            )?;
+            self.post.lut = Some(lut_image);
+            self.post.lut_size = lut.size;
+            self.upload_image(&lut_image, &lut.strip_pixels())?;
+        }
+
+        self.post.sampler = post_process::create_sampler(self.device)?;
+
+        self.post.descriptor_set_layout = compute::create_descriptor_set_layout(
+            self.device,
+            &[
+                (0, DescriptorKind::CombinedImageSampler),
+                (1, DescriptorKind::CombinedImageSampler),
+            ],
+            ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_FRAGMENT_BIT,
         )?;
 
-        self.index_buffer = idx_buf;
-        self.index_buffer_memory = idx_buf_mem;
+        self.post.pipeline_layout = compute::create_pipeline_layout(
+            self.device,
+            &[self.post.descriptor_set_layout],
+            std::mem::size_of::<post_process::PostPushConstants>() as u32,
+            ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_FRAGMENT_BIT,
+        )?;
+
+        for pass in 0..self.post.pass_count() {
+            let descriptor_set = compute::allocate_descriptor_set(
+                self.device,
+                self.descriptor_pool,
+                self.post.descriptor_set_layout,
+            )?;
+            self.post.descriptor_sets.push(descriptor_set);
+
+            let (render_pass, format, encoding) = if self.post.is_last(pass) {
+                (
+                    self.post.output_render_pass,
+                    self.swap_chain_image_format,
+                    self.output_encoding(),
+                )
+            } else {
+                (
+                    self.post.hdr_render_pass,
+                    post_process::HDR_FORMAT,
+                    OutputEncoding::Linear,
+                )
+            };
 
-        self.copy_buffer(buf, self.index_buffer, buffer_size)?;
+            let builder = GraphicsPipelineBuilder::new(self.post.pipeline_layout, render_pass)
+                .shader_stage(
+                    ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_VERTEX_BIT,
+                    vert_shader_module.get_module(),
+                )
+                .shader_stage(
+                    ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_FRAGMENT_BIT,
+                    frag_shader_module.get_module(),
+                )
+                .specialization_constant(
+                    ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_FRAGMENT_BIT,
+                    0,
+                    encoding as u32,
+                )
+                .specialization_constant(
+                    ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_FRAGMENT_BIT,
+                    1,
+                    self.post.passes[pass] as u32,
+                )
+                .cull_mode(ffi::VkCullModeFlagBits_VK_CULL_MODE_NONE)
+                .blend_mode(BlendMode::Opaque);
+            let builder = if self.uses_dynamic_rendering() {
+                builder.dynamic_rendering(&[format], ffi::VkFormat_VK_FORMAT_UNDEFINED)
+            } else {
+                builder
+            };
+
+            let pipeline = builder.build(self.device)?;
+            self.post.pipelines.push(pipeline);
+        }
+
+        let names: Vec<&str> = self
+            .post
+            .passes
+            .iter()
+            .map(|effect| effect.name())
+            .collect();
+        println!("Post-processing: {}", names.join(", "));
 
         Ok(())
     }
+
+    fn update(&mut self) {
+        let delta_time = self.clock.tick();
+        // Time spent minimized isn't a frame.
+        if !self.minimized {
+            self.frame_stats.record(self.clock.frame_time());
+        }
+        self.update_title();
+
+        self.input.poll_gamepad();
+        if self.input.action_pressed(Action::ToggleVsync) {
+            self.toggle_vsync();
+        }
+        if self.input.action_pressed(Action::ToggleCamera) {
+            self.toggle_camera_mode();
+        }
+        if self.input.action_pressed(Action::PrintGpuTimings) {
+            match &self.profiler.last_frame {
+                Some(timings) => println!("{}", timings),
+                None => println!("No GPU timings available"),
+            }
+        }
+        if self.input.action_pressed(Action::PrintGpuStats) {
+            self.print_gpu_stats();
+        }
+
+        // With a fixed step the simulation only advances in whole steps.
+        self.particles.begin_update();
+        match &mut self.fixed_step {
+            Some(fixed) => {
+                for _ in 0..fixed.advance(delta_time) {
+                    self.particles.update(fixed.step());
+                }
+            }
+            None => self.particles.update(delta_time),
+        }
+
+        let input = self.camera_input(delta_time);
+        self.camera_controller
+            .update(&input, delta_time, &mut self.camera);
+
+        self.input.end_frame();
+    }
+
+    fn print_gpu_stats(&self) {
+        if !self.queries.occlusion_enabled() {
+            println!("GPU queries are disabled, run with --gpu-stats");
+            return;
+        }
+
+        for (pass, counters) in &self.queries.last_statistics {
+            println!("{}: {}", pass, counters);
+        }
+        for (draw, &samples) in self.queries.last_occlusion.iter().enumerate() {
+            if self.queries.precise_occlusion {
+                let overdraw =
+                    queries::overdraw(samples, self.swap_chain_extent, self.msaa_samples);
+                println!(
+                    "draw {}: {} samples, {:.2}x overdraw",
+                    draw, samples, overdraw
+                );
+            } else {
+                let visibility = if samples > 0 { "visible" } else { "hidden" };
+                println!("draw {}: {}", draw, visibility);
+            }
+        }
+    }
+
+    fn camera_input(&self, delta_time: f32) -> CameraInput {
+        let input = &self.input;
+        let stick = |axis: u32| input.gamepad_axis(axis);
+        let look_speed = GAMEPAD_LOOK_SPEED * delta_time;
+        // The FPS camera captures the cursor, so every movement looks around.
+        let drag = if self.camera_controller.mode() == CameraMode::Fps
+            || input.action_held(Action::Look)
+        {
+            input.cursor_delta()
+        } else {
+            [0.0, 0.0]
+        };
+
+        CameraInput {
+            forward: (input.action_axis(Action::MoveForward, Action::MoveBack)
+                - stick(ffi::GLFW_GAMEPAD_AXIS_LEFT_Y))
+            .clamp(-1.0, 1.0),
+            right: (input.action_axis(Action::MoveRight, Action::MoveLeft)
+                + stick(ffi::GLFW_GAMEPAD_AXIS_LEFT_X))
+            .clamp(-1.0, 1.0),
+            drag: [
+                drag[0] + stick(ffi::GLFW_GAMEPAD_AXIS_RIGHT_X) * look_speed,
+                drag[1] + stick(ffi::GLFW_GAMEPAD_AXIS_RIGHT_Y) * look_speed,
+            ],
+            scroll: input.scroll()[1],
+        }
+    }
+
+    fn update_title(&mut self) {
+        if self.last_title_update.elapsed() < TITLE_REFRESH_INTERVAL {
+            return;
+        }
+        self.last_title_update = Instant::now();
+
+        if let Some(summary) = self.frame_stats.summary() {
+            let mut title = format!(
+                "{} - {:.0} fps ({:.2} ms, p99 {:.2} ms)",
+                WINDOW_TITLE,
+                summary.fps(),
+                summary.avg_ms,
+                summary.p99_ms
+            );
+            if let Some(timings) = &self.profiler.last_frame {
+                title.push_str(&format!(", GPU {:.2} ms", timings.total_ms));
+            }
+            let title = CString::new(title).unwrap();
+            unsafe {
+                ffi::glfwSetWindowTitle(self.window, title.as_ptr());
+            }
+        }
+    }
+
+    fn toggle_camera_mode(&mut self) {
+        let mode = self.camera_controller.mode().toggled();
+        self.camera_controller = CameraController::new(mode, &self.camera);
+        self.update_cursor_mode();
+        println!("Camera: {}", mode.name());
+    }
+
+    /// Hides and locks the cursor for the FPS camera and gives it back for the orbit camera.
+    fn update_cursor_mode(&mut self) {
+        let fps = self.camera_controller.mode() == CameraMode::Fps;
+        let cursor = if fps {
+            ffi::GLFW_CURSOR_DISABLED
+        } else {
+            ffi::GLFW_CURSOR_NORMAL
+        };
+        unsafe {
+            ffi::glfwSetInputMode(self.window, ffi::GLFW_CURSOR as i32, cursor as i32);
+            if ffi::glfwRawMouseMotionSupported() == ffi::GLFW_TRUE as i32 {
+                ffi::glfwSetInputMode(self.window, ffi::GLFW_RAW_MOUSE_MOTION as i32, fps as i32);
+            }
+        }
+        self.input.reset_cursor();
+    }
+}
+
+impl InputHandler for VulkanApp {
+    fn input(&mut self) -> &mut Input {
+        &mut self.input
+    }
+
+    fn framebuffer_resized(&mut self, _width: i32, _height: i32) {
+        self.set_resize_flag();
+    }
 }
 
 impl Drop for VulkanApp {
     fn drop(&mut self) {
         self.cleanup_swap_chain().unwrap();
 
+        self.particles.destroy(self.device);
+        self.profiler.destroy(self.device);
+        self.queries.destroy(self.device);
+        self.post.destroy(self.device);
+
+        if !self.compute_pipeline.is_null() {
+            unsafe {
+                ffi::vkDestroyPipeline(self.device, self.compute_pipeline, std::ptr::null());
+            }
+        }
+
+        if !self.compute_pipeline_layout.is_null() {
+            unsafe {
+                ffi::vkDestroyPipelineLayout(
+                    self.device,
+                    self.compute_pipeline_layout,
+                    std::ptr::null(),
+                );
+            }
+        }
+
+        if !self.compute_descriptor_set_layout.is_null() {
+            unsafe {
+                ffi::vkDestroyDescriptorSetLayout(
+                    self.device,
+                    self.compute_descriptor_set_layout,
+                    std::ptr::null(),
+                );
+            }
+        }
+
+        if !self.descriptor_pool.is_null() {
+            unsafe {
+                ffi::vkDestroyDescriptorPool(self.device, self.descriptor_pool, std::ptr::null());
+            }
+        }
+
+        if !self.base_vertex_buffer.is_null() {
+            unsafe {
+                ffi::vkDestroyBuffer(self.device, self.base_vertex_buffer, std::ptr::null());
+            }
+        }
+
+        if !self.base_vertex_buffer_memory.is_null() {
+            unsafe {
+                ffi::vkFreeMemory(
+                    self.device,
+                    self.base_vertex_buffer_memory,
+                    std::ptr::null(),
+                );
+            }
+        }
+
+        if !self.indirect_buffer.is_null() {
+            unsafe {
+                ffi::vkDestroyBuffer(self.device, self.indirect_buffer, std::ptr::null());
+            }
+        }
+
+        if !self.indirect_buffer_memory.is_null() {
+            unsafe {
+                ffi::vkFreeMemory(self.device, self.indirect_buffer_memory, std::ptr::null());
+            }
+        }
+
+        if !self.instance_buffer.is_null() {
+            unsafe {
+                ffi::vkDestroyBuffer(self.device, self.instance_buffer, std::ptr::null());
+            }
+        }
+
+        if !self.instance_buffer_memory.is_null() {
+            unsafe {
+                ffi::vkFreeMemory(self.device, self.instance_buffer_memory, std::ptr::null());
+            }
+        }
+
         if !self.index_buffer.is_null() {
             unsafe {
                 ffi::vkDestroyBuffer(self.device, self.index_buffer, std::ptr::null());
@@ -1947,11 +3529,15 @@ impl Drop for VulkanApp {
             }
         }
 
-        if !self.in_flight_fence.is_null() {
+        for framebuffer in self.graph_framebuffers.take_all() {
             unsafe {
-                ffi::vkDestroyFence(self.device, self.in_flight_fence, std::ptr::null());
+                ffi::vkDestroyFramebuffer(self.device, framebuffer, std::ptr::null());
             }
         }
+        for image in self.transient_images.drain() {
+            image.destroy(self.device);
+        }
+        self.timeline.destroy(self.device);
 
         if !self.render_finished_semaphore.is_null() {
             unsafe {
@@ -2003,7 +3589,7 @@ impl Drop for VulkanApp {
             }
         }
 
-        if ENABLE_VALIDATION_LAYERS && !self.debug_messenger.is_null() {
+        if !self.debug_messenger.is_null() {
             let func_opt: ffi::PFN_vkDestroyDebugUtilsMessengerEXT = unsafe {
                 std::mem::transmute(ffi::vkGetInstanceProcAddr(
                     self.vk_instance,
@@ -2044,10 +3630,57 @@ impl Drop for VulkanApp {
     }
 }
 
+fn create_instance_grid(grid_size: u32) -> Vec<InstanceData> {
+    let cell = 2.0 / grid_size as f32;
+    let mut instances = Vec::with_capacity((grid_size * grid_size) as usize);
+
+    for y in 0..grid_size {
+        for x in 0..grid_size {
+            let u = x as f32 / (grid_size - 1).max(1) as f32;
+            let v = y as f32 / (grid_size - 1).max(1) as f32;
+            let center = [
+                -1.0 + cell * (x as f32 + 0.5),
+                -1.0 + cell * (y as f32 + 0.5),
+                0.0,
+            ];
+
+            instances.push(InstanceData {
+                model: math3d::mat4_mul(
+                    &math3d::mat4_translation(center),
+                    &math3d::mat4_mul(
+                        &math3d::mat4_rotation_z((u + v) * std::f32::consts::FRAC_PI_4),
+                        &math3d::mat4_scale([cell * 0.8, cell * 0.8, 1.0]),
+                    ),
+                ),
+                color: [0.25 + 0.75 * u, 0.25 + 0.75 * v, 1.0 - 0.5 * u, 1.0],
+            });
+        }
+    }
+
+    instances
+}
+
 fn main() {
-    let mut app = VulkanApp::new();
+    let config = match AppConfig::from_env() {
+        Ok(config) => config,
+        Err(e) => {
+            println!("ERROR: {}\n\n{}", e, config::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if config.show_help {
+        println!("{}", config::USAGE);
+        return;
+    }
+    logger::set_min_level(config.log_level);
+
+    let mut app = VulkanApp::new(config);
 
     app.init_glfw();
+    if app.config.list_devices {
+        app.list_devices().unwrap();
+        return;
+    }
     app.init_vulkan();
     app.main_loop();
 }
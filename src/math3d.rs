@@ -2,6 +2,138 @@ use crate::ffi;
 
 type Vec2f = [f32; 2];
 type Vec3f = [f32; 3];
+type Vec4f = [f32; 4];
+
+/// Column-major, matching GLSL's `mat4`.
+pub type Mat4 = [[f32; 4]; 4];
+
+pub const MAT4_IDENTITY: Mat4 = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+pub fn mat4_mul(a: &Mat4, b: &Mat4) -> Mat4 {
+    let mut out = [[0.0; 4]; 4];
+    for (col, out_col) in out.iter_mut().enumerate() {
+        for (row, value) in out_col.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b[col][k]).sum();
+        }
+    }
+
+    out
+}
+
+pub fn mat4_translation(offset: Vec3f) -> Mat4 {
+    let mut out = MAT4_IDENTITY;
+    out[3][0] = offset[0];
+    out[3][1] = offset[1];
+    out[3][2] = offset[2];
+
+    out
+}
+
+pub fn mat4_scale(scale: Vec3f) -> Mat4 {
+    let mut out = MAT4_IDENTITY;
+    out[0][0] = scale[0];
+    out[1][1] = scale[1];
+    out[2][2] = scale[2];
+
+    out
+}
+
+pub fn mat4_rotation_z(radians: f32) -> Mat4 {
+    let (s, c) = radians.sin_cos();
+    let mut out = MAT4_IDENTITY;
+    out[0][0] = c;
+    out[0][1] = s;
+    out[1][0] = -s;
+    out[1][1] = c;
+
+    out
+}
+
+pub fn vec3_add(a: Vec3f, b: Vec3f) -> Vec3f {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub fn vec3_sub(a: Vec3f, b: Vec3f) -> Vec3f {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub fn vec3_scale(v: Vec3f, s: f32) -> Vec3f {
+    [v[0] * s, v[1] * s, v[2] * s]
+}
+
+pub fn vec3_dot(a: Vec3f, b: Vec3f) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn vec3_cross(a: Vec3f, b: Vec3f) -> Vec3f {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub fn vec3_normalize(v: Vec3f) -> Vec3f {
+    let length = vec3_dot(v, v).sqrt();
+    if length > 0.0 {
+        vec3_scale(v, 1.0 / length)
+    } else {
+        v
+    }
+}
+
+/// Right-handed view matrix looking from `eye` towards `target`.
+pub fn mat4_look_at(eye: Vec3f, target: Vec3f, up: Vec3f) -> Mat4 {
+    let f = vec3_normalize(vec3_sub(target, eye));
+    let s = vec3_normalize(vec3_cross(f, up));
+    let u = vec3_cross(s, f);
+
+    [
+        [s[0], u[0], -f[0], 0.0],
+        [s[1], u[1], -f[1], 0.0],
+        [s[2], u[2], -f[2], 0.0],
+        [-vec3_dot(s, eye), -vec3_dot(u, eye), vec3_dot(f, eye), 1.0],
+    ]
+}
+
+/// Perspective projection for Vulkan clip space: depth maps to [0, 1] and Y points down, so
+/// the Y axis is flipped to keep world space Y up.
+pub fn mat4_perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+    let f = 1.0 / (fov_y * 0.5).tan();
+    let mut out = [[0.0; 4]; 4];
+    out[0][0] = f / aspect;
+    out[1][1] = -f;
+    out[2][2] = far / (near - far);
+    out[2][3] = -1.0;
+    out[3][2] = near * far / (near - far);
+
+    out
+}
+
+#[allow(dead_code)]
+pub fn mat4_transform_vec4(m: &Mat4, v: Vec4f) -> Vec4f {
+    let mut out = [0.0; 4];
+    for (row, value) in out.iter_mut().enumerate() {
+        *value = (0..4).map(|col| m[col][row] * v[col]).sum();
+    }
+
+    out
+}
+
+#[allow(dead_code)]
+pub fn mat4_transform_point(m: &Mat4, point: Vec3f) -> Vec3f {
+    let mut out = [0.0; 3];
+    for (row, value) in out.iter_mut().enumerate() {
+        *value = m[0][row] * point[0] + m[1][row] * point[1] + m[2][row] * point[2] + m[3][row];
+    }
+
+    out
+}
 
 #[repr(C)]
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -67,6 +199,63 @@ impl Vertex {
     }
 }
 
+/// Per-instance data read from vertex binding 1.
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct InstanceData {
+    pub model: Mat4,
+    pub color: Vec4f,
+}
+
+impl Default for InstanceData {
+    fn default() -> Self {
+        Self {
+            model: MAT4_IDENTITY,
+            color: [1.0, 1.0, 1.0, 1.0],
+        }
+    }
+}
+
+impl InstanceData {
+    pub const BINDING: u32 = 1;
+    /// First shader location used by the instance attributes, after the `Vertex` ones.
+    pub const FIRST_LOCATION: u32 = 2;
+
+    pub const fn get_binding_description() -> ffi::VkVertexInputBindingDescription {
+        let mut bind_desc: ffi::VkVertexInputBindingDescription = unsafe { std::mem::zeroed() };
+
+        bind_desc.binding = Self::BINDING;
+        bind_desc.stride = std::mem::size_of::<Self>() as u32;
+        bind_desc.inputRate = ffi::VkVertexInputRate_VK_VERTEX_INPUT_RATE_INSTANCE;
+
+        bind_desc
+    }
+
+    /// A `mat4` takes one location per column, so the model matrix uses four locations followed
+    /// by one for the color.
+    pub const fn get_attribute_descriptions() -> [ffi::VkVertexInputAttributeDescription; 5] {
+        let mut attr_descs: [ffi::VkVertexInputAttributeDescription; 5] =
+            unsafe { std::mem::zeroed() };
+
+        let mut idx = 0;
+        while idx < 4 {
+            attr_descs[idx].binding = Self::BINDING;
+            attr_descs[idx].location = Self::FIRST_LOCATION + idx as u32;
+            attr_descs[idx].format = ffi::VkFormat_VK_FORMAT_R32G32B32A32_SFLOAT;
+            attr_descs[idx].offset = (std::mem::offset_of!(InstanceData, model)
+                + idx * std::mem::size_of::<Vec4f>()) as u32;
+            idx += 1;
+        }
+
+        attr_descs[4].binding = Self::BINDING;
+        attr_descs[4].location = Self::FIRST_LOCATION + 4;
+        attr_descs[4].format = ffi::VkFormat_VK_FORMAT_R32G32B32A32_SFLOAT;
+        attr_descs[4].offset = std::mem::offset_of!(InstanceData, color) as u32;
+
+        attr_descs
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -116,4 +305,78 @@ mod tests {
         assert_eq!(unsafe { *col_1_ptr }, vertex.color[1]);
         assert_eq!(unsafe { *col_2_ptr }, vertex.color[2]);
     }
+
+    fn assert_vec3_eq(a: Vec3f, b: Vec3f) {
+        for (x, y) in a.iter().zip(b.iter()) {
+            assert!((x - y).abs() < 1e-5, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn mat4_transforms() {
+        let translate = mat4_translation([1.0, 2.0, 3.0]);
+        let scale = mat4_scale([2.0, 2.0, 2.0]);
+        let rotate = mat4_rotation_z(std::f32::consts::FRAC_PI_2);
+
+        assert_eq!(mat4_mul(&MAT4_IDENTITY, &translate), translate);
+        assert_eq!(mat4_mul(&translate, &MAT4_IDENTITY), translate);
+
+        assert_vec3_eq(
+            mat4_transform_point(&rotate, [1.0, 0.0, 0.0]),
+            [0.0, 1.0, 0.0],
+        );
+
+        // Scale first, then translate.
+        let model = mat4_mul(&translate, &scale);
+        assert_vec3_eq(
+            mat4_transform_point(&model, [1.0, 1.0, 1.0]),
+            [3.0, 4.0, 5.0],
+        );
+    }
+
+    #[test]
+    fn look_at_and_perspective() {
+        let view = mat4_look_at([0.0, 0.0, 5.0], [0.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+        assert_vec3_eq(
+            mat4_transform_point(&view, [0.0, 0.0, 5.0]),
+            [0.0, 0.0, 0.0],
+        );
+        assert_vec3_eq(
+            mat4_transform_point(&view, [0.0, 0.0, 0.0]),
+            [0.0, 0.0, -5.0],
+        );
+        assert_vec3_eq(
+            mat4_transform_point(&view, [1.0, 0.0, 5.0]),
+            [1.0, 0.0, 0.0],
+        );
+
+        let proj = mat4_perspective(std::f32::consts::FRAC_PI_2, 2.0, 0.1, 100.0);
+        let project = |point: Vec3f| {
+            let clip = mat4_transform_vec4(&proj, [point[0], point[1], point[2], 1.0]);
+            [clip[0] / clip[3], clip[1] / clip[3], clip[2] / clip[3]]
+        };
+
+        assert_vec3_eq(project([0.0, 0.0, -0.1]), [0.0, 0.0, 0.0]);
+        assert_vec3_eq(project([0.0, 0.0, -100.0]), [0.0, 0.0, 1.0]);
+        // A 90 degree field of view reaches the top edge at y == -z, which is -1 in Vulkan.
+        let top = project([0.0, 1.0, -1.0]);
+        assert!((top[1] + 1.0).abs() < 1e-5);
+        // The aspect ratio squeezes X.
+        let right = project([2.0, 0.0, -1.0]);
+        assert!((right[0] - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn instance_attributes() {
+        assert_eq!(std::mem::size_of::<InstanceData>(), 80);
+
+        let attrs = InstanceData::get_attribute_descriptions();
+        let offsets: Vec<u32> = attrs.iter().map(|attr| attr.offset).collect();
+        let locations: Vec<u32> = attrs.iter().map(|attr| attr.location).collect();
+        assert_eq!(offsets, [0, 16, 32, 48, 64]);
+        assert_eq!(locations, [2, 3, 4, 5, 6]);
+        assert!(attrs
+            .iter()
+            .all(|attr| attr.binding == InstanceData::BINDING));
+    }
 }
@@ -0,0 +1,116 @@
+use crate::ffi;
+
+const SAMPLE_COUNTS: [ffi::VkSampleCountFlagBits; 7] = [
+    ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_1_BIT,
+    ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_2_BIT,
+    ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_4_BIT,
+    ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_8_BIT,
+    ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_16_BIT,
+    ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_32_BIT,
+    ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_64_BIT,
+];
+
+/// Requested MSAA sample count.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MsaaSetting {
+    Samples(u32),
+    /// The highest count usable for both color and depth attachments.
+    Max,
+}
+
+impl Default for MsaaSetting {
+    fn default() -> Self {
+        MsaaSetting::Samples(4)
+    }
+}
+
+impl std::str::FromStr for MsaaSetting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "max" => Ok(MsaaSetting::Max),
+            "off" => Ok(MsaaSetting::Samples(1)),
+            other => match other.parse::<u32>() {
+                Ok(count) if count.is_power_of_two() && count <= 64 => {
+                    Ok(MsaaSetting::Samples(count))
+                }
+                _ => Err(format!(
+                    "Unknown MSAA setting \"{}\", expected off, max or 1, 2, 4, 8, 16, 32, 64",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+/// Sample counts supported by both color and depth framebuffer attachments.
+pub fn usable_sample_counts(limits: &ffi::VkPhysicalDeviceLimits) -> ffi::VkSampleCountFlags {
+    limits.framebufferColorSampleCounts & limits.framebufferDepthSampleCounts
+}
+
+pub fn max_sample_count(supported: ffi::VkSampleCountFlags) -> ffi::VkSampleCountFlagBits {
+    SAMPLE_COUNTS
+        .iter()
+        .rev()
+        .copied()
+        .find(|count| supported & count != 0)
+        .unwrap_or(ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_1_BIT)
+}
+
+/// The requested count if it is supported, otherwise the highest supported count below it.
+pub fn choose_sample_count(
+    setting: MsaaSetting,
+    supported: ffi::VkSampleCountFlags,
+) -> ffi::VkSampleCountFlagBits {
+    match setting {
+        MsaaSetting::Max => max_sample_count(supported),
+        MsaaSetting::Samples(requested) => {
+            max_sample_count(supported & (requested | (requested.max(1) - 1)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_setting() {
+        assert_eq!("max".parse(), Ok(MsaaSetting::Max));
+        assert_eq!("off".parse(), Ok(MsaaSetting::Samples(1)));
+        assert_eq!("8".parse(), Ok(MsaaSetting::Samples(8)));
+        assert!("3".parse::<MsaaSetting>().is_err());
+        assert!("128".parse::<MsaaSetting>().is_err());
+    }
+
+    #[test]
+    fn sample_count_selection() {
+        let mut limits: ffi::VkPhysicalDeviceLimits = unsafe { std::mem::zeroed() };
+        limits.framebufferColorSampleCounts = 0b1111;
+        limits.framebufferDepthSampleCounts = 0b0111;
+        let supported = usable_sample_counts(&limits);
+
+        assert_eq!(
+            choose_sample_count(MsaaSetting::Max, supported),
+            ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_4_BIT
+        );
+        assert_eq!(
+            choose_sample_count(MsaaSetting::Samples(2), supported),
+            ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_2_BIT
+        );
+        // Color alone could do 8, but depth can't.
+        assert_eq!(
+            choose_sample_count(MsaaSetting::Samples(8), supported),
+            ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_4_BIT
+        );
+        assert_eq!(
+            choose_sample_count(MsaaSetting::Samples(1), supported),
+            ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_1_BIT
+        );
+        assert_eq!(
+            choose_sample_count(MsaaSetting::Max, 0),
+            ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_1_BIT
+        );
+    }
+}
@@ -0,0 +1,431 @@
+use crate::barrier::{Barriers, Usage};
+use crate::compute;
+use crate::ffi;
+use crate::math3d::Mat4;
+
+pub const PARTICLE_LOCAL_SIZE: u32 = 256;
+
+/// Matches the std430 layout of `Particle` in `shaders/particles.comp`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Particle {
+    pub pos: [f32; 2],
+    pub vel: [f32; 2],
+    pub color: [f32; 4],
+    pub age: f32,
+    pub lifetime: f32,
+    pub _pad: [f32; 2],
+}
+
+impl Particle {
+    pub const fn get_binding_description() -> ffi::VkVertexInputBindingDescription {
+        let mut bind_desc: ffi::VkVertexInputBindingDescription = unsafe { std::mem::zeroed() };
+
+        bind_desc.binding = 0;
+        bind_desc.stride = std::mem::size_of::<Self>() as u32;
+        bind_desc.inputRate = ffi::VkVertexInputRate_VK_VERTEX_INPUT_RATE_VERTEX;
+
+        bind_desc
+    }
+
+    pub const fn get_attribute_descriptions() -> [ffi::VkVertexInputAttributeDescription; 3] {
+        let mut attr_descs: [ffi::VkVertexInputAttributeDescription; 3] =
+            unsafe { std::mem::zeroed() };
+
+        attr_descs[0].binding = 0;
+        attr_descs[0].location = 0;
+        attr_descs[0].format = ffi::VkFormat_VK_FORMAT_R32G32_SFLOAT;
+        attr_descs[0].offset = std::mem::offset_of!(Particle, pos) as u32;
+
+        attr_descs[1].binding = 0;
+        attr_descs[1].location = 1;
+        attr_descs[1].format = ffi::VkFormat_VK_FORMAT_R32G32B32A32_SFLOAT;
+        attr_descs[1].offset = std::mem::offset_of!(Particle, color) as u32;
+
+        // age and lifetime are adjacent, so they are read as one vec2.
+        attr_descs[2].binding = 0;
+        attr_descs[2].location = 2;
+        attr_descs[2].format = ffi::VkFormat_VK_FORMAT_R32G32_SFLOAT;
+        attr_descs[2].offset = std::mem::offset_of!(Particle, age) as u32;
+
+        attr_descs
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EmitterConfig {
+    pub position: [f32; 2],
+    /// Particles spawned per second.
+    pub rate: f32,
+    pub lifetime: f32,
+    pub lifetime_variance: f32,
+    pub velocity: [f32; 2],
+    pub velocity_variance: f32,
+    pub gravity: [f32; 2],
+    pub start_color: [f32; 4],
+    pub end_color: [f32; 4],
+    pub point_size: f32,
+    pub max_particles: u32,
+}
+
+impl Default for EmitterConfig {
+    fn default() -> Self {
+        Self {
+            position: [0.0, 0.6],
+            rate: 2000.0,
+            lifetime: 2.0,
+            lifetime_variance: 0.5,
+            velocity: [0.0, -0.8],
+            velocity_variance: 0.35,
+            gravity: [0.0, 0.9],
+            start_color: [1.0, 0.8, 0.3, 1.0],
+            end_color: [0.8, 0.1, 0.05, 0.0],
+            point_size: 4.0,
+            max_particles: 8192,
+        }
+    }
+}
+
+/// Without `largePoints` only 1.0 is guaranteed to work, otherwise the size is clamped to the
+/// device's `pointSizeRange`.
+pub fn usable_point_size(point_size: f32, large_points: bool, range: [f32; 2]) -> f32 {
+    if large_points {
+        point_size.clamp(range[0], range[1])
+    } else {
+        1.0
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SpawnRange {
+    pub start: u32,
+    pub count: u32,
+}
+
+/// One dispatch of the simulation shader.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct SimulationStep {
+    pub delta_time: f32,
+    pub spawn: SpawnRange,
+}
+
+/// CPU side bookkeeping of which slots of the particle ring buffer get (re)spawned each frame.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct EmitterState {
+    spawn_accumulator: f32,
+    next_spawn_index: u32,
+}
+
+impl EmitterState {
+    pub fn advance(&mut self, config: &EmitterConfig, delta_time: f32) -> SpawnRange {
+        if config.max_particles == 0 {
+            return SpawnRange::default();
+        }
+
+        self.spawn_accumulator += config.rate * delta_time;
+        let count = (self.spawn_accumulator.floor() as u32).min(config.max_particles);
+        self.spawn_accumulator -= count as f32;
+        // A long stall already respawned the whole ring, don't carry the rest to the next frame.
+        if count == config.max_particles {
+            self.spawn_accumulator = 0.0;
+        }
+
+        let range = SpawnRange {
+            start: self.next_spawn_index,
+            count,
+        };
+        self.next_spawn_index = (self.next_spawn_index + count) % config.max_particles;
+
+        range
+    }
+}
+
+/// Matches the push constant block in `shaders/particles.comp`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct ParticleSimPushConstants {
+    pub emitter_pos: [f32; 2],
+    pub base_velocity: [f32; 2],
+    pub start_color: [f32; 4],
+    pub end_color: [f32; 4],
+    pub gravity: [f32; 2],
+    pub delta_time: f32,
+    pub time: f32,
+    pub velocity_variance: f32,
+    pub lifetime: f32,
+    pub lifetime_variance: f32,
+    pub spawn_start: u32,
+    pub spawn_count: u32,
+    pub particle_count: u32,
+}
+
+/// Matches the push constant block in `shaders/particle.vert`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct ParticleDrawPushConstants {
+    pub view_proj: Mat4,
+    pub point_size: f32,
+}
+
+pub struct ParticleSystem {
+    pub config: EmitterConfig,
+    pub state: EmitterState,
+    /// Steps to simulate in the next recorded frame.
+    pub steps: Vec<SimulationStep>,
+    pub buffer: ffi::VkBuffer,
+    pub buffer_memory: ffi::VkDeviceMemory,
+    pub descriptor_set_layout: ffi::VkDescriptorSetLayout,
+    pub descriptor_set: ffi::VkDescriptorSet,
+    pub sim_pipeline_layout: ffi::VkPipelineLayout,
+    pub sim_pipeline: ffi::VkPipeline,
+    pub render_pipeline_layout: ffi::VkPipelineLayout,
+    pub render_pipeline: ffi::VkPipeline,
+}
+
+impl ParticleSystem {
+    pub fn new(config: EmitterConfig) -> Self {
+        Self {
+            config,
+            state: EmitterState::default(),
+            steps: Vec::new(),
+            buffer: std::ptr::null_mut(),
+            buffer_memory: std::ptr::null_mut(),
+            descriptor_set_layout: std::ptr::null_mut(),
+            descriptor_set: std::ptr::null_mut(),
+            sim_pipeline_layout: std::ptr::null_mut(),
+            sim_pipeline: std::ptr::null_mut(),
+            render_pipeline_layout: std::ptr::null_mut(),
+            render_pipeline: std::ptr::null_mut(),
+        }
+    }
+
+    pub fn buffer_size(&self) -> ffi::VkDeviceSize {
+        (std::mem::size_of::<Particle>() * self.config.max_particles as usize) as ffi::VkDeviceSize
+    }
+
+    /// Drops the steps of the last frame, call before this frame's `update`s.
+    pub fn begin_update(&mut self) {
+        self.steps.clear();
+    }
+
+    /// Queues a step of `delta_time`, a frame can run any number of them.
+    pub fn update(&mut self, delta_time: f32) {
+        let spawn = self.state.advance(&self.config, delta_time);
+        self.steps.push(SimulationStep { delta_time, spawn });
+    }
+
+    /// Dispatches every queued step, each one seeing the results of the one before.
+    pub fn record_simulation(
+        &self,
+        command_buffer: ffi::VkCommandBuffer,
+        time: f32,
+        synchronization2: bool,
+    ) {
+        for (idx, step) in self.steps.iter().enumerate() {
+            if idx > 0 {
+                Barriers::new()
+                    .buffer(
+                        self.buffer,
+                        0,
+                        self.buffer_size(),
+                        Usage::ComputeShaderWrite,
+                        Usage::ComputeShaderRead,
+                    )
+                    .buffer(
+                        self.buffer,
+                        0,
+                        self.buffer_size(),
+                        Usage::ComputeShaderWrite,
+                        Usage::ComputeShaderWrite,
+                    )
+                    .record(command_buffer, synchronization2);
+            }
+            self.record_step(command_buffer, step, time);
+        }
+    }
+
+    fn record_step(&self, command_buffer: ffi::VkCommandBuffer, step: &SimulationStep, time: f32) {
+        let push_constants = ParticleSimPushConstants {
+            emitter_pos: self.config.position,
+            base_velocity: self.config.velocity,
+            start_color: self.config.start_color,
+            end_color: self.config.end_color,
+            gravity: self.config.gravity,
+            delta_time: step.delta_time,
+            time,
+            velocity_variance: self.config.velocity_variance,
+            lifetime: self.config.lifetime,
+            lifetime_variance: self.config.lifetime_variance,
+            spawn_start: step.spawn.start,
+            spawn_count: step.spawn.count,
+            particle_count: self.config.max_particles,
+        };
+
+        compute::record_dispatch(
+            command_buffer,
+            self.sim_pipeline,
+            self.sim_pipeline_layout,
+            self.descriptor_set,
+            &push_constants,
+            [
+                compute::group_count(self.config.max_particles, PARTICLE_LOCAL_SIZE),
+                1,
+                1,
+            ],
+        );
+    }
+
+    pub fn record_draw(&self, command_buffer: ffi::VkCommandBuffer, view_proj: &Mat4) {
+        let push_constants = ParticleDrawPushConstants {
+            view_proj: *view_proj,
+            point_size: self.config.point_size,
+        };
+        let offsets: [ffi::VkDeviceSize; 1] = [0];
+        unsafe {
+            ffi::vkCmdBindPipeline(
+                command_buffer,
+                ffi::VkPipelineBindPoint_VK_PIPELINE_BIND_POINT_GRAPHICS,
+                self.render_pipeline,
+            );
+            ffi::vkCmdPushConstants(
+                command_buffer,
+                self.render_pipeline_layout,
+                ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_VERTEX_BIT,
+                0,
+                std::mem::size_of::<ParticleDrawPushConstants>() as u32,
+                std::ptr::addr_of!(push_constants) as *const std::ffi::c_void,
+            );
+            ffi::vkCmdBindVertexBuffers(
+                command_buffer,
+                0,
+                1,
+                std::ptr::addr_of!(self.buffer),
+                offsets.as_ptr(),
+            );
+            ffi::vkCmdDraw(command_buffer, self.config.max_particles, 1, 0, 0);
+        }
+    }
+
+    pub fn destroy(&mut self, device: ffi::VkDevice) {
+        unsafe {
+            if !self.render_pipeline.is_null() {
+                ffi::vkDestroyPipeline(device, self.render_pipeline, std::ptr::null());
+            }
+            if !self.render_pipeline_layout.is_null() {
+                ffi::vkDestroyPipelineLayout(device, self.render_pipeline_layout, std::ptr::null());
+            }
+            if !self.sim_pipeline.is_null() {
+                ffi::vkDestroyPipeline(device, self.sim_pipeline, std::ptr::null());
+            }
+            if !self.sim_pipeline_layout.is_null() {
+                ffi::vkDestroyPipelineLayout(device, self.sim_pipeline_layout, std::ptr::null());
+            }
+            if !self.descriptor_set_layout.is_null() {
+                ffi::vkDestroyDescriptorSetLayout(
+                    device,
+                    self.descriptor_set_layout,
+                    std::ptr::null(),
+                );
+            }
+            if !self.buffer.is_null() {
+                ffi::vkDestroyBuffer(device, self.buffer, std::ptr::null());
+            }
+            if !self.buffer_memory.is_null() {
+                ffi::vkFreeMemory(device, self.buffer_memory, std::ptr::null());
+            }
+        }
+
+        self.render_pipeline = std::ptr::null_mut();
+        self.render_pipeline_layout = std::ptr::null_mut();
+        self.sim_pipeline = std::ptr::null_mut();
+        self.sim_pipeline_layout = std::ptr::null_mut();
+        self.descriptor_set_layout = std::ptr::null_mut();
+        self.descriptor_set = std::ptr::null_mut();
+        self.buffer = std::ptr::null_mut();
+        self.buffer_memory = std::ptr::null_mut();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn particle_layout() {
+        assert_eq!(std::mem::size_of::<Particle>(), 48);
+        assert_eq!(std::mem::offset_of!(Particle, color), 16);
+        assert_eq!(std::mem::offset_of!(Particle, age), 32);
+        assert_eq!(std::mem::size_of::<ParticleSimPushConstants>(), 88);
+        assert_eq!(std::mem::size_of::<ParticleDrawPushConstants>(), 68);
+    }
+
+    #[test]
+    fn point_size_fits_device() {
+        assert_eq!(usable_point_size(4.0, false, [1.0, 64.0]), 1.0);
+        assert_eq!(usable_point_size(4.0, true, [1.0, 64.0]), 4.0);
+        assert_eq!(usable_point_size(4.0, true, [1.0, 2.5]), 2.5);
+    }
+
+    #[test]
+    fn emitter_spawns_at_rate() {
+        let config = EmitterConfig {
+            rate: 64.0,
+            max_particles: 16,
+            ..EmitterConfig::default()
+        };
+        let mut state = EmitterState::default();
+
+        // Half a particle per step, so every other step spawns one.
+        assert_eq!(state.advance(&config, 1.0 / 128.0).count, 0);
+        assert_eq!(
+            state.advance(&config, 1.0 / 128.0),
+            SpawnRange { start: 0, count: 1 }
+        );
+        assert_eq!(
+            state.advance(&config, 0.125),
+            SpawnRange { start: 1, count: 8 }
+        );
+
+        // Wraps around the ring buffer and never spawns more than it holds.
+        assert_eq!(
+            state.advance(&config, 10.0),
+            SpawnRange {
+                start: 9,
+                count: 16
+            }
+        );
+        assert_eq!(state.next_spawn_index, 9);
+        // Only the usual rate follows the stall.
+        assert_eq!(state.advance(&config, 1.0 / 64.0).count, 1);
+    }
+
+    #[test]
+    fn queues_steps() {
+        let mut particles = ParticleSystem::new(EmitterConfig {
+            rate: 100.0,
+            max_particles: 64,
+            ..EmitterConfig::default()
+        });
+
+        particles.begin_update();
+        particles.update(0.01);
+        particles.update(0.01);
+        assert_eq!(
+            particles.steps,
+            [
+                SimulationStep {
+                    delta_time: 0.01,
+                    spawn: SpawnRange { start: 0, count: 1 }
+                },
+                SimulationStep {
+                    delta_time: 0.01,
+                    spawn: SpawnRange { start: 1, count: 1 }
+                },
+            ]
+        );
+
+        // A frame without a fixed step due doesn't simulate at all.
+        particles.begin_update();
+        assert!(particles.steps.is_empty());
+    }
+}
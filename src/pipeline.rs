@@ -0,0 +1,561 @@
+use crate::ffi;
+
+const DEFAULT_DYNAMIC_STATES: [ffi::VkDynamicState; 2] = [
+    ffi::VkDynamicState_VK_DYNAMIC_STATE_VIEWPORT,
+    ffi::VkDynamicState_VK_DYNAMIC_STATE_SCISSOR,
+];
+
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    Opaque,
+    Alpha,
+    PremultipliedAlpha,
+    Additive,
+}
+
+impl BlendMode {
+    pub fn attachment_state(self) -> ffi::VkPipelineColorBlendAttachmentState {
+        let mut attachment: ffi::VkPipelineColorBlendAttachmentState =
+            unsafe { std::mem::zeroed() };
+        attachment.colorWriteMask = ffi::VkColorComponentFlagBits_VK_COLOR_COMPONENT_R_BIT
+            | ffi::VkColorComponentFlagBits_VK_COLOR_COMPONENT_G_BIT
+            | ffi::VkColorComponentFlagBits_VK_COLOR_COMPONENT_B_BIT
+            | ffi::VkColorComponentFlagBits_VK_COLOR_COMPONENT_A_BIT;
+        attachment.colorBlendOp = ffi::VkBlendOp_VK_BLEND_OP_ADD;
+        attachment.alphaBlendOp = ffi::VkBlendOp_VK_BLEND_OP_ADD;
+
+        match self {
+            BlendMode::Opaque => {
+                attachment.blendEnable = ffi::VK_FALSE;
+                attachment.srcColorBlendFactor = ffi::VkBlendFactor_VK_BLEND_FACTOR_ONE;
+                attachment.dstColorBlendFactor = ffi::VkBlendFactor_VK_BLEND_FACTOR_ZERO;
+                attachment.srcAlphaBlendFactor = ffi::VkBlendFactor_VK_BLEND_FACTOR_ONE;
+                attachment.dstAlphaBlendFactor = ffi::VkBlendFactor_VK_BLEND_FACTOR_ZERO;
+            }
+            BlendMode::Alpha => {
+                attachment.blendEnable = ffi::VK_TRUE;
+                attachment.srcColorBlendFactor = ffi::VkBlendFactor_VK_BLEND_FACTOR_SRC_ALPHA;
+                attachment.dstColorBlendFactor =
+                    ffi::VkBlendFactor_VK_BLEND_FACTOR_ONE_MINUS_SRC_ALPHA;
+                attachment.srcAlphaBlendFactor = ffi::VkBlendFactor_VK_BLEND_FACTOR_ONE;
+                attachment.dstAlphaBlendFactor =
+                    ffi::VkBlendFactor_VK_BLEND_FACTOR_ONE_MINUS_SRC_ALPHA;
+            }
+            BlendMode::PremultipliedAlpha => {
+                attachment.blendEnable = ffi::VK_TRUE;
+                attachment.srcColorBlendFactor = ffi::VkBlendFactor_VK_BLEND_FACTOR_ONE;
+                attachment.dstColorBlendFactor =
+                    ffi::VkBlendFactor_VK_BLEND_FACTOR_ONE_MINUS_SRC_ALPHA;
+                attachment.srcAlphaBlendFactor = ffi::VkBlendFactor_VK_BLEND_FACTOR_ONE;
+                attachment.dstAlphaBlendFactor =
+                    ffi::VkBlendFactor_VK_BLEND_FACTOR_ONE_MINUS_SRC_ALPHA;
+            }
+            BlendMode::Additive => {
+                attachment.blendEnable = ffi::VK_TRUE;
+                attachment.srcColorBlendFactor = ffi::VkBlendFactor_VK_BLEND_FACTOR_SRC_ALPHA;
+                attachment.dstColorBlendFactor = ffi::VkBlendFactor_VK_BLEND_FACTOR_ONE;
+                attachment.srcAlphaBlendFactor = ffi::VkBlendFactor_VK_BLEND_FACTOR_ZERO;
+                attachment.dstAlphaBlendFactor = ffi::VkBlendFactor_VK_BLEND_FACTOR_ONE;
+            }
+        }
+
+        attachment
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DepthState {
+    pub test_enable: bool,
+    pub write_enable: bool,
+    pub compare_op: ffi::VkCompareOp,
+}
+
+#[allow(dead_code)]
+impl DepthState {
+    pub const fn read_write() -> Self {
+        Self {
+            test_enable: true,
+            write_enable: true,
+            compare_op: ffi::VkCompareOp_VK_COMPARE_OP_LESS,
+        }
+    }
+
+    pub const fn read_only() -> Self {
+        Self {
+            test_enable: true,
+            write_enable: false,
+            compare_op: ffi::VkCompareOp_VK_COMPARE_OP_LESS_OR_EQUAL,
+        }
+    }
+}
+
+/// Collects the fixed-function state of a graphics pipeline. The same builder can be cloned
+/// and tweaked to build several pipelines against one render pass.
+#[derive(Clone)]
+pub struct GraphicsPipelineBuilder {
+    stages: Vec<(ffi::VkShaderStageFlagBits, ffi::VkShaderModule)>,
+    specialization_constants: Vec<(ffi::VkShaderStageFlagBits, u32, u32)>,
+    vertex_bindings: Vec<ffi::VkVertexInputBindingDescription>,
+    vertex_attributes: Vec<ffi::VkVertexInputAttributeDescription>,
+    topology: ffi::VkPrimitiveTopology,
+    primitive_restart: bool,
+    polygon_mode: ffi::VkPolygonMode,
+    cull_mode: ffi::VkCullModeFlags,
+    front_face: ffi::VkFrontFace,
+    line_width: f32,
+    blend_mode: BlendMode,
+    color_attachment_count: u32,
+    depth_state: Option<DepthState>,
+    dynamic_states: Vec<ffi::VkDynamicState>,
+    samples: ffi::VkSampleCountFlagBits,
+    min_sample_shading: Option<f32>,
+    layout: ffi::VkPipelineLayout,
+    render_pass: ffi::VkRenderPass,
+    subpass: u32,
+    rendering_formats: Option<RenderingFormats>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RenderingFormats {
+    color: Vec<ffi::VkFormat>,
+    depth: ffi::VkFormat,
+}
+
+impl GraphicsPipelineBuilder {
+    pub fn new(layout: ffi::VkPipelineLayout, render_pass: ffi::VkRenderPass) -> Self {
+        Self {
+            stages: Vec::new(),
+            specialization_constants: Vec::new(),
+            vertex_bindings: Vec::new(),
+            vertex_attributes: Vec::new(),
+            topology: ffi::VkPrimitiveTopology_VK_PRIMITIVE_TOPOLOGY_TRIANGLE_LIST,
+            primitive_restart: false,
+            polygon_mode: ffi::VkPolygonMode_VK_POLYGON_MODE_FILL,
+            cull_mode: ffi::VkCullModeFlagBits_VK_CULL_MODE_BACK_BIT,
+            front_face: ffi::VkFrontFace_VK_FRONT_FACE_CLOCKWISE,
+            line_width: 1.0,
+            blend_mode: BlendMode::Opaque,
+            color_attachment_count: 1,
+            depth_state: None,
+            dynamic_states: DEFAULT_DYNAMIC_STATES.to_vec(),
+            samples: ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_1_BIT,
+            min_sample_shading: None,
+            layout,
+            render_pass,
+            subpass: 0,
+            rendering_formats: None,
+        }
+    }
+
+    pub fn shader_stage(
+        mut self,
+        stage: ffi::VkShaderStageFlagBits,
+        module: ffi::VkShaderModule,
+    ) -> Self {
+        self.stages.retain(|(existing, _)| *existing != stage);
+        self.stages.push((stage, module));
+        self
+    }
+
+    /// Sets a 32-bit `constant_id` specialization constant of the given stage.
+    pub fn specialization_constant(
+        mut self,
+        stage: ffi::VkShaderStageFlagBits,
+        constant_id: u32,
+        value: u32,
+    ) -> Self {
+        self.specialization_constants
+            .retain(|(existing, id, _)| *existing != stage || *id != constant_id);
+        self.specialization_constants
+            .push((stage, constant_id, value));
+        self
+    }
+
+    pub fn vertex_input(
+        mut self,
+        bindings: &[ffi::VkVertexInputBindingDescription],
+        attributes: &[ffi::VkVertexInputAttributeDescription],
+    ) -> Self {
+        self.vertex_bindings = bindings.to_vec();
+        self.vertex_attributes = attributes.to_vec();
+        self
+    }
+
+    pub fn topology(mut self, topology: ffi::VkPrimitiveTopology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn primitive_restart(mut self, enable: bool) -> Self {
+        self.primitive_restart = enable;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn polygon_mode(mut self, polygon_mode: ffi::VkPolygonMode) -> Self {
+        self.polygon_mode = polygon_mode;
+        self
+    }
+
+    pub fn cull_mode(mut self, cull_mode: ffi::VkCullModeFlags) -> Self {
+        self.cull_mode = cull_mode;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn front_face(mut self, front_face: ffi::VkFrontFace) -> Self {
+        self.front_face = front_face;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn line_width(mut self, line_width: f32) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    pub fn blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn color_attachment_count(mut self, count: u32) -> Self {
+        self.color_attachment_count = count;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn depth_state(mut self, depth_state: Option<DepthState>) -> Self {
+        self.depth_state = depth_state;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn dynamic_states(mut self, dynamic_states: &[ffi::VkDynamicState]) -> Self {
+        self.dynamic_states = dynamic_states.to_vec();
+        self
+    }
+
+    pub fn samples(mut self, samples: ffi::VkSampleCountFlagBits) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    /// Runs the fragment shader for at least `min` of the samples of each pixel. Needs the
+    /// `sampleRateShading` feature.
+    pub fn sample_shading(mut self, min: Option<f32>) -> Self {
+        self.min_sample_shading = min;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn layout(mut self, layout: ffi::VkPipelineLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn render_pass(mut self, render_pass: ffi::VkRenderPass, subpass: u32) -> Self {
+        self.render_pass = render_pass;
+        self.subpass = subpass;
+        self.rendering_formats = None;
+        self
+    }
+
+    /// Targets dynamic rendering instead of a render pass. Pass `VK_FORMAT_UNDEFINED` as
+    /// `depth_format` when there is no depth attachment.
+    pub fn dynamic_rendering(
+        mut self,
+        color_formats: &[ffi::VkFormat],
+        depth_format: ffi::VkFormat,
+    ) -> Self {
+        self.render_pass = std::ptr::null_mut();
+        self.subpass = 0;
+        self.color_attachment_count = color_formats.len() as u32;
+        self.rendering_formats = Some(RenderingFormats {
+            color: color_formats.to_vec(),
+            depth: depth_format,
+        });
+        self
+    }
+
+    pub fn build(&self, device: ffi::VkDevice) -> Result<ffi::VkPipeline, String> {
+        if self.stages.is_empty() {
+            return Err(String::from(
+                "Cannot build a graphics pipeline without shader stages!",
+            ));
+        }
+
+        let specialization_data: Vec<(Vec<ffi::VkSpecializationMapEntry>, Vec<u32>)> = self
+            .stages
+            .iter()
+            .map(|(stage, _)| {
+                let mut entries = Vec::new();
+                let mut data = Vec::new();
+                for (_, constant_id, value) in self
+                    .specialization_constants
+                    .iter()
+                    .filter(|(constant_stage, _, _)| constant_stage == stage)
+                {
+                    entries.push(ffi::VkSpecializationMapEntry {
+                        constantID: *constant_id,
+                        offset: (data.len() * std::mem::size_of::<u32>()) as u32,
+                        size: std::mem::size_of::<u32>(),
+                    });
+                    data.push(*value);
+                }
+                (entries, data)
+            })
+            .collect();
+
+        let specialization_infos: Vec<ffi::VkSpecializationInfo> = specialization_data
+            .iter()
+            .map(|(entries, data)| ffi::VkSpecializationInfo {
+                mapEntryCount: entries.len() as u32,
+                pMapEntries: entries.as_ptr(),
+                dataSize: std::mem::size_of_val(data.as_slice()),
+                pData: data.as_ptr() as *const std::ffi::c_void,
+            })
+            .collect();
+
+        let shader_stages: Vec<ffi::VkPipelineShaderStageCreateInfo> = self
+            .stages
+            .iter()
+            .zip(specialization_infos.iter())
+            .map(|((stage, module), specialization_info)| {
+                let mut stage_info: ffi::VkPipelineShaderStageCreateInfo =
+                    unsafe { std::mem::zeroed() };
+                stage_info.sType =
+                    ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_SHADER_STAGE_CREATE_INFO;
+                stage_info.stage = *stage;
+                stage_info.module = *module;
+                stage_info.pName = c"main".as_ptr();
+                if specialization_info.mapEntryCount > 0 {
+                    stage_info.pSpecializationInfo = specialization_info;
+                }
+                stage_info
+            })
+            .collect();
+
+        let mut vertex_input_info: ffi::VkPipelineVertexInputStateCreateInfo =
+            unsafe { std::mem::zeroed() };
+        vertex_input_info.sType =
+            ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_VERTEX_INPUT_STATE_CREATE_INFO;
+        vertex_input_info.vertexBindingDescriptionCount = self.vertex_bindings.len() as u32;
+        vertex_input_info.pVertexBindingDescriptions = self.vertex_bindings.as_ptr();
+        vertex_input_info.vertexAttributeDescriptionCount = self.vertex_attributes.len() as u32;
+        vertex_input_info.pVertexAttributeDescriptions = self.vertex_attributes.as_ptr();
+
+        let mut input_assembly: ffi::VkPipelineInputAssemblyStateCreateInfo =
+            unsafe { std::mem::zeroed() };
+        input_assembly.sType =
+            ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_INPUT_ASSEMBLY_STATE_CREATE_INFO;
+        input_assembly.topology = self.topology;
+        input_assembly.primitiveRestartEnable = vk_bool(self.primitive_restart);
+
+        let mut dynamic_state: ffi::VkPipelineDynamicStateCreateInfo =
+            unsafe { std::mem::zeroed() };
+        dynamic_state.sType =
+            ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_DYNAMIC_STATE_CREATE_INFO;
+        dynamic_state.dynamicStateCount = self.dynamic_states.len() as u32;
+        dynamic_state.pDynamicStates = self.dynamic_states.as_ptr();
+
+        let mut viewport_state: ffi::VkPipelineViewportStateCreateInfo =
+            unsafe { std::mem::zeroed() };
+        viewport_state.sType =
+            ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_VIEWPORT_STATE_CREATE_INFO;
+        viewport_state.viewportCount = 1;
+        viewport_state.scissorCount = 1;
+
+        let mut rasterizer_info: ffi::VkPipelineRasterizationStateCreateInfo =
+            unsafe { std::mem::zeroed() };
+        rasterizer_info.sType =
+            ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_RASTERIZATION_STATE_CREATE_INFO;
+        rasterizer_info.depthClampEnable = ffi::VK_FALSE;
+        rasterizer_info.rasterizerDiscardEnable = ffi::VK_FALSE;
+        rasterizer_info.polygonMode = self.polygon_mode;
+        rasterizer_info.lineWidth = self.line_width;
+        rasterizer_info.cullMode = self.cull_mode;
+        rasterizer_info.frontFace = self.front_face;
+        rasterizer_info.depthBiasEnable = ffi::VK_FALSE;
+
+        let mut multisampling_info: ffi::VkPipelineMultisampleStateCreateInfo =
+            unsafe { std::mem::zeroed() };
+        multisampling_info.sType =
+            ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_MULTISAMPLE_STATE_CREATE_INFO;
+        multisampling_info.sampleShadingEnable = vk_bool(self.min_sample_shading.is_some());
+        multisampling_info.rasterizationSamples = self.samples;
+        multisampling_info.minSampleShading = self.min_sample_shading.unwrap_or(1.0);
+        multisampling_info.pSampleMask = std::ptr::null();
+        multisampling_info.alphaToCoverageEnable = ffi::VK_FALSE;
+        multisampling_info.alphaToOneEnable = ffi::VK_FALSE;
+
+        let mut depth_stencil_info: ffi::VkPipelineDepthStencilStateCreateInfo =
+            unsafe { std::mem::zeroed() };
+        if let Some(depth_state) = self.depth_state {
+            depth_stencil_info.sType =
+                ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_DEPTH_STENCIL_STATE_CREATE_INFO;
+            depth_stencil_info.depthTestEnable = vk_bool(depth_state.test_enable);
+            depth_stencil_info.depthWriteEnable = vk_bool(depth_state.write_enable);
+            depth_stencil_info.depthCompareOp = depth_state.compare_op;
+            depth_stencil_info.depthBoundsTestEnable = ffi::VK_FALSE;
+            depth_stencil_info.minDepthBounds = 0.0;
+            depth_stencil_info.maxDepthBounds = 1.0;
+            depth_stencil_info.stencilTestEnable = ffi::VK_FALSE;
+        }
+
+        let color_blend_attachments: Vec<ffi::VkPipelineColorBlendAttachmentState> =
+            vec![self.blend_mode.attachment_state(); self.color_attachment_count as usize];
+
+        let mut color_blending: ffi::VkPipelineColorBlendStateCreateInfo =
+            unsafe { std::mem::zeroed() };
+        color_blending.sType =
+            ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_COLOR_BLEND_STATE_CREATE_INFO;
+        color_blending.logicOpEnable = ffi::VK_FALSE;
+        color_blending.logicOp = ffi::VkLogicOp_VK_LOGIC_OP_COPY;
+        color_blending.attachmentCount = color_blend_attachments.len() as u32;
+        color_blending.pAttachments = color_blend_attachments.as_ptr();
+
+        let mut pipeline_info: ffi::VkGraphicsPipelineCreateInfo = unsafe { std::mem::zeroed() };
+        pipeline_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_GRAPHICS_PIPELINE_CREATE_INFO;
+        pipeline_info.stageCount = shader_stages.len() as u32;
+        pipeline_info.pStages = shader_stages.as_ptr();
+
+        pipeline_info.pVertexInputState = std::ptr::addr_of!(vertex_input_info);
+        pipeline_info.pInputAssemblyState = std::ptr::addr_of!(input_assembly);
+        pipeline_info.pViewportState = std::ptr::addr_of!(viewport_state);
+        pipeline_info.pRasterizationState = std::ptr::addr_of!(rasterizer_info);
+        pipeline_info.pMultisampleState = std::ptr::addr_of!(multisampling_info);
+        pipeline_info.pDepthStencilState = if self.depth_state.is_some() {
+            std::ptr::addr_of!(depth_stencil_info)
+        } else {
+            std::ptr::null()
+        };
+        pipeline_info.pColorBlendState = std::ptr::addr_of!(color_blending);
+        pipeline_info.pDynamicState = if self.dynamic_states.is_empty() {
+            std::ptr::null()
+        } else {
+            std::ptr::addr_of!(dynamic_state)
+        };
+
+        pipeline_info.layout = self.layout;
+
+        pipeline_info.renderPass = self.render_pass;
+        pipeline_info.subpass = self.subpass;
+
+        let mut rendering_info: ffi::VkPipelineRenderingCreateInfo = unsafe { std::mem::zeroed() };
+        if let Some(formats) = &self.rendering_formats {
+            rendering_info.sType =
+                ffi::VkStructureType_VK_STRUCTURE_TYPE_PIPELINE_RENDERING_CREATE_INFO;
+            rendering_info.colorAttachmentCount = formats.color.len() as u32;
+            rendering_info.pColorAttachmentFormats = formats.color.as_ptr();
+            rendering_info.depthAttachmentFormat = formats.depth;
+            rendering_info.stencilAttachmentFormat = ffi::VkFormat_VK_FORMAT_UNDEFINED;
+            pipeline_info.pNext = std::ptr::addr_of!(rendering_info) as *const std::ffi::c_void;
+        }
+
+        pipeline_info.basePipelineHandle = std::ptr::null_mut();
+        pipeline_info.basePipelineIndex = -1;
+
+        let mut pipeline: ffi::VkPipeline = std::ptr::null_mut();
+        let result = unsafe {
+            ffi::vkCreateGraphicsPipelines(
+                device,
+                std::ptr::null_mut(),
+                1,
+                std::ptr::addr_of!(pipeline_info),
+                std::ptr::null(),
+                std::ptr::addr_of_mut!(pipeline),
+            )
+        };
+        if result != ffi::VkResult_VK_SUCCESS {
+            return Err(String::from("Failed to create a graphics pipeline!"));
+        }
+
+        Ok(pipeline)
+    }
+}
+
+fn vk_bool(value: bool) -> ffi::VkBool32 {
+    if value {
+        ffi::VK_TRUE
+    } else {
+        ffi::VK_FALSE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blend_presets() {
+        let opaque = BlendMode::Opaque.attachment_state();
+        assert_eq!(opaque.blendEnable, ffi::VK_FALSE);
+
+        let alpha = BlendMode::Alpha.attachment_state();
+        assert_eq!(alpha.blendEnable, ffi::VK_TRUE);
+        assert_eq!(
+            alpha.srcColorBlendFactor,
+            ffi::VkBlendFactor_VK_BLEND_FACTOR_SRC_ALPHA
+        );
+        assert_eq!(
+            alpha.dstColorBlendFactor,
+            ffi::VkBlendFactor_VK_BLEND_FACTOR_ONE_MINUS_SRC_ALPHA
+        );
+
+        let additive = BlendMode::Additive.attachment_state();
+        assert_eq!(additive.blendEnable, ffi::VK_TRUE);
+        assert_eq!(
+            additive.dstColorBlendFactor,
+            ffi::VkBlendFactor_VK_BLEND_FACTOR_ONE
+        );
+
+        for mode in [
+            BlendMode::Opaque,
+            BlendMode::Alpha,
+            BlendMode::PremultipliedAlpha,
+            BlendMode::Additive,
+        ] {
+            assert_eq!(mode.attachment_state().colorWriteMask, 0xF);
+        }
+    }
+
+    #[test]
+    fn builder_replaces_stages() {
+        let builder = GraphicsPipelineBuilder::new(std::ptr::null_mut(), std::ptr::null_mut())
+            .shader_stage(
+                ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_VERTEX_BIT,
+                std::ptr::null_mut(),
+            )
+            .shader_stage(
+                ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_VERTEX_BIT,
+                std::ptr::null_mut(),
+            )
+            .shader_stage(
+                ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_FRAGMENT_BIT,
+                std::ptr::null_mut(),
+            );
+        assert_eq!(builder.stages.len(), 2);
+    }
+
+    #[test]
+    fn dynamic_rendering_drops_render_pass() {
+        let render_pass = std::ptr::NonNull::<ffi::VkRenderPass_T>::dangling().as_ptr();
+        let builder = GraphicsPipelineBuilder::new(std::ptr::null_mut(), render_pass)
+            .color_attachment_count(3)
+            .dynamic_rendering(
+                &[ffi::VkFormat_VK_FORMAT_B8G8R8A8_SRGB],
+                ffi::VkFormat_VK_FORMAT_UNDEFINED,
+            );
+        assert!(builder.render_pass.is_null());
+        assert_eq!(builder.color_attachment_count, 1);
+
+        let builder = builder.render_pass(render_pass, 0);
+        assert!(builder.rendering_formats.is_none());
+        assert_eq!(builder.render_pass, render_pass);
+    }
+}
@@ -0,0 +1,575 @@
+use crate::compute;
+use crate::ffi;
+use crate::image::AllocatedImage;
+
+/// Format of the offscreen scene target and of the targets between post-process passes.
+pub const HDR_FORMAT: ffi::VkFormat = ffi::VkFormat_VK_FORMAT_R16G16B16A16_SFLOAT;
+
+pub const LUT_FORMAT: ffi::VkFormat = ffi::VkFormat_VK_FORMAT_R8G8B8A8_UNORM;
+
+/// Size of the identity LUT used when no `.cube` file is given.
+pub const DEFAULT_LUT_SIZE: u32 = 16;
+
+/// The strip is `size * size` texels wide, and 128 keeps that within the 16384 wide 2D images
+/// most devices support.
+pub const MAX_LUT_SIZE: u32 = 128;
+
+/// A full-screen pass of the chain. The values are the `EFFECT_*` constants in
+/// `shaders/post.frag`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PostEffect {
+    /// Only copies and encodes, used when the chain is empty.
+    Passthrough = 0,
+    ToneMap = 1,
+    Gamma = 2,
+    Fxaa = 3,
+    Vignette = 4,
+    ColorGrade = 5,
+}
+
+impl PostEffect {
+    pub fn name(self) -> &'static str {
+        match self {
+            PostEffect::Passthrough => "passthrough",
+            PostEffect::ToneMap => "tonemap",
+            PostEffect::Gamma => "gamma",
+            PostEffect::Fxaa => "fxaa",
+            PostEffect::Vignette => "vignette",
+            PostEffect::ColorGrade => "lut",
+        }
+    }
+}
+
+impl std::str::FromStr for PostEffect {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "tonemap" => Ok(PostEffect::ToneMap),
+            "gamma" => Ok(PostEffect::Gamma),
+            "fxaa" => Ok(PostEffect::Fxaa),
+            "vignette" => Ok(PostEffect::Vignette),
+            "lut" => Ok(PostEffect::ColorGrade),
+            _ => Err(format!(
+                "Unknown post effect \"{}\", expected tonemap, gamma, fxaa, vignette or lut",
+                s
+            )),
+        }
+    }
+}
+
+/// The configured effects, in the order they run.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PostChain(pub Vec<PostEffect>);
+
+impl Default for PostChain {
+    fn default() -> Self {
+        PostChain(vec![PostEffect::ToneMap, PostEffect::Fxaa])
+    }
+}
+
+impl std::str::FromStr for PostChain {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() || s.eq_ignore_ascii_case("none") {
+            return Ok(PostChain(Vec::new()));
+        }
+
+        let mut effects: Vec<PostEffect> = Vec::new();
+        for name in s.split(',') {
+            let effect: PostEffect = name.parse()?;
+            if effects.contains(&effect) {
+                return Err(format!("Post effect \"{}\" is listed twice", effect.name()));
+            }
+            effects.push(effect);
+        }
+
+        Ok(PostChain(effects))
+    }
+}
+
+impl PostChain {
+    /// The effect of every pass. The scene still has to be encoded into the swapchain image
+    /// when no effect is configured.
+    pub fn passes(&self) -> Vec<PostEffect> {
+        if self.0.is_empty() {
+            vec![PostEffect::Passthrough]
+        } else {
+            self.0.clone()
+        }
+    }
+}
+
+/// An image a pass samples from or renders to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PostTarget {
+    Scene,
+    Intermediate(usize),
+    Swapchain,
+}
+
+/// Every pass reads what the one before it wrote.
+pub fn pass_input(pass: usize) -> PostTarget {
+    if pass == 0 {
+        PostTarget::Scene
+    } else {
+        PostTarget::Intermediate((pass - 1) % 2)
+    }
+}
+
+/// The last pass writes the swapchain image. The ones before it alternate between two
+/// intermediates so no pass samples the image it renders to.
+pub fn pass_output(pass: usize, pass_count: usize) -> PostTarget {
+    if pass + 1 == pass_count {
+        PostTarget::Swapchain
+    } else {
+        PostTarget::Intermediate(pass % 2)
+    }
+}
+
+pub fn intermediate_count(pass_count: usize) -> usize {
+    pass_count.saturating_sub(1).min(2)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PostSettings {
+    /// Scene brightness multiplier applied before tone mapping.
+    pub exposure: f32,
+    pub gamma: f32,
+    /// 0 disables the vignette, 1 darkens the corners to black.
+    pub vignette: f32,
+}
+
+impl Default for PostSettings {
+    fn default() -> Self {
+        Self {
+            exposure: 1.0,
+            gamma: 1.0,
+            vignette: 0.35,
+        }
+    }
+}
+
+/// Matches the push constant block in `shaders/post.frag`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct PostPushConstants {
+    pub texel_size: [f32; 2],
+    pub exposure: f32,
+    pub gamma: f32,
+    pub vignette: f32,
+    pub lut_size: f32,
+}
+
+/// A 3D color lookup table with red changing fastest, then green, then blue, like in `.cube`
+/// files.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorLut {
+    pub size: u32,
+    pub entries: Vec<[f32; 3]>,
+}
+
+impl ColorLut {
+    pub fn identity(size: u32) -> Self {
+        let max = (size - 1) as f32;
+        let mut entries = Vec::with_capacity((size * size * size) as usize);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    entries.push([r as f32 / max, g as f32 / max, b as f32 / max]);
+                }
+            }
+        }
+
+        Self { size, entries }
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read LUT \"{}\": {}", path, e))?;
+        Self::parse_cube(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Parses an Adobe/Resolve `.cube` 3D LUT with the default 0-1 domain.
+    pub fn parse_cube(text: &str) -> Result<Self, String> {
+        let mut size: Option<u32> = None;
+        let mut entries: Vec<[f32; 3]> = Vec::new();
+
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap_or_default();
+            match keyword {
+                "TITLE" => {}
+                "LUT_3D_SIZE" => {
+                    size = match words.next().map(str::parse::<u32>) {
+                        Some(Ok(size)) if (2..=MAX_LUT_SIZE).contains(&size) => Some(size),
+                        _ => return Err(format!("Invalid \"{}\"", line)),
+                    }
+                }
+                "LUT_1D_SIZE" => return Err(String::from("1D LUTs aren't supported")),
+                "DOMAIN_MIN" | "DOMAIN_MAX" => {
+                    let expected = if keyword == "DOMAIN_MIN" { 0.0 } else { 1.0 };
+                    if !words.all(|word| word.parse::<f32>() == Ok(expected)) {
+                        return Err(format!("Unsupported \"{}\"", line));
+                    }
+                }
+                _ => {
+                    let values: Vec<f32> = line
+                        .split_whitespace()
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| format!("Invalid line \"{}\"", line))?;
+                    match values[..] {
+                        [r, g, b] => entries.push([r, g, b]),
+                        _ => return Err(format!("Invalid line \"{}\"", line)),
+                    }
+                }
+            }
+        }
+
+        let size = size.ok_or_else(|| String::from("Missing LUT_3D_SIZE"))?;
+        if entries.len() != (size * size * size) as usize {
+            return Err(format!(
+                "Expected {} entries for LUT_3D_SIZE {}, found {}",
+                size * size * size,
+                size,
+                entries.len()
+            ));
+        }
+
+        Ok(Self { size, entries })
+    }
+
+    /// The blue slices side by side in a `size * size` wide and `size` high image, so the LUT
+    /// fits a regular 2D texture.
+    pub fn strip_extent(&self) -> ffi::VkExtent2D {
+        ffi::VkExtent2D {
+            width: self.size * self.size,
+            height: self.size,
+        }
+    }
+
+    /// RGBA8 pixels of the image described by `strip_extent`.
+    pub fn strip_pixels(&self) -> Vec<u8> {
+        let size = self.size as usize;
+        let width = size * size;
+        let mut pixels = vec![0u8; width * size * 4];
+
+        for (idx, entry) in self.entries.iter().enumerate() {
+            let r = idx % size;
+            let g = (idx / size) % size;
+            let b = idx / (size * size);
+            let offset = (g * width + b * size + r) * 4;
+            for (channel, value) in entry.iter().enumerate() {
+                pixels[offset + channel] = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+            pixels[offset + 3] = 255;
+        }
+
+        pixels
+    }
+}
+
+pub struct PostProcessChain {
+    pub passes: Vec<PostEffect>,
+    pub settings: PostSettings,
+    pub lut: Option<AllocatedImage>,
+    pub lut_size: u32,
+    pub sampler: ffi::VkSampler,
+    pub descriptor_set_layout: ffi::VkDescriptorSetLayout,
+    /// One per pass, rewritten every frame since the targets change with the swapchain.
+    pub descriptor_sets: Vec<ffi::VkDescriptorSet>,
+    pub pipeline_layout: ffi::VkPipelineLayout,
+    /// One per pass, the last one renders to the swapchain format with its output encoding.
+    pub pipelines: Vec<ffi::VkPipeline>,
+    /// Only used without dynamic rendering.
+    pub hdr_render_pass: ffi::VkRenderPass,
+    pub output_render_pass: ffi::VkRenderPass,
+}
+
+impl PostProcessChain {
+    pub fn new(chain: &PostChain, settings: PostSettings) -> Self {
+        Self {
+            passes: chain.passes(),
+            settings,
+            lut: None,
+            lut_size: 0,
+            sampler: std::ptr::null_mut(),
+            descriptor_set_layout: std::ptr::null_mut(),
+            descriptor_sets: Vec::new(),
+            pipeline_layout: std::ptr::null_mut(),
+            pipelines: Vec::new(),
+            hdr_render_pass: std::ptr::null_mut(),
+            output_render_pass: std::ptr::null_mut(),
+        }
+    }
+
+    pub fn pass_count(&self) -> usize {
+        self.passes.len()
+    }
+
+    pub fn has_lut_pass(&self) -> bool {
+        self.passes.contains(&PostEffect::ColorGrade)
+    }
+
+    pub fn is_last(&self, pass: usize) -> bool {
+        pass + 1 == self.pass_count()
+    }
+
+    /// Only call while the GPU isn't using the set, i.e. before recording a frame.
+    pub fn write_input(&self, device: ffi::VkDevice, pass: usize, input_view: ffi::VkImageView) {
+        let set = self.descriptor_sets[pass];
+        compute::write_combined_image_sampler(device, set, 0, input_view, self.sampler);
+        // Every pass declares the LUT binding, so without a LUT the input stands in for it.
+        let lut_view = self.lut.as_ref().map_or(input_view, |lut| lut.view);
+        compute::write_combined_image_sampler(device, set, 1, lut_view, self.sampler);
+    }
+
+    /// Records the draw of `pass` inside an already begun render pass or rendering scope.
+    pub fn record_pass(
+        &self,
+        command_buffer: ffi::VkCommandBuffer,
+        pass: usize,
+        extent: ffi::VkExtent2D,
+    ) {
+        let push_constants = PostPushConstants {
+            texel_size: [1.0 / extent.width as f32, 1.0 / extent.height as f32],
+            exposure: self.settings.exposure,
+            gamma: self.settings.gamma,
+            vignette: self.settings.vignette,
+            lut_size: self.lut_size as f32,
+        };
+
+        let mut viewport: ffi::VkViewport = unsafe { std::mem::zeroed() };
+        viewport.width = extent.width as f32;
+        viewport.height = extent.height as f32;
+        viewport.maxDepth = 1.0;
+
+        let mut scissor: ffi::VkRect2D = unsafe { std::mem::zeroed() };
+        scissor.extent = extent;
+
+        unsafe {
+            ffi::vkCmdBindPipeline(
+                command_buffer,
+                ffi::VkPipelineBindPoint_VK_PIPELINE_BIND_POINT_GRAPHICS,
+                self.pipelines[pass],
+            );
+            ffi::vkCmdBindDescriptorSets(
+                command_buffer,
+                ffi::VkPipelineBindPoint_VK_PIPELINE_BIND_POINT_GRAPHICS,
+                self.pipeline_layout,
+                0,
+                1,
+                std::ptr::addr_of!(self.descriptor_sets[pass]),
+                0,
+                std::ptr::null(),
+            );
+            ffi::vkCmdPushConstants(
+                command_buffer,
+                self.pipeline_layout,
+                ffi::VkShaderStageFlagBits_VK_SHADER_STAGE_FRAGMENT_BIT,
+                0,
+                std::mem::size_of::<PostPushConstants>() as u32,
+                std::ptr::addr_of!(push_constants) as *const std::ffi::c_void,
+            );
+            ffi::vkCmdSetViewport(command_buffer, 0, 1, std::ptr::addr_of!(viewport));
+            ffi::vkCmdSetScissor(command_buffer, 0, 1, std::ptr::addr_of!(scissor));
+            ffi::vkCmdDraw(command_buffer, 3, 1, 0, 0);
+        }
+    }
+
+    pub fn destroy(&mut self, device: ffi::VkDevice) {
+        unsafe {
+            for pipeline in self.pipelines.drain(..) {
+                ffi::vkDestroyPipeline(device, pipeline, std::ptr::null());
+            }
+            if !self.pipeline_layout.is_null() {
+                ffi::vkDestroyPipelineLayout(device, self.pipeline_layout, std::ptr::null());
+            }
+            if !self.descriptor_set_layout.is_null() {
+                ffi::vkDestroyDescriptorSetLayout(
+                    device,
+                    self.descriptor_set_layout,
+                    std::ptr::null(),
+                );
+            }
+            if !self.sampler.is_null() {
+                ffi::vkDestroySampler(device, self.sampler, std::ptr::null());
+            }
+            if !self.hdr_render_pass.is_null() {
+                ffi::vkDestroyRenderPass(device, self.hdr_render_pass, std::ptr::null());
+            }
+            if !self.output_render_pass.is_null() {
+                ffi::vkDestroyRenderPass(device, self.output_render_pass, std::ptr::null());
+            }
+        }
+        if let Some(lut) = self.lut.take() {
+            lut.destroy(device);
+        }
+
+        self.pipeline_layout = std::ptr::null_mut();
+        self.descriptor_set_layout = std::ptr::null_mut();
+        self.descriptor_sets.clear();
+        self.sampler = std::ptr::null_mut();
+        self.hdr_render_pass = std::ptr::null_mut();
+        self.output_render_pass = std::ptr::null_mut();
+    }
+}
+
+/// Bilinear, clamped to the edge so the full-screen passes don't wrap around.
+pub fn create_sampler(device: ffi::VkDevice) -> Result<ffi::VkSampler, String> {
+    let mut sampler_info: ffi::VkSamplerCreateInfo = unsafe { std::mem::zeroed() };
+    sampler_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_SAMPLER_CREATE_INFO;
+    sampler_info.magFilter = ffi::VkFilter_VK_FILTER_LINEAR;
+    sampler_info.minFilter = ffi::VkFilter_VK_FILTER_LINEAR;
+    sampler_info.mipmapMode = ffi::VkSamplerMipmapMode_VK_SAMPLER_MIPMAP_MODE_NEAREST;
+    sampler_info.addressModeU = ffi::VkSamplerAddressMode_VK_SAMPLER_ADDRESS_MODE_CLAMP_TO_EDGE;
+    sampler_info.addressModeV = ffi::VkSamplerAddressMode_VK_SAMPLER_ADDRESS_MODE_CLAMP_TO_EDGE;
+    sampler_info.addressModeW = ffi::VkSamplerAddressMode_VK_SAMPLER_ADDRESS_MODE_CLAMP_TO_EDGE;
+    sampler_info.maxLod = 0.0;
+
+    let mut sampler: ffi::VkSampler = std::ptr::null_mut();
+    let result = unsafe {
+        ffi::vkCreateSampler(
+            device,
+            std::ptr::addr_of!(sampler_info),
+            std::ptr::null(),
+            std::ptr::addr_of_mut!(sampler),
+        )
+    };
+    if result != ffi::VkResult_VK_SUCCESS {
+        return Err(String::from("Failed to create sampler!"));
+    }
+
+    Ok(sampler)
+}
+
+/// A single color attachment render pass for a full-screen pass. The previous contents are
+/// never loaded and the frame graph handles the layout transitions.
+pub fn create_render_pass(
+    device: ffi::VkDevice,
+    format: ffi::VkFormat,
+) -> Result<ffi::VkRenderPass, String> {
+    let mut color_attachment: ffi::VkAttachmentDescription = unsafe { std::mem::zeroed() };
+    color_attachment.format = format;
+    color_attachment.samples = ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_1_BIT;
+    color_attachment.loadOp = ffi::VkAttachmentLoadOp_VK_ATTACHMENT_LOAD_OP_DONT_CARE;
+    color_attachment.storeOp = ffi::VkAttachmentStoreOp_VK_ATTACHMENT_STORE_OP_STORE;
+    color_attachment.stencilLoadOp = ffi::VkAttachmentLoadOp_VK_ATTACHMENT_LOAD_OP_DONT_CARE;
+    color_attachment.stencilStoreOp = ffi::VkAttachmentStoreOp_VK_ATTACHMENT_STORE_OP_DONT_CARE;
+    color_attachment.initialLayout = ffi::VkImageLayout_VK_IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL;
+    color_attachment.finalLayout = ffi::VkImageLayout_VK_IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL;
+
+    let color_attachment_ref = ffi::VkAttachmentReference {
+        attachment: 0,
+        layout: ffi::VkImageLayout_VK_IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL,
+    };
+
+    let mut subpass: ffi::VkSubpassDescription = unsafe { std::mem::zeroed() };
+    subpass.pipelineBindPoint = ffi::VkPipelineBindPoint_VK_PIPELINE_BIND_POINT_GRAPHICS;
+    subpass.colorAttachmentCount = 1;
+    subpass.pColorAttachments = std::ptr::addr_of!(color_attachment_ref);
+
+    let mut render_pass_info: ffi::VkRenderPassCreateInfo = unsafe { std::mem::zeroed() };
+    render_pass_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_RENDER_PASS_CREATE_INFO;
+    render_pass_info.attachmentCount = 1;
+    render_pass_info.pAttachments = std::ptr::addr_of!(color_attachment);
+    render_pass_info.subpassCount = 1;
+    render_pass_info.pSubpasses = std::ptr::addr_of!(subpass);
+
+    let mut render_pass: ffi::VkRenderPass = std::ptr::null_mut();
+    let result = unsafe {
+        ffi::vkCreateRenderPass(
+            device,
+            std::ptr::addr_of!(render_pass_info),
+            std::ptr::null(),
+            std::ptr::addr_of_mut!(render_pass),
+        )
+    };
+    if result != ffi::VkResult_VK_SUCCESS {
+        return Err(String::from("Failed to create post-process render pass!"));
+    }
+
+    Ok(render_pass)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_chain() {
+        assert_eq!(
+            "tonemap, fxaa,lut".parse(),
+            Ok(PostChain(vec![
+                PostEffect::ToneMap,
+                PostEffect::Fxaa,
+                PostEffect::ColorGrade
+            ]))
+        );
+        assert_eq!("none".parse(), Ok(PostChain(Vec::new())));
+        assert!("tonemap,bloom".parse::<PostChain>().is_err());
+        assert!("fxaa,fxaa".parse::<PostChain>().is_err());
+
+        let settings = PostSettings::default();
+        assert!(!PostProcessChain::new(&PostChain::default(), settings).has_lut_pass());
+        let graded = PostChain(vec![PostEffect::ToneMap, PostEffect::ColorGrade]);
+        assert!(PostProcessChain::new(&graded, settings).has_lut_pass());
+
+        assert_eq!(
+            PostChain(Vec::new()).passes(),
+            vec![PostEffect::Passthrough]
+        );
+        assert_eq!(std::mem::size_of::<PostPushConstants>(), 24);
+    }
+
+    #[test]
+    fn passes_ping_pong_between_intermediates() {
+        assert_eq!(intermediate_count(1), 0);
+        assert_eq!(pass_input(0), PostTarget::Scene);
+        assert_eq!(pass_output(0, 1), PostTarget::Swapchain);
+
+        let routes: Vec<_> = (0..4)
+            .map(|pass| (pass_input(pass), pass_output(pass, 4)))
+            .collect();
+        assert_eq!(
+            routes,
+            vec![
+                (PostTarget::Scene, PostTarget::Intermediate(0)),
+                (PostTarget::Intermediate(0), PostTarget::Intermediate(1)),
+                (PostTarget::Intermediate(1), PostTarget::Intermediate(0)),
+                (PostTarget::Intermediate(0), PostTarget::Swapchain),
+            ]
+        );
+        assert_eq!(intermediate_count(4), 2);
+    }
+
+    #[test]
+    fn cube_luts() {
+        let cube = "TITLE \"test\"\n# comment\nLUT_3D_SIZE 2\nDOMAIN_MIN 0 0 0\n\
+                    0 0 0\n1 0 0\n0 1 0\n1 1 0\n0 0 1\n1 0 1\n0 1 1\n1 1 1\n";
+        assert_eq!(ColorLut::parse_cube(cube), Ok(ColorLut::identity(2)));
+        assert!(ColorLut::parse_cube("LUT_3D_SIZE 2\n0 0 0\n").is_err());
+        assert!(ColorLut::parse_cube("DOMAIN_MAX 2 2 2\nLUT_3D_SIZE 2\n").is_err());
+        assert!(ColorLut::parse_cube("LUT_1D_SIZE 4\n").is_err());
+        assert!(ColorLut::parse_cube("LUT_3D_SIZE 129\n").is_err());
+
+        // Red along x within a slice, green along y, blue picks the slice.
+        let lut = ColorLut::identity(2);
+        assert_eq!(lut.strip_extent().width, 4);
+        assert_eq!(lut.strip_extent().height, 2);
+        let pixels = lut.strip_pixels();
+        let pixel = |x: usize, y: usize| &pixels[(y * 4 + x) * 4..(y * 4 + x) * 4 + 4];
+        assert_eq!(pixel(1, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(0, 1), [0, 255, 0, 255]);
+        assert_eq!(pixel(2, 0), [0, 0, 255, 255]);
+        assert_eq!(pixel(3, 1), [255, 255, 255, 255]);
+    }
+}
@@ -0,0 +1,366 @@
+use crate::ffi;
+use crate::queries::create_query_pool;
+
+/// Most passes timed per frame, later ones are recorded without timestamps.
+pub const MAX_GPU_SCOPES: u32 = 32;
+/// Frame start and end, plus a begin and end timestamp per scope.
+pub const TIMESTAMP_QUERY_COUNT: u32 = 2 + 2 * MAX_GPU_SCOPES;
+/// Frames kept for the Chrome trace, about 10 seconds at 60 fps.
+pub const MAX_TRACE_FRAMES: usize = 600;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassTiming {
+    pub name: String,
+    /// Relative to the start of the frame.
+    pub start_ms: f64,
+    pub duration_ms: f64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrameTimings {
+    /// GPU ticks at the start of the frame, masked to the valid bits.
+    pub start_ticks: u64,
+    pub total_ms: f64,
+    pub passes: Vec<PassTiming>,
+}
+
+impl std::fmt::Display for FrameTimings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "GPU frame: {:.3} ms", self.total_ms)?;
+        for pass in &self.passes {
+            write!(f, "\n  {:<24} {:>8.3} ms", pass.name, pass.duration_ms)?;
+        }
+        Ok(())
+    }
+}
+
+/// Ticks `to - from`, allowing for the counter wrapping at `valid_bits`.
+fn tick_delta(from: u64, to: u64, valid_bits: u32) -> u64 {
+    let mask = if valid_bits >= 64 {
+        u64::MAX
+    } else {
+        (1u64 << valid_bits) - 1
+    };
+    to.wrapping_sub(from) & mask
+}
+
+fn ticks_to_ms(ticks: u64, period_ns: f32) -> f64 {
+    ticks as f64 * period_ns as f64 / 1_000_000.0
+}
+
+/// Turns the raw timestamps of a frame, laid out as written by `GpuProfiler`, into timings.
+pub fn frame_timings(
+    ticks: &[u64],
+    names: &[String],
+    period_ns: f32,
+    valid_bits: u32,
+) -> FrameTimings {
+    let frame_start = ticks[0];
+    let frame_end = ticks[1 + 2 * names.len()];
+    let since_start = |tick: u64| ticks_to_ms(tick_delta(frame_start, tick, valid_bits), period_ns);
+
+    FrameTimings {
+        start_ticks: frame_start,
+        total_ms: since_start(frame_end),
+        passes: names
+            .iter()
+            .enumerate()
+            .map(|(idx, name)| {
+                let begin = ticks[1 + 2 * idx];
+                let end = ticks[2 + 2 * idx];
+                PassTiming {
+                    name: name.clone(),
+                    start_ms: since_start(begin),
+                    duration_ms: ticks_to_ms(tick_delta(begin, end, valid_bits), period_ns),
+                }
+            })
+            .collect(),
+    }
+}
+
+fn escape_json(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Chrome trace event format, loadable in chrome://tracing or Perfetto. Each frame is an event
+/// with its passes nested inside.
+pub fn chrome_trace_json(frames: &[FrameTimings], period_ns: f32, valid_bits: u32) -> String {
+    let origin = frames.first().map(|frame| frame.start_ticks).unwrap_or(0);
+    let mut events = Vec::new();
+    let mut push_event = |name: &str, ts_us: f64, dur_us: f64| {
+        events.push(format!(
+            "{{\"name\":\"{}\",\"cat\":\"gpu\",\"ph\":\"X\",\"pid\":0,\"tid\":0,\"ts\":{:.3},\"dur\":{:.3}}}",
+            escape_json(name),
+            ts_us,
+            dur_us
+        ));
+    };
+
+    for frame in frames {
+        let frame_ts =
+            ticks_to_ms(tick_delta(origin, frame.start_ticks, valid_bits), period_ns) * 1000.0;
+        push_event("frame", frame_ts, frame.total_ms * 1000.0);
+        for pass in &frame.passes {
+            push_event(
+                &pass.name,
+                frame_ts + pass.start_ms * 1000.0,
+                pass.duration_ms * 1000.0,
+            );
+        }
+    }
+
+    format!(
+        "{{\"traceEvents\":[\n{}\n],\"displayTimeUnit\":\"ms\"}}\n",
+        events.join(",\n")
+    )
+}
+
+/// Timestamp queries around every pass of a frame. Results are read back once the frame's
+/// timeline value is reached, so a single pool is enough with one frame in flight.
+pub struct GpuProfiler {
+    pub query_pool: ffi::VkQueryPool,
+    /// Nanoseconds per tick, `VkPhysicalDeviceLimits::timestampPeriod`.
+    pub timestamp_period: f32,
+    pub valid_bits: u32,
+    /// Names of the scopes recorded in the frame that hasn't been read back yet.
+    pending: Option<Vec<String>>,
+    pub last_frame: Option<FrameTimings>,
+    /// Only collected when a trace file was requested.
+    pub trace: Option<Vec<FrameTimings>>,
+}
+
+impl GpuProfiler {
+    pub fn new(collect_trace: bool) -> Self {
+        Self {
+            query_pool: std::ptr::null_mut(),
+            timestamp_period: 0.0,
+            valid_bits: 0,
+            pending: None,
+            last_frame: None,
+            trace: collect_trace.then(Vec::new),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.query_pool.is_null()
+    }
+
+    /// Leaves the profiler disabled if the queue doesn't support timestamps.
+    pub fn create(
+        &mut self,
+        device: ffi::VkDevice,
+        limits: &ffi::VkPhysicalDeviceLimits,
+        queue_valid_bits: u32,
+    ) -> Result<(), String> {
+        if queue_valid_bits == 0 || limits.timestampPeriod <= 0.0 {
+            println!("GPU timestamps aren't supported on the graphics queue, profiling disabled");
+            return Ok(());
+        }
+
+        self.query_pool = create_query_pool(
+            device,
+            ffi::VkQueryType_VK_QUERY_TYPE_TIMESTAMP,
+            TIMESTAMP_QUERY_COUNT,
+            0,
+        )?;
+
+        self.timestamp_period = limits.timestampPeriod;
+        self.valid_bits = queue_valid_bits;
+
+        Ok(())
+    }
+
+    /// Resets the pool and writes the frame start, must be recorded outside of a render pass.
+    pub fn begin_frame(&self, command_buffer: ffi::VkCommandBuffer) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        unsafe {
+            ffi::vkCmdResetQueryPool(command_buffer, self.query_pool, 0, TIMESTAMP_QUERY_COUNT);
+            ffi::vkCmdWriteTimestamp(
+                command_buffer,
+                ffi::VkPipelineStageFlagBits_VK_PIPELINE_STAGE_TOP_OF_PIPE_BIT,
+                self.query_pool,
+                0,
+            );
+        }
+    }
+
+    pub fn begin_scope(&self, command_buffer: ffi::VkCommandBuffer, scope: u32) {
+        if self.is_enabled() && scope < MAX_GPU_SCOPES {
+            unsafe {
+                ffi::vkCmdWriteTimestamp(
+                    command_buffer,
+                    ffi::VkPipelineStageFlagBits_VK_PIPELINE_STAGE_TOP_OF_PIPE_BIT,
+                    self.query_pool,
+                    1 + 2 * scope,
+                );
+            }
+        }
+    }
+
+    pub fn end_scope(&self, command_buffer: ffi::VkCommandBuffer, scope: u32) {
+        if self.is_enabled() && scope < MAX_GPU_SCOPES {
+            unsafe {
+                ffi::vkCmdWriteTimestamp(
+                    command_buffer,
+                    ffi::VkPipelineStageFlagBits_VK_PIPELINE_STAGE_BOTTOM_OF_PIPE_BIT,
+                    self.query_pool,
+                    2 + 2 * scope,
+                );
+            }
+        }
+    }
+
+    /// Writes the frame end after the scopes named by `scope_names`, in the order they were
+    /// begun.
+    pub fn end_frame(
+        &mut self,
+        command_buffer: ffi::VkCommandBuffer,
+        mut scope_names: Vec<String>,
+    ) {
+        if !self.is_enabled() {
+            return;
+        }
+
+        scope_names.truncate(MAX_GPU_SCOPES as usize);
+        unsafe {
+            ffi::vkCmdWriteTimestamp(
+                command_buffer,
+                ffi::VkPipelineStageFlagBits_VK_PIPELINE_STAGE_BOTTOM_OF_PIPE_BIT,
+                self.query_pool,
+                1 + 2 * scope_names.len() as u32,
+            );
+        }
+        self.pending = Some(scope_names);
+    }
+
+    /// Reads back the last recorded frame. Call once its submission has completed.
+    pub fn collect(&mut self, device: ffi::VkDevice) -> Result<(), String> {
+        let Some(names) = self.pending.take() else {
+            return Ok(());
+        };
+
+        let query_count = 2 + 2 * names.len() as u32;
+        let mut ticks = vec![0u64; query_count as usize];
+        let result = unsafe {
+            ffi::vkGetQueryPoolResults(
+                device,
+                self.query_pool,
+                0,
+                query_count,
+                std::mem::size_of_val(ticks.as_slice()),
+                ticks.as_mut_ptr() as *mut std::ffi::c_void,
+                std::mem::size_of::<u64>() as ffi::VkDeviceSize,
+                ffi::VkQueryResultFlagBits_VK_QUERY_RESULT_64_BIT,
+            )
+        };
+        if result == ffi::VkResult_VK_NOT_READY {
+            // The frame was recorded but never submitted.
+            return Ok(());
+        }
+        if result != ffi::VkResult_VK_SUCCESS {
+            return Err(String::from("Failed to read timestamp queries!"));
+        }
+
+        let timings = frame_timings(&ticks, &names, self.timestamp_period, self.valid_bits);
+        if let Some(trace) = &mut self.trace {
+            if trace.len() == MAX_TRACE_FRAMES {
+                trace.remove(0);
+            }
+            trace.push(timings.clone());
+        }
+        self.last_frame = Some(timings);
+
+        Ok(())
+    }
+
+    pub fn write_trace(&self, path: &str) -> Result<(), String> {
+        let frames = self.trace.as_deref().unwrap_or(&[]);
+        std::fs::write(
+            path,
+            chrome_trace_json(frames, self.timestamp_period, self.valid_bits),
+        )
+        .map_err(|e| format!("Failed to write GPU trace \"{}\": {}", path, e))
+    }
+
+    pub fn destroy(&mut self, device: ffi::VkDevice) {
+        if !self.query_pool.is_null() {
+            unsafe {
+                ffi::vkDestroyQueryPool(device, self.query_pool, std::ptr::null());
+            }
+        }
+
+        self.query_pool = std::ptr::null_mut();
+        self.pending = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn timings_from_ticks() {
+        // 2 ns per tick: frame start, scene 100..600, post 600..700, frame end.
+        let ticks = [1000, 1100, 1600, 1600, 1700, 1800];
+        let timings = frame_timings(&ticks, &names(&["scene", "post"]), 2.0, 64);
+
+        assert_eq!(timings.start_ticks, 1000);
+        assert!((timings.total_ms - 0.0016).abs() < 1e-9);
+        assert_eq!(timings.passes.len(), 2);
+        assert_eq!(timings.passes[0].name, "scene");
+        assert!((timings.passes[0].start_ms - 0.0002).abs() < 1e-9);
+        assert!((timings.passes[0].duration_ms - 0.001).abs() < 1e-9);
+        assert!((timings.passes[1].duration_ms - 0.0002).abs() < 1e-9);
+
+        // A 36 bit counter wrapping during the frame.
+        let top = (1u64 << 36) - 10;
+        let ticks = [top, 20];
+        let timings = frame_timings(&ticks, &[], 1_000_000.0, 36);
+        assert!((timings.total_ms - 30.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn chrome_trace() {
+        let frame = |start_ticks: u64| FrameTimings {
+            start_ticks,
+            total_ms: 2.0,
+            passes: vec![PassTiming {
+                name: String::from("post \"fxaa\""),
+                start_ms: 0.5,
+                duration_ms: 1.0,
+            }],
+        };
+
+        let json = chrome_trace_json(&[frame(1000), frame(17000)], 1000.0, 64);
+        assert!(json.starts_with("{\"traceEvents\":["));
+        assert!(json.contains(
+            "{\"name\":\"frame\",\"cat\":\"gpu\",\"ph\":\"X\",\"pid\":0,\"tid\":0,\"ts\":0.000,\"dur\":2000.000}"
+        ));
+        // The second frame starts 16000 ticks of 1 us later.
+        assert!(json.contains("\"ts\":16000.000,\"dur\":2000.000"));
+        assert!(json.contains("\"name\":\"post \\\"fxaa\\\"\""));
+        assert!(json.contains("\"ts\":16500.000,\"dur\":1000.000"));
+        assert_eq!(json.matches("\"ph\":\"X\"").count(), 4);
+
+        assert_eq!(
+            chrome_trace_json(&[], 1.0, 64),
+            "{\"traceEvents\":[\n\n],\"displayTimeUnit\":\"ms\"}\n"
+        );
+    }
+}
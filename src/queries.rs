@@ -0,0 +1,358 @@
+use crate::ffi;
+use crate::profiler::MAX_GPU_SCOPES;
+
+/// Most draws given an occlusion query per frame.
+pub const MAX_OCCLUSION_DRAWS: u32 = 256;
+
+/// Counters collected per pass. Results come back in the order of the flag bits.
+pub const PIPELINE_STATISTICS: [ffi::VkQueryPipelineStatisticFlagBits; 7] = [
+    ffi::VkQueryPipelineStatisticFlagBits_VK_QUERY_PIPELINE_STATISTIC_INPUT_ASSEMBLY_VERTICES_BIT,
+    ffi::VkQueryPipelineStatisticFlagBits_VK_QUERY_PIPELINE_STATISTIC_INPUT_ASSEMBLY_PRIMITIVES_BIT,
+    ffi::VkQueryPipelineStatisticFlagBits_VK_QUERY_PIPELINE_STATISTIC_VERTEX_SHADER_INVOCATIONS_BIT,
+    ffi::VkQueryPipelineStatisticFlagBits_VK_QUERY_PIPELINE_STATISTIC_CLIPPING_INVOCATIONS_BIT,
+    ffi::VkQueryPipelineStatisticFlagBits_VK_QUERY_PIPELINE_STATISTIC_CLIPPING_PRIMITIVES_BIT,
+    ffi::VkQueryPipelineStatisticFlagBits_VK_QUERY_PIPELINE_STATISTIC_FRAGMENT_SHADER_INVOCATIONS_BIT,
+    ffi::VkQueryPipelineStatisticFlagBits_VK_QUERY_PIPELINE_STATISTIC_COMPUTE_SHADER_INVOCATIONS_BIT,
+];
+
+pub fn pipeline_statistics_flags() -> ffi::VkQueryPipelineStatisticFlags {
+    PIPELINE_STATISTICS.iter().fold(0, |flags, bit| flags | bit)
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PipelineCounters {
+    pub input_vertices: u64,
+    pub input_primitives: u64,
+    pub vertex_invocations: u64,
+    pub clipping_invocations: u64,
+    pub clipping_primitives: u64,
+    pub fragment_invocations: u64,
+    pub compute_invocations: u64,
+}
+
+impl PipelineCounters {
+    /// `results` holds one value per entry of `PIPELINE_STATISTICS`.
+    pub fn from_results(results: &[u64]) -> Self {
+        Self {
+            input_vertices: results[0],
+            input_primitives: results[1],
+            vertex_invocations: results[2],
+            clipping_invocations: results[3],
+            clipping_primitives: results[4],
+            fragment_invocations: results[5],
+            compute_invocations: results[6],
+        }
+    }
+}
+
+impl std::fmt::Display for PipelineCounters {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} vertices, {} primitives, {} VS, {} clipped in / {} out, {} FS, {} CS",
+            self.input_vertices,
+            self.input_primitives,
+            self.vertex_invocations,
+            self.clipping_invocations,
+            self.clipping_primitives,
+            self.fragment_invocations,
+            self.compute_invocations
+        )
+    }
+}
+
+/// Samples that passed per sample in the render target, 1.0 when a draw covers every pixel
+/// once. Only meaningful with precise occlusion queries.
+pub fn overdraw(samples_passed: u64, extent: ffi::VkExtent2D, sample_count: u32) -> f64 {
+    let target_samples = extent.width as u64 * extent.height as u64 * sample_count as u64;
+    if target_samples == 0 {
+        0.0
+    } else {
+        samples_passed as f64 / target_samples as f64
+    }
+}
+
+pub fn create_query_pool(
+    device: ffi::VkDevice,
+    query_type: ffi::VkQueryType,
+    query_count: u32,
+    pipeline_statistics: ffi::VkQueryPipelineStatisticFlags,
+) -> Result<ffi::VkQueryPool, String> {
+    let mut create_info: ffi::VkQueryPoolCreateInfo = unsafe { std::mem::zeroed() };
+    create_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_QUERY_POOL_CREATE_INFO;
+    create_info.queryType = query_type;
+    create_info.queryCount = query_count;
+    create_info.pipelineStatistics = pipeline_statistics;
+
+    let mut query_pool: ffi::VkQueryPool = std::ptr::null_mut();
+    let result = unsafe {
+        ffi::vkCreateQueryPool(
+            device,
+            std::ptr::addr_of!(create_info),
+            std::ptr::null(),
+            std::ptr::addr_of_mut!(query_pool),
+        )
+    };
+    if result != ffi::VkResult_VK_SUCCESS {
+        return Err(String::from("Failed to create query pool!"));
+    }
+
+    Ok(query_pool)
+}
+
+/// Reads `query_count` queries of `values_per_query` 64 bit values each. Returns `None` while
+/// they aren't available, e.g. when the frame was recorded but never submitted.
+fn read_results(
+    device: ffi::VkDevice,
+    query_pool: ffi::VkQueryPool,
+    query_count: u32,
+    values_per_query: usize,
+) -> Result<Option<Vec<u64>>, String> {
+    let mut results = vec![0u64; query_count as usize * values_per_query];
+    if results.is_empty() {
+        return Ok(Some(results));
+    }
+
+    let result = unsafe {
+        ffi::vkGetQueryPoolResults(
+            device,
+            query_pool,
+            0,
+            query_count,
+            std::mem::size_of_val(results.as_slice()),
+            results.as_mut_ptr() as *mut std::ffi::c_void,
+            (values_per_query * std::mem::size_of::<u64>()) as ffi::VkDeviceSize,
+            ffi::VkQueryResultFlagBits_VK_QUERY_RESULT_64_BIT,
+        )
+    };
+    match result {
+        ffi::VkResult_VK_SUCCESS => Ok(Some(results)),
+        ffi::VkResult_VK_NOT_READY => Ok(None),
+        _ => Err(String::from("Failed to read query results!")),
+    }
+}
+
+/// Pipeline statistics per frame graph pass and an occlusion query per scene draw. Like the
+/// timestamps, results are read back once the frame's timeline value is reached.
+pub struct GpuQueries {
+    pub statistics_pool: ffi::VkQueryPool,
+    pub occlusion_pool: ffi::VkQueryPool,
+    /// Sample counts instead of just zero or non-zero, needs `occlusionQueryPrecise`.
+    pub precise_occlusion: bool,
+    pending_scopes: Option<Vec<String>>,
+    pending_draws: u32,
+    pub last_statistics: Vec<(String, PipelineCounters)>,
+    /// Samples passed per draw of the last frame.
+    pub last_occlusion: Vec<u64>,
+}
+
+impl GpuQueries {
+    pub fn new() -> Self {
+        Self {
+            statistics_pool: std::ptr::null_mut(),
+            occlusion_pool: std::ptr::null_mut(),
+            precise_occlusion: false,
+            pending_scopes: None,
+            pending_draws: 0,
+            last_statistics: Vec::new(),
+            last_occlusion: Vec::new(),
+        }
+    }
+
+    pub fn statistics_enabled(&self) -> bool {
+        !self.statistics_pool.is_null()
+    }
+
+    pub fn occlusion_enabled(&self) -> bool {
+        !self.occlusion_pool.is_null()
+    }
+
+    pub fn create(
+        &mut self,
+        device: ffi::VkDevice,
+        pipeline_statistics: bool,
+        precise_occlusion: bool,
+    ) -> Result<(), String> {
+        if pipeline_statistics {
+            self.statistics_pool = create_query_pool(
+                device,
+                ffi::VkQueryType_VK_QUERY_TYPE_PIPELINE_STATISTICS,
+                MAX_GPU_SCOPES,
+                pipeline_statistics_flags(),
+            )?;
+        }
+        self.occlusion_pool = create_query_pool(
+            device,
+            ffi::VkQueryType_VK_QUERY_TYPE_OCCLUSION,
+            MAX_OCCLUSION_DRAWS,
+            0,
+        )?;
+        self.precise_occlusion = precise_occlusion;
+
+        Ok(())
+    }
+
+    /// Resets the pools, must be recorded outside of a render pass.
+    pub fn begin_frame(&self, command_buffer: ffi::VkCommandBuffer) {
+        unsafe {
+            if self.statistics_enabled() {
+                ffi::vkCmdResetQueryPool(command_buffer, self.statistics_pool, 0, MAX_GPU_SCOPES);
+            }
+            if self.occlusion_enabled() {
+                ffi::vkCmdResetQueryPool(
+                    command_buffer,
+                    self.occlusion_pool,
+                    0,
+                    MAX_OCCLUSION_DRAWS,
+                );
+            }
+        }
+    }
+
+    /// Pass scopes are recorded outside of render passes, so they can contain whole ones.
+    pub fn begin_scope(&self, command_buffer: ffi::VkCommandBuffer, scope: u32) {
+        if self.statistics_enabled() && scope < MAX_GPU_SCOPES {
+            unsafe {
+                ffi::vkCmdBeginQuery(command_buffer, self.statistics_pool, scope, 0);
+            }
+        }
+    }
+
+    pub fn end_scope(&self, command_buffer: ffi::VkCommandBuffer, scope: u32) {
+        if self.statistics_enabled() && scope < MAX_GPU_SCOPES {
+            unsafe {
+                ffi::vkCmdEndQuery(command_buffer, self.statistics_pool, scope);
+            }
+        }
+    }
+
+    pub fn begin_draw(&self, command_buffer: ffi::VkCommandBuffer, draw: u32) {
+        if self.occlusion_enabled() && draw < MAX_OCCLUSION_DRAWS {
+            let flags = if self.precise_occlusion {
+                ffi::VkQueryControlFlagBits_VK_QUERY_CONTROL_PRECISE_BIT
+            } else {
+                0
+            };
+            unsafe {
+                ffi::vkCmdBeginQuery(command_buffer, self.occlusion_pool, draw, flags);
+            }
+        }
+    }
+
+    pub fn end_draw(&self, command_buffer: ffi::VkCommandBuffer, draw: u32) {
+        if self.occlusion_enabled() && draw < MAX_OCCLUSION_DRAWS {
+            unsafe {
+                ffi::vkCmdEndQuery(command_buffer, self.occlusion_pool, draw);
+            }
+        }
+    }
+
+    pub fn end_frame(&mut self, mut scope_names: Vec<String>, draw_count: u32) {
+        if self.statistics_enabled() {
+            scope_names.truncate(MAX_GPU_SCOPES as usize);
+            self.pending_scopes = Some(scope_names);
+        }
+        self.pending_draws = draw_count.min(MAX_OCCLUSION_DRAWS);
+    }
+
+    /// Reads back the last recorded frame. Call once its submission has completed.
+    pub fn collect(&mut self, device: ffi::VkDevice) -> Result<(), String> {
+        if let Some(names) = self.pending_scopes.take() {
+            let values = PIPELINE_STATISTICS.len();
+            if let Some(results) =
+                read_results(device, self.statistics_pool, names.len() as u32, values)?
+            {
+                self.last_statistics = names
+                    .into_iter()
+                    .zip(results.chunks_exact(values))
+                    .map(|(name, counters)| (name, PipelineCounters::from_results(counters)))
+                    .collect();
+            }
+        }
+
+        if self.occlusion_enabled() {
+            let draws = std::mem::take(&mut self.pending_draws);
+            if let Some(results) = read_results(device, self.occlusion_pool, draws, 1)? {
+                self.last_occlusion = results;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Draws that produced no samples last frame, candidates for visibility culling.
+    #[allow(dead_code)]
+    pub fn hidden_draws(&self) -> Vec<u32> {
+        self.last_occlusion
+            .iter()
+            .enumerate()
+            .filter(|(_, &samples)| samples == 0)
+            .map(|(draw, _)| draw as u32)
+            .collect()
+    }
+
+    pub fn destroy(&mut self, device: ffi::VkDevice) {
+        unsafe {
+            if !self.statistics_pool.is_null() {
+                ffi::vkDestroyQueryPool(device, self.statistics_pool, std::ptr::null());
+            }
+            if !self.occlusion_pool.is_null() {
+                ffi::vkDestroyQueryPool(device, self.occlusion_pool, std::ptr::null());
+            }
+        }
+
+        self.statistics_pool = std::ptr::null_mut();
+        self.occlusion_pool = std::ptr::null_mut();
+        self.pending_scopes = None;
+        self.pending_draws = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statistics_layout() {
+        // Results are returned in bit order, so the list has to be sorted.
+        assert!(PIPELINE_STATISTICS.windows(2).all(|bits| bits[0] < bits[1]));
+        assert_eq!(pipeline_statistics_flags().count_ones(), 7);
+
+        let counters = PipelineCounters::from_results(&[6, 2, 4, 2, 3, 1000, 8192]);
+        assert_eq!(counters.input_vertices, 6);
+        assert_eq!(counters.vertex_invocations, 4);
+        assert_eq!(counters.clipping_primitives, 3);
+        assert_eq!(counters.fragment_invocations, 1000);
+        assert_eq!(counters.compute_invocations, 8192);
+    }
+
+    #[test]
+    fn overdraw_ratio() {
+        let extent = ffi::VkExtent2D {
+            width: 100,
+            height: 50,
+        };
+        assert_eq!(overdraw(5000, extent, 1), 1.0);
+        assert_eq!(overdraw(10000, extent, 4), 0.5);
+        assert_eq!(
+            overdraw(
+                1,
+                ffi::VkExtent2D {
+                    width: 0,
+                    height: 0
+                },
+                1
+            ),
+            0.0
+        );
+    }
+
+    #[test]
+    fn hidden_draws() {
+        let mut queries = GpuQueries::new();
+        assert!(queries.hidden_draws().is_empty());
+
+        queries.last_occlusion = vec![120, 0, 7, 0];
+        assert_eq!(queries.hidden_draws(), [1, 3]);
+    }
+}
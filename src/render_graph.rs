@@ -0,0 +1,792 @@
+use std::collections::BTreeSet;
+
+use crate::barrier::{Barriers, Usage};
+use crate::ffi;
+use crate::image::AllocatedImage;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ResourceId(usize);
+
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ImageSize {
+    /// Follows the swapchain extent, so the image is recreated on resize.
+    Swapchain,
+    Fixed(u32, u32),
+}
+
+/// Describes an image the graph allocates for the frame.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ImageDesc {
+    pub format: ffi::VkFormat,
+    pub size: ImageSize,
+    pub usage: ffi::VkImageUsageFlags,
+    pub aspect: ffi::VkImageAspectFlags,
+    pub samples: ffi::VkSampleCountFlagBits,
+}
+
+impl ImageDesc {
+    pub fn extent(&self, swapchain_extent: ffi::VkExtent2D) -> ffi::VkExtent2D {
+        match self.size {
+            ImageSize::Swapchain => swapchain_extent,
+            ImageSize::Fixed(width, height) => ffi::VkExtent2D { width, height },
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub struct GraphImage {
+    pub image: ffi::VkImage,
+    pub view: ffi::VkImageView,
+    pub format: ffi::VkFormat,
+    pub extent: ffi::VkExtent2D,
+}
+
+impl From<AllocatedImage> for GraphImage {
+    fn from(image: AllocatedImage) -> Self {
+        Self {
+            image: image.image,
+            view: image.view,
+            format: image.format,
+            extent: image.extent,
+        }
+    }
+}
+
+enum ResourceKind {
+    Image {
+        aspect: ffi::VkImageAspectFlags,
+        /// Only set for transient images, imported ones are resolved from the start.
+        desc: Option<ImageDesc>,
+        resolved: Option<GraphImage>,
+    },
+    Buffer {
+        buffer: ffi::VkBuffer,
+        size: ffi::VkDeviceSize,
+    },
+}
+
+struct Resource {
+    name: String,
+    kind: ResourceKind,
+    initial_usage: Usage,
+    /// Imported resources outlive the frame and can ask to be left in a specific state.
+    final_usage: Option<Usage>,
+    imported: bool,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Access {
+    resource: ResourceId,
+    usage: Usage,
+}
+
+struct Pass<P> {
+    name: String,
+    payload: P,
+    reads: Vec<Access>,
+    writes: Vec<Access>,
+    side_effects: bool,
+}
+
+/// A state change of one resource that has to happen before a pass or at the end of the frame.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Transition {
+    pub resource: ResourceId,
+    pub from: Usage,
+    pub to: Usage,
+}
+
+pub struct Step {
+    pub pass: usize,
+    pub transitions: Vec<Transition>,
+}
+
+/// Execution order of the passes that survived culling, with the transitions each one needs.
+pub struct CompiledGraph {
+    pub steps: Vec<Step>,
+    pub final_transitions: Vec<Transition>,
+    #[allow(dead_code)]
+    pub culled: Vec<usize>,
+}
+
+/// A frame described as passes reading and writing resources. `P` is whatever the caller
+/// needs to record a pass, it gets handed back in execution order.
+pub struct RenderGraph<P> {
+    resources: Vec<Resource>,
+    passes: Vec<Pass<P>>,
+}
+
+pub struct PassBuilder<'a, P> {
+    graph: &'a mut RenderGraph<P>,
+    pass: usize,
+}
+
+impl<'a, P> PassBuilder<'a, P> {
+    pub fn read(self, resource: ResourceId, usage: Usage) -> Self {
+        self.graph.passes[self.pass]
+            .reads
+            .push(Access { resource, usage });
+        self
+    }
+
+    pub fn write(self, resource: ResourceId, usage: Usage) -> Self {
+        self.graph.passes[self.pass]
+            .writes
+            .push(Access { resource, usage });
+        self
+    }
+
+    /// Keeps the pass even if nothing reads what it writes.
+    #[allow(dead_code)]
+    pub fn side_effects(self) -> Self {
+        self.graph.passes[self.pass].side_effects = true;
+        self
+    }
+}
+
+impl<P> RenderGraph<P> {
+    pub fn new() -> Self {
+        Self {
+            resources: Vec::new(),
+            passes: Vec::new(),
+        }
+    }
+
+    fn add_resource(&mut self, resource: Resource) -> ResourceId {
+        self.resources.push(resource);
+        ResourceId(self.resources.len() - 1)
+    }
+
+    /// An image owned outside the graph, e.g. a swapchain image. It starts out in
+    /// `initial_usage` and is transitioned to `final_usage` after the last pass.
+    pub fn import_image(
+        &mut self,
+        name: &str,
+        image: GraphImage,
+        aspect: ffi::VkImageAspectFlags,
+        initial_usage: Usage,
+        final_usage: Usage,
+    ) -> ResourceId {
+        self.add_resource(Resource {
+            name: name.to_owned(),
+            kind: ResourceKind::Image {
+                aspect,
+                desc: None,
+                resolved: Some(image),
+            },
+            initial_usage,
+            final_usage: Some(final_usage),
+            imported: true,
+        })
+    }
+
+    /// A buffer owned outside the graph. `initial_usage` is how it was last used, which can be
+    /// in the previous frame.
+    pub fn import_buffer(
+        &mut self,
+        name: &str,
+        buffer: ffi::VkBuffer,
+        size: ffi::VkDeviceSize,
+        initial_usage: Usage,
+    ) -> ResourceId {
+        self.add_resource(Resource {
+            name: name.to_owned(),
+            kind: ResourceKind::Buffer { buffer, size },
+            initial_usage,
+            final_usage: None,
+            imported: true,
+        })
+    }
+
+    /// An image that only lives for the frame. Its contents are undefined before the first
+    /// write.
+    pub fn create_image(&mut self, name: &str, desc: ImageDesc) -> ResourceId {
+        self.add_resource(Resource {
+            name: name.to_owned(),
+            kind: ResourceKind::Image {
+                aspect: desc.aspect,
+                desc: Some(desc),
+                resolved: None,
+            },
+            initial_usage: Usage::Nothing,
+            final_usage: None,
+            imported: false,
+        })
+    }
+
+    pub fn add_pass(&mut self, name: &str, payload: P) -> PassBuilder<'_, P> {
+        self.passes.push(Pass {
+            name: name.to_owned(),
+            payload,
+            reads: Vec::new(),
+            writes: Vec::new(),
+            side_effects: false,
+        });
+        let pass = self.passes.len() - 1;
+        PassBuilder { graph: self, pass }
+    }
+
+    pub fn pass_name(&self, pass: usize) -> &str {
+        &self.passes[pass].name
+    }
+
+    pub fn payload(&self, pass: usize) -> &P {
+        &self.passes[pass].payload
+    }
+
+    pub fn image(&self, resource: ResourceId) -> Option<GraphImage> {
+        match self.resources[resource.0].kind {
+            ResourceKind::Image { resolved, .. } => resolved,
+            ResourceKind::Buffer { .. } => None,
+        }
+    }
+
+    /// Transient images resolved by `allocate`, with the name of their resource.
+    pub fn transient_images(&self) -> impl Iterator<Item = (&str, GraphImage)> + '_ {
+        self.resources
+            .iter()
+            .filter_map(|resource| match resource.kind {
+                ResourceKind::Image {
+                    desc: Some(_),
+                    resolved: Some(image),
+                    ..
+                } => Some((resource.name.as_str(), image)),
+                _ => None,
+            })
+    }
+
+    /// Pass `b` needs the result of pass `a`: either `b` reads something `a` writes, or both
+    /// write the same resource and `a` was added first. A read sees the writes added before it,
+    /// or every write when there are none, so a reader may be added ahead of its writer.
+    fn needs_output_of(&self, b: usize, a: usize) -> bool {
+        if a == b {
+            return false;
+        }
+        self.passes[a].writes.iter().any(|write| {
+            (self.reads(b, write.resource) && (a < b || !self.written_before(write.resource, b)))
+                || (a < b && self.writes(b, write.resource))
+        })
+    }
+
+    /// Pass `a` has to run before pass `b`: `b` needs its output, or `b` overwrites something
+    /// `a` still has to read.
+    fn depends_on(&self, b: usize, a: usize) -> bool {
+        self.needs_output_of(b, a)
+            || (a < b
+                && self.passes[a].reads.iter().any(|read| {
+                    self.writes(b, read.resource) && self.written_before(read.resource, a)
+                }))
+    }
+
+    fn reads(&self, pass: usize, resource: ResourceId) -> bool {
+        self.passes[pass]
+            .reads
+            .iter()
+            .any(|read| read.resource == resource)
+    }
+
+    fn writes(&self, pass: usize, resource: ResourceId) -> bool {
+        self.passes[pass]
+            .writes
+            .iter()
+            .any(|write| write.resource == resource)
+    }
+
+    /// Whether a pass added before `pass` writes `resource`.
+    fn written_before(&self, resource: ResourceId, pass: usize) -> bool {
+        (0..pass).any(|other| self.writes(other, resource))
+    }
+
+    /// Passes that write an imported resource or have side effects are kept, along with
+    /// everything whose output they need.
+    fn live_passes(&self) -> BTreeSet<usize> {
+        let mut live = BTreeSet::new();
+        let mut stack: Vec<usize> = (0..self.passes.len())
+            .filter(|&idx| {
+                let pass = &self.passes[idx];
+                pass.side_effects
+                    || pass
+                        .writes
+                        .iter()
+                        .any(|write| self.resources[write.resource.0].imported)
+            })
+            .collect();
+
+        while let Some(idx) = stack.pop() {
+            if !live.insert(idx) {
+                continue;
+            }
+            stack.extend((0..self.passes.len()).filter(|&other| self.needs_output_of(idx, other)));
+        }
+
+        live
+    }
+
+    /// Culls unused passes, orders the rest and works out the transitions between them.
+    /// When passes don't depend on each other they run in the order they were added.
+    pub fn compile(&self) -> Result<CompiledGraph, String> {
+        let live = self.live_passes();
+        let culled = (0..self.passes.len())
+            .filter(|idx| !live.contains(idx))
+            .collect();
+
+        let mut remaining = live;
+        let mut order = Vec::with_capacity(remaining.len());
+        while !remaining.is_empty() {
+            let next = remaining
+                .iter()
+                .copied()
+                .find(|&idx| !remaining.iter().any(|&other| self.depends_on(idx, other)))
+                .ok_or_else(|| {
+                    let names: Vec<&str> = remaining
+                        .iter()
+                        .map(|&idx| self.passes[idx].name.as_str())
+                        .collect();
+                    format!("Render graph has a cycle between {}", names.join(", "))
+                })?;
+            remaining.remove(&next);
+            order.push(next);
+        }
+
+        let mut state: Vec<Usage> = self
+            .resources
+            .iter()
+            .map(|resource| resource.initial_usage)
+            .collect();
+
+        let mut steps = Vec::with_capacity(order.len());
+        for idx in order {
+            let pass = &self.passes[idx];
+            let mut transitions = Vec::new();
+
+            for access in pass.reads.iter().chain(pass.writes.iter()) {
+                let resource = &self.resources[access.resource.0];
+                let current = state[access.resource.0];
+
+                if pass.reads.contains(access) && current == Usage::Nothing {
+                    return Err(format!(
+                        "Pass \"{}\" reads \"{}\" before anything writes it",
+                        pass.name, resource.name
+                    ));
+                }
+
+                if needs_transition(resource, current, access.usage) {
+                    transitions.push(Transition {
+                        resource: access.resource,
+                        from: current,
+                        to: access.usage,
+                    });
+                }
+                state[access.resource.0] = access.usage;
+            }
+
+            steps.push(Step {
+                pass: idx,
+                transitions,
+            });
+        }
+
+        let final_transitions = self
+            .resources
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, resource)| {
+                let to = resource.final_usage?;
+                let from = state[idx];
+                (from != to).then_some(Transition {
+                    resource: ResourceId(idx),
+                    from,
+                    to,
+                })
+            })
+            .collect();
+
+        Ok(CompiledGraph {
+            steps,
+            final_transitions,
+            culled,
+        })
+    }
+
+    /// Resolves the transient images used by `compiled` from `pool`.
+    pub fn allocate(
+        &mut self,
+        compiled: &CompiledGraph,
+        pool: &mut TransientPool,
+        device: ffi::VkDevice,
+        mem_props: &ffi::VkPhysicalDeviceMemoryProperties,
+        swapchain_extent: ffi::VkExtent2D,
+    ) -> Result<(), String> {
+        let used: BTreeSet<usize> = compiled
+            .steps
+            .iter()
+            .flat_map(|step| {
+                let pass = &self.passes[step.pass];
+                pass.reads
+                    .iter()
+                    .chain(pass.writes.iter())
+                    .map(|access| access.resource.0)
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        for idx in used {
+            if let ResourceKind::Image {
+                desc: Some(desc),
+                resolved,
+                ..
+            } = &mut self.resources[idx].kind
+            {
+                let image = pool.acquire(device, mem_props, desc, swapchain_extent)?;
+                *resolved = Some(image.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn barriers(&self, transitions: &[Transition]) -> Barriers {
+        let mut barriers = Barriers::new();
+        for transition in transitions {
+            barriers = match &self.resources[transition.resource.0].kind {
+                ResourceKind::Image {
+                    aspect, resolved, ..
+                } => {
+                    let image = resolved.expect("transient images should be allocated");
+                    let mut range = crate::barrier::color_subresource_range();
+                    range.aspectMask = *aspect;
+                    barriers.image(image.image, range, transition.from, transition.to)
+                }
+                ResourceKind::Buffer { buffer, size } => {
+                    barriers.buffer(*buffer, 0, *size, transition.from, transition.to)
+                }
+            };
+        }
+        barriers
+    }
+
+    /// Records the transitions of every step and hands its name and payload to `record`.
+    pub fn execute<F>(
+        &self,
+        compiled: &CompiledGraph,
+        command_buffer: ffi::VkCommandBuffer,
+        synchronization2: bool,
+        mut record: F,
+    ) where
+        F: FnMut(&str, &P),
+    {
+        for step in &compiled.steps {
+            self.barriers(&step.transitions)
+                .record(command_buffer, synchronization2);
+            let pass = &self.passes[step.pass];
+            record(&pass.name, &pass.payload);
+        }
+
+        self.barriers(&compiled.final_transitions)
+            .record(command_buffer, synchronization2);
+    }
+}
+
+impl<P> Default for RenderGraph<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Images always need one for their layout. Buffers only need one after a write, or when a
+/// write follows a read.
+fn needs_transition(resource: &Resource, from: Usage, to: Usage) -> bool {
+    match resource.kind {
+        ResourceKind::Image { .. } => from != to || to.is_write(),
+        ResourceKind::Buffer { .. } => from != Usage::Nothing && (from.is_write() || to.is_write()),
+    }
+}
+
+/// Transient images kept across frames. Each `acquire` hands out an image no other resource
+/// got this frame, and images that weren't acquired during a frame are returned by
+/// `take_unused` so the caller can destroy them once the GPU is done.
+pub struct TransientPool {
+    images: Vec<(ImageDesc, AllocatedImage, bool)>,
+}
+
+impl TransientPool {
+    pub fn new() -> Self {
+        Self { images: Vec::new() }
+    }
+
+    pub fn begin_frame(&mut self) {
+        for (_, _, used) in self.images.iter_mut() {
+            *used = false;
+        }
+    }
+
+    pub fn acquire(
+        &mut self,
+        device: ffi::VkDevice,
+        mem_props: &ffi::VkPhysicalDeviceMemoryProperties,
+        desc: &ImageDesc,
+        swapchain_extent: ffi::VkExtent2D,
+    ) -> Result<AllocatedImage, String> {
+        let extent = desc.extent(swapchain_extent);
+        if let Some((_, image, used)) = self.images.iter_mut().find(|(existing, image, used)| {
+            !*used
+                && existing == desc
+                && image.extent.width == extent.width
+                && image.extent.height == extent.height
+        }) {
+            *used = true;
+            return Ok(*image);
+        }
+
+        let image = AllocatedImage::create(
+            device,
+            mem_props,
+            desc.format,
+            extent,
+            desc.usage,
+            desc.aspect,
+            desc.samples,
+        )?;
+        self.images.push((*desc, image, true));
+        Ok(image)
+    }
+
+    pub fn take_unused(&mut self) -> Vec<AllocatedImage> {
+        let (used, unused): (Vec<_>, Vec<_>) = std::mem::take(&mut self.images)
+            .into_iter()
+            .partition(|(_, _, used)| *used);
+        self.images = used;
+        unused.into_iter().map(|(_, image, _)| image).collect()
+    }
+
+    pub fn drain(&mut self) -> Vec<AllocatedImage> {
+        self.images.drain(..).map(|(_, image, _)| image).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+}
+
+impl Default for TransientPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color_desc() -> ImageDesc {
+        ImageDesc {
+            format: ffi::VkFormat_VK_FORMAT_R16G16B16A16_SFLOAT,
+            size: ImageSize::Swapchain,
+            usage: ffi::VkImageUsageFlagBits_VK_IMAGE_USAGE_COLOR_ATTACHMENT_BIT,
+            aspect: ffi::VkImageAspectFlagBits_VK_IMAGE_ASPECT_COLOR_BIT,
+            samples: ffi::VkSampleCountFlagBits_VK_SAMPLE_COUNT_1_BIT,
+        }
+    }
+
+    fn swapchain_image() -> GraphImage {
+        GraphImage {
+            image: std::ptr::null_mut(),
+            view: std::ptr::null_mut(),
+            format: ffi::VkFormat_VK_FORMAT_B8G8R8A8_SRGB,
+            extent: ffi::VkExtent2D {
+                width: 800,
+                height: 600,
+            },
+        }
+    }
+
+    fn import_swapchain(graph: &mut RenderGraph<&'static str>) -> ResourceId {
+        graph.import_image(
+            "swapchain",
+            swapchain_image(),
+            ffi::VkImageAspectFlagBits_VK_IMAGE_ASPECT_COLOR_BIT,
+            Usage::AcquiredImage,
+            Usage::Present,
+        )
+    }
+
+    fn pass_order(
+        graph: &RenderGraph<&'static str>,
+        compiled: &CompiledGraph,
+    ) -> Vec<&'static str> {
+        compiled
+            .steps
+            .iter()
+            .map(|step| graph.passes[step.pass].payload)
+            .collect()
+    }
+
+    #[test]
+    fn orders_by_dependencies_and_culls() {
+        let mut graph = RenderGraph::new();
+        let swapchain = import_swapchain(&mut graph);
+        let hdr = graph.create_image("hdr", color_desc());
+        let unused = graph.create_image("debug", color_desc());
+
+        // Added out of order on purpose.
+        graph
+            .add_pass("tonemap", "tonemap")
+            .read(hdr, Usage::FragmentShaderRead)
+            .write(swapchain, Usage::ColorAttachment);
+        graph
+            .add_pass("debug", "debug")
+            .read(hdr, Usage::FragmentShaderRead)
+            .write(unused, Usage::ColorAttachment);
+        graph
+            .add_pass("scene", "scene")
+            .write(hdr, Usage::ColorAttachment);
+        graph.add_pass("query", "query").side_effects();
+
+        let compiled = graph.compile().unwrap();
+        assert_eq!(
+            pass_order(&graph, &compiled),
+            vec!["scene", "tonemap", "query"]
+        );
+        assert_eq!(compiled.culled, vec![1]);
+
+        // A pass overwriting an image has to wait for the earlier passes reading it.
+        let mut graph = RenderGraph::new();
+        let swapchain = import_swapchain(&mut graph);
+        let hdr = graph.create_image("hdr", color_desc());
+        graph
+            .add_pass("scene", "scene")
+            .write(hdr, Usage::ColorAttachment);
+        graph
+            .add_pass("copy", "copy")
+            .read(hdr, Usage::FragmentShaderRead)
+            .write(swapchain, Usage::ColorAttachment);
+        graph
+            .add_pass("overlay", "overlay")
+            .write(hdr, Usage::ColorAttachment)
+            .side_effects();
+
+        let compiled = graph.compile().unwrap();
+        assert_eq!(
+            pass_order(&graph, &compiled),
+            vec!["scene", "copy", "overlay"]
+        );
+    }
+
+    #[test]
+    fn inserts_transitions() {
+        let mut graph = RenderGraph::new();
+        let swapchain = import_swapchain(&mut graph);
+        let hdr = graph.create_image("hdr", color_desc());
+        let vertices =
+            graph.import_buffer("vertices", std::ptr::null_mut(), 256, Usage::VertexBuffer);
+
+        graph
+            .add_pass("animate", "animate")
+            .write(vertices, Usage::ComputeShaderWrite);
+        graph
+            .add_pass("scene", "scene")
+            .read(vertices, Usage::VertexBuffer)
+            .write(hdr, Usage::ColorAttachment);
+        graph
+            .add_pass("tonemap", "tonemap")
+            .read(hdr, Usage::FragmentShaderRead)
+            .write(swapchain, Usage::ColorAttachment);
+
+        let compiled = graph.compile().unwrap();
+        assert_eq!(
+            compiled.steps[0].transitions,
+            vec![Transition {
+                resource: vertices,
+                from: Usage::VertexBuffer,
+                to: Usage::ComputeShaderWrite,
+            }]
+        );
+        assert_eq!(
+            compiled.steps[1].transitions,
+            vec![
+                Transition {
+                    resource: vertices,
+                    from: Usage::ComputeShaderWrite,
+                    to: Usage::VertexBuffer,
+                },
+                Transition {
+                    resource: hdr,
+                    from: Usage::Nothing,
+                    to: Usage::ColorAttachment,
+                },
+            ]
+        );
+        assert_eq!(
+            compiled.steps[2].transitions,
+            vec![
+                Transition {
+                    resource: hdr,
+                    from: Usage::ColorAttachment,
+                    to: Usage::FragmentShaderRead,
+                },
+                Transition {
+                    resource: swapchain,
+                    from: Usage::AcquiredImage,
+                    to: Usage::ColorAttachment,
+                },
+            ]
+        );
+        assert_eq!(
+            compiled.final_transitions,
+            vec![Transition {
+                resource: swapchain,
+                from: Usage::ColorAttachment,
+                to: Usage::Present,
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_graphs() {
+        let mut graph = RenderGraph::new();
+        let swapchain = import_swapchain(&mut graph);
+        let never_written = graph.create_image("shadow", color_desc());
+        graph
+            .add_pass("scene", "scene")
+            .read(never_written, Usage::FragmentShaderRead)
+            .write(swapchain, Usage::ColorAttachment);
+        assert!(graph.compile().is_err());
+
+        let mut graph = RenderGraph::new();
+        let swapchain = import_swapchain(&mut graph);
+        let a = graph.create_image("a", color_desc());
+        let b = graph.create_image("b", color_desc());
+        graph
+            .add_pass("first", "first")
+            .read(b, Usage::FragmentShaderRead)
+            .write(a, Usage::ColorAttachment);
+        graph
+            .add_pass("second", "second")
+            .read(a, Usage::FragmentShaderRead)
+            .write(b, Usage::ColorAttachment)
+            .write(swapchain, Usage::ColorAttachment);
+        assert!(graph.compile().is_err());
+    }
+
+    #[test]
+    fn image_sizes() {
+        let swapchain_extent = ffi::VkExtent2D {
+            width: 1280,
+            height: 720,
+        };
+        assert_eq!(color_desc().extent(swapchain_extent).width, 1280);
+
+        let shadow = ImageDesc {
+            size: ImageSize::Fixed(2048, 2048),
+            ..color_desc()
+        };
+        assert_eq!(shadow.extent(swapchain_extent).height, 2048);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::ffi;
+
+/// Begins a dynamic rendering scope that clears `view`. With a `resolve_view` the samples of
+/// `view` are averaged into it at the end and `view` itself isn't stored. Both images have to
+/// be in `COLOR_ATTACHMENT_OPTIMAL` already.
+pub fn begin_color_rendering(
+    command_buffer: ffi::VkCommandBuffer,
+    view: ffi::VkImageView,
+    resolve_view: Option<ffi::VkImageView>,
+    extent: ffi::VkExtent2D,
+    clear_color: [f32; 4],
+) {
+    let mut color_attachment: ffi::VkRenderingAttachmentInfo = unsafe { std::mem::zeroed() };
+    color_attachment.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_RENDERING_ATTACHMENT_INFO;
+    color_attachment.imageView = view;
+    color_attachment.imageLayout = ffi::VkImageLayout_VK_IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL;
+    color_attachment.loadOp = ffi::VkAttachmentLoadOp_VK_ATTACHMENT_LOAD_OP_CLEAR;
+    match resolve_view {
+        Some(resolve_view) => {
+            color_attachment.resolveMode = ffi::VkResolveModeFlagBits_VK_RESOLVE_MODE_AVERAGE_BIT;
+            color_attachment.resolveImageView = resolve_view;
+            color_attachment.resolveImageLayout =
+                ffi::VkImageLayout_VK_IMAGE_LAYOUT_COLOR_ATTACHMENT_OPTIMAL;
+            color_attachment.storeOp = ffi::VkAttachmentStoreOp_VK_ATTACHMENT_STORE_OP_DONT_CARE;
+        }
+        None => {
+            color_attachment.resolveMode = ffi::VkResolveModeFlagBits_VK_RESOLVE_MODE_NONE;
+            color_attachment.storeOp = ffi::VkAttachmentStoreOp_VK_ATTACHMENT_STORE_OP_STORE;
+        }
+    }
+    color_attachment.clearValue.color.float32 = clear_color;
+
+    let mut rendering_info: ffi::VkRenderingInfo = unsafe { std::mem::zeroed() };
+    rendering_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_RENDERING_INFO;
+    rendering_info.renderArea.offset.x = 0;
+    rendering_info.renderArea.offset.y = 0;
+    rendering_info.renderArea.extent = extent;
+    rendering_info.layerCount = 1;
+    rendering_info.colorAttachmentCount = 1;
+    rendering_info.pColorAttachments = std::ptr::addr_of!(color_attachment);
+
+    unsafe {
+        ffi::vkCmdBeginRendering(command_buffer, std::ptr::addr_of!(rendering_info));
+    }
+}
+
+pub fn end_color_rendering(command_buffer: ffi::VkCommandBuffer) {
+    unsafe {
+        ffi::vkCmdEndRendering(command_buffer);
+    }
+}
+
+/// A framebuffer for a render pass object, attachments in the order of the render pass.
+pub fn create_framebuffer(
+    device: ffi::VkDevice,
+    render_pass: ffi::VkRenderPass,
+    attachments: &[ffi::VkImageView],
+    extent: ffi::VkExtent2D,
+) -> Result<ffi::VkFramebuffer, String> {
+    let mut framebuffer_info: ffi::VkFramebufferCreateInfo = unsafe { std::mem::zeroed() };
+    framebuffer_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_FRAMEBUFFER_CREATE_INFO;
+    framebuffer_info.renderPass = render_pass;
+    framebuffer_info.attachmentCount = attachments.len() as u32;
+    framebuffer_info.pAttachments = attachments.as_ptr();
+    framebuffer_info.width = extent.width;
+    framebuffer_info.height = extent.height;
+    framebuffer_info.layers = 1;
+
+    let mut framebuffer: ffi::VkFramebuffer = std::ptr::null_mut();
+    let result = unsafe {
+        ffi::vkCreateFramebuffer(
+            device,
+            std::ptr::addr_of!(framebuffer_info),
+            std::ptr::null(),
+            std::ptr::addr_of_mut!(framebuffer),
+        )
+    };
+    if result != ffi::VkResult_VK_SUCCESS {
+        return Err(String::from("Failed to create framebuffer!"));
+    }
+
+    Ok(framebuffer)
+}
+
+/// Framebuffers for render graph images. The transient pool hands out the same images every
+/// frame, so a framebuffer only has to be created again after the pool replaced its images.
+pub struct FramebufferCache {
+    framebuffers: Vec<(ffi::VkRenderPass, Vec<ffi::VkImageView>, ffi::VkFramebuffer)>,
+}
+
+impl FramebufferCache {
+    pub fn new() -> Self {
+        Self {
+            framebuffers: Vec::new(),
+        }
+    }
+
+    pub fn get(
+        &self,
+        render_pass: ffi::VkRenderPass,
+        attachments: &[ffi::VkImageView],
+    ) -> Option<ffi::VkFramebuffer> {
+        self.framebuffers
+            .iter()
+            .find(|(pass, views, _)| *pass == render_pass && views == attachments)
+            .map(|(_, _, framebuffer)| *framebuffer)
+    }
+
+    pub fn insert(
+        &mut self,
+        render_pass: ffi::VkRenderPass,
+        attachments: Vec<ffi::VkImageView>,
+        framebuffer: ffi::VkFramebuffer,
+    ) {
+        self.framebuffers
+            .push((render_pass, attachments, framebuffer));
+    }
+
+    /// Empties the cache, the caller destroys the framebuffers once the GPU is done with them.
+    pub fn take_all(&mut self) -> Vec<ffi::VkFramebuffer> {
+        self.framebuffers
+            .drain(..)
+            .map(|(_, _, framebuffer)| framebuffer)
+            .collect()
+    }
+}
+
+impl Default for FramebufferCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
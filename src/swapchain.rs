@@ -0,0 +1,403 @@
+use crate::ffi;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PresentModePolicy {
+    /// FIFO, always supported.
+    #[default]
+    Vsync,
+    /// MAILBOX, no tearing but doesn't block on the display.
+    LowLatency,
+    /// IMMEDIATE, may tear.
+    Immediate,
+    /// FIFO_RELAXED, tears only when a frame is late.
+    Relaxed,
+}
+
+impl PresentModePolicy {
+    pub const NAMES: [&'static str; 4] = ["vsync", "mailbox", "immediate", "relaxed"];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PresentModePolicy::Vsync => Self::NAMES[0],
+            PresentModePolicy::LowLatency => Self::NAMES[1],
+            PresentModePolicy::Immediate => Self::NAMES[2],
+            PresentModePolicy::Relaxed => Self::NAMES[3],
+        }
+    }
+
+    /// Present modes to try in order. FIFO is last since every surface must support it.
+    pub fn preferred_modes(self) -> &'static [ffi::VkPresentModeKHR] {
+        match self {
+            PresentModePolicy::Vsync => &[ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_KHR],
+            // Never falls back to a mode that tears.
+            PresentModePolicy::LowLatency => &[
+                ffi::VkPresentModeKHR_VK_PRESENT_MODE_MAILBOX_KHR,
+                ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_KHR,
+            ],
+            PresentModePolicy::Immediate => &[
+                ffi::VkPresentModeKHR_VK_PRESENT_MODE_IMMEDIATE_KHR,
+                ffi::VkPresentModeKHR_VK_PRESENT_MODE_MAILBOX_KHR,
+                ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_RELAXED_KHR,
+                ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_KHR,
+            ],
+            PresentModePolicy::Relaxed => &[
+                ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_RELAXED_KHR,
+                ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_KHR,
+            ],
+        }
+    }
+
+    pub fn choose(self, available: &[ffi::VkPresentModeKHR]) -> ffi::VkPresentModeKHR {
+        self.preferred_modes()
+            .iter()
+            .copied()
+            .find(|mode| available.contains(mode))
+            .unwrap_or(ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_KHR)
+    }
+
+    /// Switches between vsync and `unsynced`, falling back to `LowLatency` if `unsynced` is
+    /// itself vsync.
+    pub fn toggle_vsync(self, unsynced: PresentModePolicy) -> PresentModePolicy {
+        if self != PresentModePolicy::Vsync {
+            PresentModePolicy::Vsync
+        } else if unsynced != PresentModePolicy::Vsync {
+            unsynced
+        } else {
+            PresentModePolicy::LowLatency
+        }
+    }
+}
+
+impl std::str::FromStr for PresentModePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "vsync" | "fifo" => Ok(PresentModePolicy::Vsync),
+            "mailbox" | "low-latency" => Ok(PresentModePolicy::LowLatency),
+            "immediate" => Ok(PresentModePolicy::Immediate),
+            "relaxed" | "fifo-relaxed" => Ok(PresentModePolicy::Relaxed),
+            _ => Err(format!(
+                "Unknown present mode \"{}\", expected one of: {}",
+                s,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+pub fn present_mode_name(mode: ffi::VkPresentModeKHR) -> &'static str {
+    match mode {
+        ffi::VkPresentModeKHR_VK_PRESENT_MODE_IMMEDIATE_KHR => "IMMEDIATE",
+        ffi::VkPresentModeKHR_VK_PRESENT_MODE_MAILBOX_KHR => "MAILBOX",
+        ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_KHR => "FIFO",
+        ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_RELAXED_KHR => "FIFO_RELAXED",
+        _ => "UNKNOWN",
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SurfaceFormatPreference {
+    /// 8-bit sRGB.
+    #[default]
+    Sdr,
+    /// 10-bit per channel, still sRGB encoded.
+    TenBit,
+    /// 10-bit HDR10 (BT.2020 primaries, ST 2084 PQ transfer).
+    Hdr10,
+    /// 16-bit float extended linear sRGB.
+    ScRgb,
+}
+
+const SDR_FORMATS: [(ffi::VkFormat, ffi::VkColorSpaceKHR); 3] = [
+    (
+        ffi::VkFormat_VK_FORMAT_B8G8R8A8_SRGB,
+        ffi::VkColorSpaceKHR_VK_COLOR_SPACE_SRGB_NONLINEAR_KHR,
+    ),
+    (
+        ffi::VkFormat_VK_FORMAT_R8G8B8A8_SRGB,
+        ffi::VkColorSpaceKHR_VK_COLOR_SPACE_SRGB_NONLINEAR_KHR,
+    ),
+    (
+        ffi::VkFormat_VK_FORMAT_B8G8R8A8_UNORM,
+        ffi::VkColorSpaceKHR_VK_COLOR_SPACE_SRGB_NONLINEAR_KHR,
+    ),
+];
+
+const TEN_BIT_FORMATS: [(ffi::VkFormat, ffi::VkColorSpaceKHR); 2] = [
+    (
+        ffi::VkFormat_VK_FORMAT_A2B10G10R10_UNORM_PACK32,
+        ffi::VkColorSpaceKHR_VK_COLOR_SPACE_SRGB_NONLINEAR_KHR,
+    ),
+    (
+        ffi::VkFormat_VK_FORMAT_A2R10G10B10_UNORM_PACK32,
+        ffi::VkColorSpaceKHR_VK_COLOR_SPACE_SRGB_NONLINEAR_KHR,
+    ),
+];
+
+const HDR10_FORMATS: [(ffi::VkFormat, ffi::VkColorSpaceKHR); 2] = [
+    (
+        ffi::VkFormat_VK_FORMAT_A2B10G10R10_UNORM_PACK32,
+        ffi::VkColorSpaceKHR_VK_COLOR_SPACE_HDR10_ST2084_EXT,
+    ),
+    (
+        ffi::VkFormat_VK_FORMAT_A2R10G10B10_UNORM_PACK32,
+        ffi::VkColorSpaceKHR_VK_COLOR_SPACE_HDR10_ST2084_EXT,
+    ),
+];
+
+const SCRGB_FORMATS: [(ffi::VkFormat, ffi::VkColorSpaceKHR); 1] = [(
+    ffi::VkFormat_VK_FORMAT_R16G16B16A16_SFLOAT,
+    ffi::VkColorSpaceKHR_VK_COLOR_SPACE_EXTENDED_SRGB_LINEAR_EXT,
+)];
+
+impl SurfaceFormatPreference {
+    pub const NAMES: [&'static str; 4] = ["sdr", "10bit", "hdr10", "scrgb"];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SurfaceFormatPreference::Sdr => Self::NAMES[0],
+            SurfaceFormatPreference::TenBit => Self::NAMES[1],
+            SurfaceFormatPreference::Hdr10 => Self::NAMES[2],
+            SurfaceFormatPreference::ScRgb => Self::NAMES[3],
+        }
+    }
+
+    pub fn needs_swapchain_colorspace(self) -> bool {
+        matches!(
+            self,
+            SurfaceFormatPreference::Hdr10 | SurfaceFormatPreference::ScRgb
+        )
+    }
+
+    /// Format and color space pairs to try in order, always ending with the SDR ones.
+    pub fn preferred_formats(self) -> Vec<(ffi::VkFormat, ffi::VkColorSpaceKHR)> {
+        let mut formats = Vec::new();
+        match self {
+            SurfaceFormatPreference::Sdr => (),
+            SurfaceFormatPreference::TenBit => formats.extend_from_slice(&TEN_BIT_FORMATS),
+            SurfaceFormatPreference::Hdr10 => {
+                formats.extend_from_slice(&HDR10_FORMATS);
+                formats.extend_from_slice(&TEN_BIT_FORMATS);
+            }
+            SurfaceFormatPreference::ScRgb => {
+                formats.extend_from_slice(&SCRGB_FORMATS);
+                formats.extend_from_slice(&HDR10_FORMATS);
+                formats.extend_from_slice(&TEN_BIT_FORMATS);
+            }
+        }
+        formats.extend_from_slice(&SDR_FORMATS);
+
+        formats
+    }
+
+    /// Returns the index into `available` to use, or the first format if none are preferred.
+    pub fn choose(self, available: &[ffi::VkSurfaceFormatKHR]) -> Option<usize> {
+        if available.is_empty() {
+            return None;
+        }
+
+        self.preferred_formats()
+            .iter()
+            .find_map(|(format, color_space)| {
+                available.iter().position(|surface_format| {
+                    surface_format.format == *format && surface_format.colorSpace == *color_space
+                })
+            })
+            .or(Some(0))
+    }
+}
+
+impl std::str::FromStr for SurfaceFormatPreference {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "sdr" | "srgb" => Ok(SurfaceFormatPreference::Sdr),
+            "10bit" | "10-bit" => Ok(SurfaceFormatPreference::TenBit),
+            "hdr10" | "hdr" => Ok(SurfaceFormatPreference::Hdr10),
+            "scrgb" => Ok(SurfaceFormatPreference::ScRgb),
+            _ => Err(format!(
+                "Unknown surface format \"{}\", expected one of: {}",
+                s,
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// How the fragment shaders encode their linear output, matching `OUTPUT_ENCODING` in
+/// `shaders/encoding.glsl`.
+#[repr(u32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OutputEncoding {
+    /// `*_SRGB` formats, the hardware applies the sRGB curve on write.
+    Linear = 0,
+    /// `*_UNORM` formats in the sRGB color space.
+    Srgb = 1,
+    /// HDR10, BT.2020 primaries with the PQ curve.
+    Pq = 2,
+    /// scRGB, linear BT.709 where 1.0 is 80 nits.
+    ScRgb = 3,
+}
+
+impl OutputEncoding {
+    pub fn for_surface_format(surface_format: &ffi::VkSurfaceFormatKHR) -> Self {
+        match surface_format.colorSpace {
+            ffi::VkColorSpaceKHR_VK_COLOR_SPACE_HDR10_ST2084_EXT => OutputEncoding::Pq,
+            ffi::VkColorSpaceKHR_VK_COLOR_SPACE_EXTENDED_SRGB_LINEAR_EXT => OutputEncoding::ScRgb,
+            _ => match surface_format.format {
+                ffi::VkFormat_VK_FORMAT_B8G8R8A8_SRGB
+                | ffi::VkFormat_VK_FORMAT_R8G8B8A8_SRGB
+                | ffi::VkFormat_VK_FORMAT_A8B8G8R8_SRGB_PACK32 => OutputEncoding::Linear,
+                _ => OutputEncoding::Srgb,
+            },
+        }
+    }
+}
+
+/// Swapchain objects replaced by `recreate_swap_chain` that may still be used by frames in
+/// flight or by the presentation engine.
+pub struct RetiredSwapchain {
+    pub swap_chain: ffi::VkSwapchainKHR,
+    pub image_views: Vec<ffi::VkImageView>,
+    pub framebuffers: Vec<ffi::VkFramebuffer>,
+}
+
+impl RetiredSwapchain {
+    pub fn destroy(self, device: ffi::VkDevice) {
+        unsafe {
+            for framebuffer in self.framebuffers {
+                ffi::vkDestroyFramebuffer(device, framebuffer, std::ptr::null());
+            }
+            for view in self.image_views {
+                ffi::vkDestroyImageView(device, view, std::ptr::null());
+            }
+            if !self.swap_chain.is_null() {
+                ffi::vkDestroySwapchainKHR(device, self.swap_chain, std::ptr::null());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn present_mode_fallback() {
+        let fifo_only = [ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_KHR];
+        let all = [
+            ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_KHR,
+            ffi::VkPresentModeKHR_VK_PRESENT_MODE_MAILBOX_KHR,
+            ffi::VkPresentModeKHR_VK_PRESENT_MODE_IMMEDIATE_KHR,
+            ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_RELAXED_KHR,
+        ];
+
+        assert_eq!(
+            PresentModePolicy::LowLatency.choose(&all),
+            ffi::VkPresentModeKHR_VK_PRESENT_MODE_MAILBOX_KHR
+        );
+        assert_eq!(
+            PresentModePolicy::Immediate.choose(&all),
+            ffi::VkPresentModeKHR_VK_PRESENT_MODE_IMMEDIATE_KHR
+        );
+        assert_eq!(
+            PresentModePolicy::Immediate.choose(&[
+                ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_KHR,
+                ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_RELAXED_KHR,
+            ]),
+            ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_RELAXED_KHR
+        );
+        assert_eq!(
+            PresentModePolicy::LowLatency.choose(&[
+                ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_RELAXED_KHR,
+                ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_KHR,
+            ]),
+            ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_KHR
+        );
+        for policy in [
+            PresentModePolicy::Vsync,
+            PresentModePolicy::LowLatency,
+            PresentModePolicy::Immediate,
+            PresentModePolicy::Relaxed,
+        ] {
+            assert_eq!(
+                policy.choose(&fifo_only),
+                ffi::VkPresentModeKHR_VK_PRESENT_MODE_FIFO_KHR
+            );
+            assert_eq!(policy.name().parse::<PresentModePolicy>(), Ok(policy));
+        }
+    }
+
+    #[test]
+    fn vsync_toggle() {
+        use PresentModePolicy::*;
+
+        assert_eq!(Vsync.toggle_vsync(Immediate), Immediate);
+        assert_eq!(Immediate.toggle_vsync(Immediate), Vsync);
+        assert_eq!(Vsync.toggle_vsync(Vsync), LowLatency);
+        assert_eq!(LowLatency.toggle_vsync(Vsync), Vsync);
+        assert!("tearing".parse::<PresentModePolicy>().is_err());
+    }
+
+    fn surface_format(
+        format: ffi::VkFormat,
+        color_space: ffi::VkColorSpaceKHR,
+    ) -> ffi::VkSurfaceFormatKHR {
+        ffi::VkSurfaceFormatKHR {
+            format,
+            colorSpace: color_space,
+        }
+    }
+
+    #[test]
+    fn surface_format_preference() {
+        let available = [
+            surface_format(
+                ffi::VkFormat_VK_FORMAT_B8G8R8A8_UNORM,
+                ffi::VkColorSpaceKHR_VK_COLOR_SPACE_SRGB_NONLINEAR_KHR,
+            ),
+            surface_format(
+                ffi::VkFormat_VK_FORMAT_B8G8R8A8_SRGB,
+                ffi::VkColorSpaceKHR_VK_COLOR_SPACE_SRGB_NONLINEAR_KHR,
+            ),
+            surface_format(
+                ffi::VkFormat_VK_FORMAT_A2B10G10R10_UNORM_PACK32,
+                ffi::VkColorSpaceKHR_VK_COLOR_SPACE_SRGB_NONLINEAR_KHR,
+            ),
+            surface_format(
+                ffi::VkFormat_VK_FORMAT_A2B10G10R10_UNORM_PACK32,
+                ffi::VkColorSpaceKHR_VK_COLOR_SPACE_HDR10_ST2084_EXT,
+            ),
+        ];
+
+        assert_eq!(SurfaceFormatPreference::Sdr.choose(&available), Some(1));
+        assert_eq!(SurfaceFormatPreference::TenBit.choose(&available), Some(2));
+        assert_eq!(SurfaceFormatPreference::Hdr10.choose(&available), Some(3));
+        // No scRGB, so it falls back to HDR10.
+        assert_eq!(SurfaceFormatPreference::ScRgb.choose(&available), Some(3));
+        // Nothing preferred, so the first reported format is used.
+        assert_eq!(
+            SurfaceFormatPreference::Hdr10.choose(&[surface_format(
+                ffi::VkFormat_VK_FORMAT_R5G6B5_UNORM_PACK16,
+                ffi::VkColorSpaceKHR_VK_COLOR_SPACE_SRGB_NONLINEAR_KHR,
+            )]),
+            Some(0)
+        );
+        assert_eq!(SurfaceFormatPreference::Sdr.choose(&[]), None);
+
+        assert_eq!(
+            OutputEncoding::for_surface_format(&available[0]),
+            OutputEncoding::Srgb
+        );
+        assert_eq!(
+            OutputEncoding::for_surface_format(&available[1]),
+            OutputEncoding::Linear
+        );
+        assert_eq!(
+            OutputEncoding::for_surface_format(&available[3]),
+            OutputEncoding::Pq
+        );
+    }
+}
@@ -0,0 +1,296 @@
+use crate::ffi;
+use crate::image::AllocatedImage;
+use crate::swapchain::RetiredSwapchain;
+
+/// Host side bookkeeping of a timeline: which values were handed out, which one the GPU is
+/// known to have reached, and what to do once it reaches the others.
+pub struct TimelineState<T> {
+    last_submitted: u64,
+    completed: u64,
+    pending: Vec<(u64, T)>,
+}
+
+impl<T> TimelineState<T> {
+    pub fn new() -> Self {
+        Self {
+            last_submitted: 0,
+            completed: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    /// The value the next submission should signal. Only counts as submitted once
+    /// `mark_submitted` was called, so a failed submit doesn't leave a value nothing signals.
+    pub fn next_value(&self) -> u64 {
+        self.last_submitted + 1
+    }
+
+    pub fn mark_submitted(&mut self, value: u64) {
+        self.last_submitted = self.last_submitted.max(value);
+    }
+
+    pub fn last_submitted(&self) -> u64 {
+        self.last_submitted
+    }
+
+    #[allow(dead_code)]
+    pub fn completed(&self) -> u64 {
+        self.completed
+    }
+
+    pub fn is_complete(&self, value: u64) -> bool {
+        value <= self.completed
+    }
+
+    /// Keeps `item` alive until the GPU has reached `value`.
+    pub fn defer(&mut self, value: u64, item: T) {
+        self.pending.push((value, item));
+    }
+
+    /// Records that the GPU reached `completed` and returns everything that was waiting for it.
+    /// The counter never goes backwards, so older values are ignored.
+    pub fn advance(&mut self, completed: u64) -> Vec<T> {
+        self.completed = self.completed.max(completed);
+
+        let (ready, pending): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|(value, _)| *value <= self.completed);
+        self.pending = pending;
+
+        ready.into_iter().map(|(_, item)| item).collect()
+    }
+
+    pub fn drain(&mut self) -> Vec<T> {
+        self.pending.drain(..).map(|(_, item)| item).collect()
+    }
+
+    #[allow(dead_code)]
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+impl<T> Default for TimelineState<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Something the GPU may still be using when the host is done with it.
+pub enum Release {
+    Buffer(ffi::VkBuffer, ffi::VkDeviceMemory),
+    CommandBuffer(ffi::VkCommandPool, ffi::VkCommandBuffer),
+    Framebuffer(ffi::VkFramebuffer),
+    Image(AllocatedImage),
+    Swapchain(RetiredSwapchain),
+}
+
+impl Release {
+    pub fn destroy(self, device: ffi::VkDevice) {
+        unsafe {
+            match self {
+                Release::Buffer(buffer, memory) => {
+                    ffi::vkDestroyBuffer(device, buffer, std::ptr::null());
+                    ffi::vkFreeMemory(device, memory, std::ptr::null());
+                }
+                Release::CommandBuffer(pool, command_buffer) => {
+                    ffi::vkFreeCommandBuffers(device, pool, 1, std::ptr::addr_of!(command_buffer));
+                }
+                Release::Framebuffer(framebuffer) => {
+                    ffi::vkDestroyFramebuffer(device, framebuffer, std::ptr::null());
+                }
+                Release::Image(image) => image.destroy(device),
+                Release::Swapchain(retired) => retired.destroy(device),
+            }
+        }
+    }
+}
+
+/// Orders every submission on a single timeline semaphore. Each submission signals a new,
+/// larger value, so waiting for a value also waits for everything submitted before it.
+pub struct TimelineScheduler {
+    pub semaphore: ffi::VkSemaphore,
+    state: TimelineState<Release>,
+}
+
+impl TimelineScheduler {
+    pub fn new() -> Self {
+        Self {
+            semaphore: std::ptr::null_mut(),
+            state: TimelineState::new(),
+        }
+    }
+
+    pub fn create_semaphore(&mut self, device: ffi::VkDevice) -> Result<(), String> {
+        let mut type_info: ffi::VkSemaphoreTypeCreateInfo = unsafe { std::mem::zeroed() };
+        type_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_SEMAPHORE_TYPE_CREATE_INFO;
+        type_info.semaphoreType = ffi::VkSemaphoreType_VK_SEMAPHORE_TYPE_TIMELINE;
+        type_info.initialValue = 0;
+
+        let mut semaphore_info: ffi::VkSemaphoreCreateInfo = unsafe { std::mem::zeroed() };
+        semaphore_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_SEMAPHORE_CREATE_INFO;
+        semaphore_info.pNext = std::ptr::addr_of!(type_info) as *const std::ffi::c_void;
+
+        let result = unsafe {
+            ffi::vkCreateSemaphore(
+                device,
+                std::ptr::addr_of!(semaphore_info),
+                std::ptr::null(),
+                std::ptr::addr_of_mut!(self.semaphore),
+            )
+        };
+        if result != ffi::VkResult_VK_SUCCESS {
+            return Err(String::from("Failed to create timeline semaphore!"));
+        }
+
+        Ok(())
+    }
+
+    pub fn next_value(&self) -> u64 {
+        self.state.next_value()
+    }
+
+    /// Call once the submission signaling `value` succeeded.
+    pub fn mark_submitted(&mut self, value: u64) {
+        self.state.mark_submitted(value);
+    }
+
+    pub fn last_submitted(&self) -> u64 {
+        self.state.last_submitted()
+    }
+
+    pub fn defer_release(&mut self, value: u64, release: Release) {
+        self.state.defer(value, release);
+    }
+
+    /// Polls the semaphore without blocking.
+    #[allow(dead_code)]
+    pub fn is_complete(&mut self, device: ffi::VkDevice, value: u64) -> Result<bool, String> {
+        if self.state.is_complete(value) {
+            return Ok(true);
+        }
+        self.poll(device)?;
+        Ok(self.state.is_complete(value))
+    }
+
+    /// Returns false if `timeout_ns` passed before the GPU reached `value`.
+    pub fn wait(
+        &mut self,
+        device: ffi::VkDevice,
+        value: u64,
+        timeout_ns: u64,
+    ) -> Result<bool, String> {
+        if self.state.is_complete(value) {
+            return Ok(true);
+        }
+
+        let mut wait_info: ffi::VkSemaphoreWaitInfo = unsafe { std::mem::zeroed() };
+        wait_info.sType = ffi::VkStructureType_VK_STRUCTURE_TYPE_SEMAPHORE_WAIT_INFO;
+        wait_info.semaphoreCount = 1;
+        wait_info.pSemaphores = std::ptr::addr_of!(self.semaphore);
+        wait_info.pValues = std::ptr::addr_of!(value);
+
+        match unsafe { ffi::vkWaitSemaphores(device, std::ptr::addr_of!(wait_info), timeout_ns) } {
+            ffi::VkResult_VK_SUCCESS => {
+                self.release_completed(device, value);
+                Ok(true)
+            }
+            ffi::VkResult_VK_TIMEOUT => Ok(false),
+            _ => Err(String::from("Failed to wait for timeline semaphore!")),
+        }
+    }
+
+    /// Reads the current counter value and destroys everything the GPU is done with.
+    #[allow(dead_code)]
+    pub fn poll(&mut self, device: ffi::VkDevice) -> Result<u64, String> {
+        let mut value: u64 = 0;
+        let result = unsafe {
+            ffi::vkGetSemaphoreCounterValue(device, self.semaphore, std::ptr::addr_of_mut!(value))
+        };
+        if result != ffi::VkResult_VK_SUCCESS {
+            return Err(String::from("Failed to read timeline semaphore value!"));
+        }
+
+        self.release_completed(device, value);
+        Ok(value)
+    }
+
+    fn release_completed(&mut self, device: ffi::VkDevice, completed: u64) {
+        for release in self.state.advance(completed) {
+            release.destroy(device);
+        }
+    }
+
+    /// Only call once the device is idle.
+    pub fn destroy(&mut self, device: ffi::VkDevice) {
+        for release in self.state.drain() {
+            release.destroy(device);
+        }
+        if !self.semaphore.is_null() {
+            unsafe {
+                ffi::vkDestroySemaphore(device, self.semaphore, std::ptr::null());
+            }
+            self.semaphore = std::ptr::null_mut();
+        }
+    }
+}
+
+impl Default for TimelineScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_increase_monotonically() {
+        let mut state: TimelineState<()> = TimelineState::new();
+        assert_eq!(state.last_submitted(), 0);
+        assert!(state.is_complete(0));
+
+        let first = state.next_value();
+        state.mark_submitted(first);
+        let second = state.next_value();
+        assert!(second > first);
+
+        // Until the submit succeeded, the value is handed out again.
+        assert_eq!(state.last_submitted(), first);
+        assert_eq!(state.next_value(), second);
+        state.mark_submitted(second);
+        assert_eq!(state.last_submitted(), second);
+        assert!(!state.is_complete(first));
+
+        state.advance(first);
+        assert!(state.is_complete(first));
+        assert!(!state.is_complete(second));
+
+        // A stale read doesn't move the counter back.
+        state.advance(0);
+        assert_eq!(state.completed(), first);
+    }
+
+    #[test]
+    fn releases_wait_for_their_value() {
+        let mut state = TimelineState::new();
+        let upload = state.next_value();
+        state.mark_submitted(upload);
+        let frame = state.next_value();
+        state.mark_submitted(frame);
+        state.defer(upload, "staging");
+        state.defer(frame, "old buffer");
+        state.defer(upload, "upload commands");
+
+        assert!(state.advance(0).is_empty());
+        assert_eq!(state.advance(upload), vec!["staging", "upload commands"]);
+        assert_eq!(state.pending_count(), 1);
+        assert_eq!(state.advance(frame + 5), vec!["old buffer"]);
+        assert_eq!(state.pending_count(), 0);
+
+        let value = state.next_value();
+        state.defer(value, "leftover");
+        assert_eq!(state.drain(), vec!["leftover"]);
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Longest delta handed to the simulation, so a stall (dragging the window, a breakpoint) doesn't
+/// turn into one giant step.
+pub const MAX_DELTA_TIME: f32 = 0.25;
+
+/// Most fixed steps run in one frame before the remaining time is dropped.
+pub const MAX_FIXED_STEPS: u32 = 8;
+
+/// Frames kept by `FrameStats`.
+pub const FRAME_STATS_WINDOW: usize = 240;
+
+pub struct FrameClock {
+    start: Instant,
+    last: Instant,
+    frame_time: Duration,
+    delta_time: f32,
+    frame_count: u64,
+}
+
+impl FrameClock {
+    pub fn new() -> Self {
+        Self::starting_at(Instant::now())
+    }
+
+    pub fn starting_at(now: Instant) -> Self {
+        Self {
+            start: now,
+            last: now,
+            frame_time: Duration::ZERO,
+            delta_time: 0.0,
+            frame_count: 0,
+        }
+    }
+
+    /// Starts a new frame and returns its delta time in seconds.
+    pub fn tick(&mut self) -> f32 {
+        self.tick_at(Instant::now())
+    }
+
+    pub fn tick_at(&mut self, now: Instant) -> f32 {
+        self.frame_time = now.saturating_duration_since(self.last);
+        self.delta_time = self.frame_time.as_secs_f32().min(MAX_DELTA_TIME);
+        self.last = now;
+        self.frame_count += 1;
+
+        self.delta_time
+    }
+
+    /// Unclamped time between the last two ticks.
+    pub fn frame_time(&self) -> Duration {
+        self.frame_time
+    }
+
+    #[allow(dead_code)]
+    pub fn delta_time(&self) -> f32 {
+        self.delta_time
+    }
+
+    /// Seconds from the start to the last tick, so it stays the same for the whole frame.
+    pub fn elapsed(&self) -> f32 {
+        self.last.duration_since(self.start).as_secs_f32()
+    }
+
+    #[allow(dead_code)]
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+}
+
+/// Splits variable frame deltas into whole steps of a fixed length.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FixedTimestep {
+    step: f32,
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    pub fn new(rate_hz: f32) -> Self {
+        Self {
+            step: 1.0 / rate_hz,
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Returns how many steps to run for a frame that took `delta_time`.
+    pub fn advance(&mut self, delta_time: f32) -> u32 {
+        self.accumulator += delta_time;
+        let steps = (self.accumulator / self.step).floor() as u32;
+        if steps > MAX_FIXED_STEPS {
+            // Falling behind, catching up would only make the next frame slower.
+            self.accumulator = 0.0;
+            return MAX_FIXED_STEPS;
+        }
+        self.accumulator -= steps as f32 * self.step;
+
+        steps
+    }
+
+    /// How far into the next step the leftover time is, for interpolating between steps.
+    #[allow(dead_code)]
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.step
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FrameTimeSummary {
+    pub frames: usize,
+    pub min_ms: f32,
+    pub avg_ms: f32,
+    pub max_ms: f32,
+    pub p50_ms: f32,
+    pub p95_ms: f32,
+    pub p99_ms: f32,
+}
+
+impl FrameTimeSummary {
+    pub fn fps(&self) -> f32 {
+        if self.avg_ms > 0.0 {
+            1000.0 / self.avg_ms
+        } else {
+            0.0
+        }
+    }
+}
+
+impl std::fmt::Display for FrameTimeSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.0} fps, {:.2} ms (min {:.2}, p50 {:.2}, p95 {:.2}, p99 {:.2}, max {:.2})",
+            self.fps(),
+            self.avg_ms,
+            self.min_ms,
+            self.p50_ms,
+            self.p95_ms,
+            self.p99_ms,
+            self.max_ms
+        )
+    }
+}
+
+/// Frame times over a sliding window of the most recent frames.
+pub struct FrameStats {
+    samples_ms: VecDeque<f32>,
+    capacity: usize,
+}
+
+impl FrameStats {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples_ms: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn record(&mut self, frame_time: Duration) {
+        if self.samples_ms.len() == self.capacity {
+            self.samples_ms.pop_front();
+        }
+        self.samples_ms.push_back(frame_time.as_secs_f32() * 1000.0);
+    }
+
+    pub fn summary(&self) -> Option<FrameTimeSummary> {
+        if self.samples_ms.is_empty() {
+            return None;
+        }
+
+        let mut sorted: Vec<f32> = self.samples_ms.iter().copied().collect();
+        sorted.sort_by(f32::total_cmp);
+
+        Some(FrameTimeSummary {
+            frames: sorted.len(),
+            min_ms: sorted[0],
+            avg_ms: sorted.iter().sum::<f32>() / sorted.len() as f32,
+            max_ms: sorted[sorted.len() - 1],
+            p50_ms: percentile(&sorted, 50.0),
+            p95_ms: percentile(&sorted, 95.0),
+            p99_ms: percentile(&sorted, 99.0),
+        })
+    }
+}
+
+/// Nearest-rank percentile of an ascending, non-empty slice.
+pub fn percentile(sorted: &[f32], percent: f32) -> f32 {
+    let rank = (percent / 100.0 * sorted.len() as f32).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Sleeps away the rest of each frame to stay under a frame rate cap.
+pub struct FrameLimiter {
+    period: Option<Duration>,
+    next_frame: Option<Instant>,
+}
+
+impl FrameLimiter {
+    /// `None` doesn't limit anything.
+    pub fn new(fps_cap: Option<f32>) -> Self {
+        Self {
+            period: fps_cap.map(|fps| Duration::from_secs_f32(1.0 / fps)),
+            next_frame: None,
+        }
+    }
+
+    pub fn wait(&mut self) {
+        let delay = self.delay_at(Instant::now());
+        if !delay.is_zero() {
+            std::thread::sleep(delay);
+        }
+    }
+
+    /// How long to wait at `now` before starting the next frame. Frames are scheduled a period
+    /// apart, so a short frame can make up for a long one, but never by more than one period.
+    pub fn delay_at(&mut self, now: Instant) -> Duration {
+        let Some(period) = self.period else {
+            return Duration::ZERO;
+        };
+
+        let deadline = match self.next_frame {
+            Some(next) if next + period >= now => next,
+            _ => now,
+        };
+        self.next_frame = Some(deadline + period);
+
+        deadline.saturating_duration_since(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(value: u64) -> Duration {
+        Duration::from_millis(value)
+    }
+
+    #[test]
+    fn frame_clock() {
+        let start = Instant::now();
+        let mut clock = FrameClock::starting_at(start);
+
+        assert!((clock.tick_at(start + ms(16)) - 0.016).abs() < 1e-6);
+        assert_eq!(clock.frame_time(), ms(16));
+        assert!((clock.elapsed() - 0.016).abs() < 1e-6);
+
+        // Long stalls are clamped for the simulation but reported as they were.
+        assert_eq!(clock.tick_at(start + ms(2016)), MAX_DELTA_TIME);
+        assert_eq!(clock.frame_time(), ms(2000));
+        assert_eq!(clock.frame_count(), 2);
+    }
+
+    #[test]
+    fn fixed_timestep() {
+        let mut fixed = FixedTimestep::new(100.0);
+
+        assert_eq!(fixed.advance(0.025), 2);
+        assert!((fixed.alpha() - 0.5).abs() < 1e-3);
+        assert_eq!(fixed.advance(0.006), 1);
+        assert_eq!(fixed.advance(0.004), 0);
+
+        assert_eq!(fixed.advance(1.0), MAX_FIXED_STEPS);
+        assert_eq!(fixed.alpha(), 0.0);
+    }
+
+    #[test]
+    fn frame_stats() {
+        let mut stats = FrameStats::new(100);
+        assert_eq!(stats.summary(), None);
+
+        // The first sample falls out of the window.
+        stats.record(ms(1000));
+        for value in 1..=100 {
+            stats.record(ms(value));
+        }
+
+        let summary = stats.summary().unwrap();
+        assert_eq!(summary.frames, 100);
+        assert_eq!(summary.min_ms, 1.0);
+        assert_eq!(summary.max_ms, 100.0);
+        assert!((summary.avg_ms - 50.5).abs() < 1e-3);
+        assert_eq!(summary.p50_ms, 50.0);
+        assert_eq!(summary.p95_ms, 95.0);
+        assert_eq!(summary.p99_ms, 99.0);
+        assert!((summary.fps() - 19.8).abs() < 0.01);
+
+        assert_eq!(percentile(&[4.0], 99.0), 4.0);
+        assert_eq!(percentile(&[1.0, 2.0, 3.0], 0.0), 1.0);
+    }
+
+    #[test]
+    fn frame_limiter() {
+        let start = Instant::now();
+
+        let mut uncapped = FrameLimiter::new(None);
+        assert_eq!(uncapped.delay_at(start), Duration::ZERO);
+
+        let mut limiter = FrameLimiter::new(Some(50.0));
+        assert_eq!(limiter.delay_at(start), Duration::ZERO);
+        // A 5 ms frame waits out the rest of the 20 ms period.
+        assert_eq!(limiter.delay_at(start + ms(5)), ms(15));
+        // A 30 ms frame is late, the next one gets the time back.
+        assert_eq!(limiter.delay_at(start + ms(50)), Duration::ZERO);
+        assert_eq!(limiter.delay_at(start + ms(55)), ms(5));
+        // Far behind, the schedule restarts instead of bursting.
+        assert_eq!(limiter.delay_at(start + ms(500)), Duration::ZERO);
+        assert_eq!(limiter.delay_at(start + ms(505)), ms(15));
+    }
+}
@@ -0,0 +1,495 @@
+use std::ffi::CStr;
+use std::sync::Mutex;
+
+use crate::debug_utils::object_type_name;
+use crate::ffi;
+use crate::logger::{self, Level};
+
+pub const LOG_TARGET: &str = "validation";
+/// Output of `debugPrintfEXT` in shaders.
+pub const PRINTF_TARGET: &str = "shader";
+
+/// Opt-in checks of `VK_EXT_validation_features`, on top of the default validation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ValidationFeature {
+    /// Instruments shaders to catch out of bounds descriptor and buffer accesses.
+    GpuAssisted,
+    Synchronization,
+    BestPractices,
+    /// Forwards `debugPrintfEXT` output to the logger.
+    DebugPrintf,
+}
+
+impl ValidationFeature {
+    pub fn name(self) -> &'static str {
+        match self {
+            ValidationFeature::GpuAssisted => "gpu-assisted",
+            ValidationFeature::Synchronization => "sync",
+            ValidationFeature::BestPractices => "best-practices",
+            ValidationFeature::DebugPrintf => "debug-printf",
+        }
+    }
+
+    fn enables(self) -> &'static [ffi::VkValidationFeatureEnableEXT] {
+        match self {
+            ValidationFeature::GpuAssisted => &[
+                ffi::VkValidationFeatureEnableEXT_VK_VALIDATION_FEATURE_ENABLE_GPU_ASSISTED_EXT,
+                ffi::VkValidationFeatureEnableEXT_VK_VALIDATION_FEATURE_ENABLE_GPU_ASSISTED_RESERVE_BINDING_SLOT_EXT,
+            ],
+            ValidationFeature::Synchronization => &[
+                ffi::VkValidationFeatureEnableEXT_VK_VALIDATION_FEATURE_ENABLE_SYNCHRONIZATION_VALIDATION_EXT,
+            ],
+            ValidationFeature::BestPractices => &[
+                ffi::VkValidationFeatureEnableEXT_VK_VALIDATION_FEATURE_ENABLE_BEST_PRACTICES_EXT,
+            ],
+            ValidationFeature::DebugPrintf => &[
+                ffi::VkValidationFeatureEnableEXT_VK_VALIDATION_FEATURE_ENABLE_DEBUG_PRINTF_EXT,
+            ],
+        }
+    }
+}
+
+impl std::str::FromStr for ValidationFeature {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "gpu-assisted" | "gpu" => Ok(ValidationFeature::GpuAssisted),
+            "sync" | "synchronization" => Ok(ValidationFeature::Synchronization),
+            "best-practices" => Ok(ValidationFeature::BestPractices),
+            "debug-printf" | "printf" => Ok(ValidationFeature::DebugPrintf),
+            _ => Err(format!(
+                "Unknown validation feature \"{}\", expected gpu-assisted, sync, best-practices \
+                 or debug-printf",
+                s
+            )),
+        }
+    }
+}
+
+/// Parses a comma separated list of features, rejecting combinations the layer can't run
+/// together.
+pub fn parse_features(value: &str) -> Result<Vec<ValidationFeature>, String> {
+    let mut features = Vec::new();
+    for name in value.split(',').filter(|name| !name.trim().is_empty()) {
+        let feature = name.parse()?;
+        if !features.contains(&feature) {
+            features.push(feature);
+        }
+    }
+
+    if features.contains(&ValidationFeature::GpuAssisted)
+        && features.contains(&ValidationFeature::DebugPrintf)
+    {
+        return Err(String::from(
+            "gpu-assisted validation and debug-printf can't be enabled together",
+        ));
+    }
+
+    Ok(features)
+}
+
+/// Values for `VkValidationFeaturesEXT::pEnabledValidationFeatures`.
+pub fn feature_enables(features: &[ValidationFeature]) -> Vec<ffi::VkValidationFeatureEnableEXT> {
+    features
+        .iter()
+        .flat_map(|feature| feature.enables().iter().copied())
+        .collect()
+}
+
+fn is_debug_printf(message: &ValidationMessage) -> bool {
+    message.id_name.contains("DEBUG-PRINTF")
+}
+
+/// Older layers prefix the printed text with the objects and message ID, up to
+/// `MessageID = 0x... | `. The text itself may contain `|` as well.
+fn printf_text(message: &str) -> &str {
+    let text = message
+        .split_once("MessageID = ")
+        .and_then(|(_, rest)| rest.split_once(" | "))
+        .map_or(message, |(_, text)| text);
+    text.trim()
+}
+
+/// What happens after a validation error was logged.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ErrorAction {
+    #[default]
+    Log,
+    /// Keeps the errors so they can be inspected, e.g. by tests.
+    Record,
+    /// Panics at the end of the frame that caused the first error. Panicking inside the
+    /// callback would abort, since it can't unwind into the driver.
+    Panic,
+}
+
+impl std::str::FromStr for ErrorAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "log" => Ok(ErrorAction::Log),
+            "record" => Ok(ErrorAction::Record),
+            "panic" => Ok(ErrorAction::Panic),
+            _ => Err(format!(
+                "Unknown validation error action \"{}\", expected log, record or panic",
+                s
+            )),
+        }
+    }
+}
+
+pub fn level_from_severity(severity: ffi::VkDebugUtilsMessageSeverityFlagBitsEXT) -> Level {
+    if severity >= ffi::VkDebugUtilsMessageSeverityFlagBitsEXT_VK_DEBUG_UTILS_MESSAGE_SEVERITY_ERROR_BIT_EXT {
+        Level::Error
+    } else if severity
+        >= ffi::VkDebugUtilsMessageSeverityFlagBitsEXT_VK_DEBUG_UTILS_MESSAGE_SEVERITY_WARNING_BIT_EXT
+    {
+        Level::Warn
+    } else if severity
+        >= ffi::VkDebugUtilsMessageSeverityFlagBitsEXT_VK_DEBUG_UTILS_MESSAGE_SEVERITY_INFO_BIT_EXT
+    {
+        Level::Info
+    } else {
+        Level::Debug
+    }
+}
+
+/// Severities worth subscribing to when messages below `min_level` are dropped anyway.
+pub fn severity_flags(min_level: Level) -> ffi::VkDebugUtilsMessageSeverityFlagsEXT {
+    [
+        ffi::VkDebugUtilsMessageSeverityFlagBitsEXT_VK_DEBUG_UTILS_MESSAGE_SEVERITY_VERBOSE_BIT_EXT,
+        ffi::VkDebugUtilsMessageSeverityFlagBitsEXT_VK_DEBUG_UTILS_MESSAGE_SEVERITY_INFO_BIT_EXT,
+        ffi::VkDebugUtilsMessageSeverityFlagBitsEXT_VK_DEBUG_UTILS_MESSAGE_SEVERITY_WARNING_BIT_EXT,
+        ffi::VkDebugUtilsMessageSeverityFlagBitsEXT_VK_DEBUG_UTILS_MESSAGE_SEVERITY_ERROR_BIT_EXT,
+    ]
+    .into_iter()
+    .filter(|&severity| level_from_severity(severity) >= min_level)
+    .fold(0, |flags, severity| flags | severity)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationMessage {
+    pub level: Level,
+    /// e.g. "VUID-vkCmdDraw-None-02859", empty for messages without one.
+    pub id_name: String,
+    pub id_number: i32,
+    pub message: String,
+    /// The objects involved, with their debug names when they have one.
+    pub objects: Vec<String>,
+}
+
+impl ValidationMessage {
+    /// # Safety
+    /// `data` has to be the callback data passed to a debug utils messenger callback.
+    unsafe fn from_callback_data(
+        severity: ffi::VkDebugUtilsMessageSeverityFlagBitsEXT,
+        data: &ffi::VkDebugUtilsMessengerCallbackDataEXT,
+    ) -> Self {
+        let string = |ptr: *const i8| {
+            if ptr.is_null() {
+                String::new()
+            } else {
+                CStr::from_ptr(ptr).to_string_lossy().into_owned()
+            }
+        };
+
+        let objects = if data.pObjects.is_null() {
+            &[][..]
+        } else {
+            std::slice::from_raw_parts(data.pObjects, data.objectCount as usize)
+        };
+
+        Self {
+            level: level_from_severity(severity),
+            id_name: string(data.pMessageIdName),
+            id_number: data.messageIdNumber,
+            message: string(data.pMessage),
+            objects: objects
+                .iter()
+                .map(|object| {
+                    let mut description = format!(
+                        "{} {:#x}",
+                        object_type_name(object.objectType),
+                        object.objectHandle
+                    );
+                    if !object.pObjectName.is_null() {
+                        description += &format!(" \"{}\"", string(object.pObjectName));
+                    }
+                    description
+                })
+                .collect(),
+        }
+    }
+
+    /// `id` is either a message ID name or its number, in decimal or 0x prefixed hex.
+    pub fn matches_id(&self, id: &str) -> bool {
+        let id = id.trim();
+        if !self.id_name.is_empty() && id == self.id_name {
+            return true;
+        }
+
+        let number = match id.strip_prefix("0x").or_else(|| id.strip_prefix("0X")) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok().map(|n| n as i32),
+            None => id.parse::<i32>().ok(),
+        };
+        number == Some(self.id_number)
+    }
+}
+
+impl std::fmt::Display for ValidationMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.id_name.is_empty() {
+            write!(f, "{} ({:#x}): ", self.id_name, self.id_number as u32)?;
+        }
+        f.write_str(&self.message)?;
+        for object in &self.objects {
+            write!(f, "\n    {}", object)?;
+        }
+        Ok(())
+    }
+}
+
+/// Filters messenger callbacks and forwards them to the logger. Has to stay at the same
+/// address while a messenger uses it, since it is the callback's user data.
+pub struct ValidationLog {
+    suppressed: Vec<String>,
+    on_error: ErrorAction,
+    errors: Mutex<RecordedErrors>,
+}
+
+/// How many errors are kept, later ones are only counted.
+pub const MAX_RECORDED_ERRORS: usize = 64;
+
+#[derive(Default)]
+struct RecordedErrors {
+    count: usize,
+    first: Vec<ValidationMessage>,
+}
+
+impl ValidationLog {
+    pub fn new(suppressed: Vec<String>, on_error: ErrorAction) -> Self {
+        Self {
+            suppressed,
+            on_error,
+            errors: Mutex::new(RecordedErrors::default()),
+        }
+    }
+
+    pub fn is_suppressed(&self, message: &ValidationMessage) -> bool {
+        self.suppressed.iter().any(|id| message.matches_id(id))
+    }
+
+    pub fn handle(&self, message: ValidationMessage) {
+        if self.is_suppressed(&message) {
+            return;
+        }
+
+        if is_debug_printf(&message) {
+            logger::log(Level::Info, PRINTF_TARGET, printf_text(&message.message));
+            return;
+        }
+
+        logger::log(message.level, LOG_TARGET, &message.to_string());
+        if message.level == Level::Error && self.on_error != ErrorAction::Log {
+            let mut errors = self.errors.lock().unwrap();
+            errors.count += 1;
+            if errors.first.len() < MAX_RECORDED_ERRORS {
+                errors.first.push(message);
+            }
+        }
+    }
+
+    /// The first `MAX_RECORDED_ERRORS` errors, only with `ErrorAction::Record` or
+    /// `ErrorAction::Panic`.
+    #[allow(dead_code)]
+    pub fn errors(&self) -> Vec<ValidationMessage> {
+        self.errors.lock().unwrap().first.clone()
+    }
+
+    /// Every error reported so far, including those past `MAX_RECORDED_ERRORS`.
+    pub fn error_count(&self) -> usize {
+        self.errors.lock().unwrap().count
+    }
+
+    /// Panics if an error was reported and errors should panic.
+    pub fn check(&self) {
+        if self.on_error == ErrorAction::Panic {
+            if let Some(error) = self.errors.lock().unwrap().first.first() {
+                panic!("Validation error: {}", error);
+            }
+        }
+    }
+
+    pub fn messenger_create_info(&self) -> ffi::VkDebugUtilsMessengerCreateInfoEXT {
+        ffi::VkDebugUtilsMessengerCreateInfoEXT {
+            sType: ffi::VkStructureType_VK_STRUCTURE_TYPE_DEBUG_UTILS_MESSENGER_CREATE_INFO_EXT,
+            pNext: std::ptr::null(),
+            flags: 0,
+            messageSeverity: severity_flags(logger::min_level()),
+            messageType: ffi::VkDebugUtilsMessageTypeFlagBitsEXT_VK_DEBUG_UTILS_MESSAGE_TYPE_GENERAL_BIT_EXT | ffi::VkDebugUtilsMessageTypeFlagBitsEXT_VK_DEBUG_UTILS_MESSAGE_TYPE_VALIDATION_BIT_EXT | ffi::VkDebugUtilsMessageTypeFlagBitsEXT_VK_DEBUG_UTILS_MESSAGE_TYPE_PERFORMANCE_BIT_EXT,
+            pfnUserCallback: Some(debug_callback),
+            pUserData: self as *const Self as *mut std::ffi::c_void,
+        }
+    }
+}
+
+extern "C" fn debug_callback(
+    message_severity: ffi::VkDebugUtilsMessageSeverityFlagBitsEXT,
+    _message_type: ffi::VkDebugUtilsMessageTypeFlagsEXT,
+    callback_data: *const ffi::VkDebugUtilsMessengerCallbackDataEXT,
+    user_data: *mut std::ffi::c_void,
+) -> u32 {
+    let (log, message) = unsafe {
+        (
+            &*(user_data as *const ValidationLog),
+            ValidationMessage::from_callback_data(message_severity, &*callback_data),
+        )
+    };
+    log.handle(message);
+
+    ffi::VK_FALSE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(level: Level, id_name: &str, id_number: i32) -> ValidationMessage {
+        ValidationMessage {
+            level,
+            id_name: id_name.to_owned(),
+            id_number,
+            message: String::from("something went wrong"),
+            objects: vec![String::from("VkBuffer 0x10 \"vertex buffer\"")],
+        }
+    }
+
+    #[test]
+    fn severity_mapping() {
+        assert_eq!(
+            level_from_severity(
+                ffi::VkDebugUtilsMessageSeverityFlagBitsEXT_VK_DEBUG_UTILS_MESSAGE_SEVERITY_VERBOSE_BIT_EXT
+            ),
+            Level::Debug
+        );
+        assert_eq!(
+            level_from_severity(
+                ffi::VkDebugUtilsMessageSeverityFlagBitsEXT_VK_DEBUG_UTILS_MESSAGE_SEVERITY_ERROR_BIT_EXT
+            ),
+            Level::Error
+        );
+
+        assert_eq!(severity_flags(Level::Debug), 0x1111);
+        assert_eq!(severity_flags(Level::Warn), 0x1100);
+        assert_eq!(severity_flags(Level::Error), 0x1000);
+    }
+
+    #[test]
+    fn message_ids() {
+        let message = message(Level::Error, "VUID-vkCmdDraw-None-02859", 0x4dae5635);
+        assert!(message.matches_id("VUID-vkCmdDraw-None-02859"));
+        assert!(message.matches_id("0x4DAE5635"));
+        assert!(message.matches_id("1303270965"));
+        assert!(!message.matches_id("VUID-vkCmdDraw-None-02860"));
+
+        // Numbers above i32::MAX are reported negative.
+        let negative = self::message(Level::Warn, "", 0x8000_0001u32 as i32);
+        assert!(negative.matches_id("0x80000001"));
+        assert!(!negative.matches_id(""));
+
+        assert_eq!(
+            message.to_string(),
+            "VUID-vkCmdDraw-None-02859 (0x4dae5635): something went wrong\n    \
+             VkBuffer 0x10 \"vertex buffer\""
+        );
+    }
+
+    #[test]
+    fn records_errors() {
+        let log = ValidationLog::new(vec![String::from("0x1")], ErrorAction::Record);
+        log.handle(message(Level::Error, "VUID-a", 1));
+        log.handle(message(Level::Warn, "VUID-b", 2));
+        log.handle(message(Level::Error, "VUID-c", 3));
+
+        let errors = log.errors();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].id_name, "VUID-c");
+        log.check();
+
+        for _ in 0..MAX_RECORDED_ERRORS {
+            log.handle(message(Level::Error, "VUID-d", 4));
+        }
+        assert_eq!(log.errors().len(), MAX_RECORDED_ERRORS);
+        assert_eq!(log.error_count(), MAX_RECORDED_ERRORS + 1);
+
+        let logged = ValidationLog::new(Vec::new(), ErrorAction::Log);
+        logged.handle(message(Level::Error, "VUID-a", 1));
+        assert!(logged.errors().is_empty());
+    }
+
+    #[test]
+    fn validation_features() {
+        assert_eq!(
+            parse_features("sync, best-practices,sync"),
+            Ok(vec![
+                ValidationFeature::Synchronization,
+                ValidationFeature::BestPractices
+            ])
+        );
+        assert_eq!(parse_features(""), Ok(Vec::new()));
+        assert!(parse_features("gpu-assisted,debug-printf").is_err());
+        assert!(parse_features("everything").is_err());
+
+        for feature in [
+            ValidationFeature::GpuAssisted,
+            ValidationFeature::Synchronization,
+            ValidationFeature::BestPractices,
+            ValidationFeature::DebugPrintf,
+        ] {
+            assert_eq!(feature.name().parse(), Ok(feature));
+        }
+        assert_eq!(
+            feature_enables(&[
+                ValidationFeature::GpuAssisted,
+                ValidationFeature::BestPractices
+            ]),
+            [
+                ffi::VkValidationFeatureEnableEXT_VK_VALIDATION_FEATURE_ENABLE_GPU_ASSISTED_EXT,
+                ffi::VkValidationFeatureEnableEXT_VK_VALIDATION_FEATURE_ENABLE_GPU_ASSISTED_RESERVE_BINDING_SLOT_EXT,
+                ffi::VkValidationFeatureEnableEXT_VK_VALIDATION_FEATURE_ENABLE_BEST_PRACTICES_EXT,
+            ]
+        );
+    }
+
+    #[test]
+    fn debug_printf_messages() {
+        let mut printf = message(Level::Info, "WARNING-DEBUG-PRINTF", 0x76589099);
+        printf.message = String::from(
+            "Validation Information: [ WARNING-DEBUG-PRINTF ] Object 0: handle = 0x1 | \
+             MessageID = 0x76589099 | value = 42",
+        );
+        assert!(is_debug_printf(&printf));
+        assert_eq!(printf_text(&printf.message), "value = 42");
+        assert_eq!(printf_text("value = 42\n"), "value = 42");
+        assert_eq!(
+            printf_text("MessageID = 0x76589099 | a = 1 | b = 2"),
+            "a = 1 | b = 2"
+        );
+        assert_eq!(printf_text("a = 1 | b = 2"), "a = 1 | b = 2");
+
+        // Printf output isn't a validation error, even with errors recorded.
+        let log = ValidationLog::new(Vec::new(), ErrorAction::Record);
+        printf.level = Level::Error;
+        log.handle(printf);
+        assert!(log.errors().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "VUID-a")]
+    fn panics_on_errors() {
+        let log = ValidationLog::new(Vec::new(), ErrorAction::Panic);
+        log.handle(message(Level::Error, "VUID-a", 1));
+        log.check();
+    }
+}